@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use resource_mesh_portal_serde::version::latest::bin::Bin;
+use resource_mesh_portal_serde::version::latest::http::{HttpRequest, HttpResponse};
+
+use crate::client::Request;
+
+// routes an HttpRequest by method & path to one of its handlers, answering 404 when no route
+// has the path and 405 when none of the routes that have it take the method.
+// a PortalCtrl hands it every request from http_request():
+//
+//     async fn http_request( &self, request: Request<HttpRequest> ) -> Result<HttpResponse,Error> {
+//         self.router.route(request).await
+//     }
+pub struct HttpRouter {
+    routes: Vec<Route>
+}
+
+struct Route {
+    method: String,
+    pattern: PathPattern,
+    handler: Box<dyn HttpHandler>
+}
+
+impl Default for HttpRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpRouter {
+    pub fn new() -> Self {
+        Self {
+            routes: vec![]
+        }
+    }
+
+    // path segments are literal, a ':name' parameter matching any one segment, or a '*name'
+    // wildcard matching whatever remains of the path.  the first route added that matches wins
+    pub fn with_route( mut self, method: &str, path: &str, handler: impl HttpHandler + 'static ) -> Self {
+        self.routes.push(Route {
+            method: method.to_uppercase(),
+            pattern: PathPattern::new(path),
+            handler: Box::new(handler)
+        });
+        self
+    }
+
+    pub async fn route( &self, request: Request<HttpRequest> ) -> Result<HttpResponse,Error> {
+        let (path, query) = match request.path.split_once('?') {
+            Some((path, query)) => (path.to_string(), query.to_string()),
+            None => (request.path.clone(), String::new())
+        };
+        let method = request.method.to_uppercase();
+
+        let mut allowed = vec![];
+        for route in &self.routes {
+            if let Some(params) = route.pattern.matches(path.as_str()) {
+                if route.method == method {
+                    let call = HttpCall {
+                        request,
+                        params,
+                        query
+                    };
+                    return route.handler.handle(call).await;
+                }
+                if !allowed.contains(&route.method) {
+                    allowed.push(route.method.clone());
+                }
+            }
+        }
+
+        if allowed.is_empty() {
+            Ok(HttpResponse::not_found())
+        } else {
+            let mut response = HttpResponse::new(405);
+            response.headers.insert("Allow".to_string(), allowed.join(", "));
+            Ok(response)
+        }
+    }
+}
+
+#[async_trait]
+pub trait HttpHandler: Sync+Send {
+    async fn handle( &self, call: HttpCall ) -> Result<HttpResponse,Error>;
+}
+
+// so an async fn( HttpCall ) -> Result<HttpResponse,Error> is a handler as it is
+#[async_trait]
+impl<F,FUT> HttpHandler for F where F: Fn(HttpCall) -> FUT + Sync + Send, FUT: Future<Output=Result<HttpResponse,Error>> + Send + 'static {
+    async fn handle( &self, call: HttpCall ) -> Result<HttpResponse,Error> {
+        (self)(call).await
+    }
+}
+
+// a request that matched a route, with what the route's pattern took from its path
+pub struct HttpCall {
+    pub request: Request<HttpRequest>,
+    pub params: HashMap<String,String>,
+    query: String
+}
+
+impl HttpCall {
+    // a ':name' or '*name' from the route's path
+    pub fn param<T: FromStr>( &self, name: &str ) -> Result<T,Error> where T::Err: std::fmt::Display {
+        match self.params.get(name) {
+            Some(value) => value.parse().map_err(|err: T::Err| anyhow!("path parameter '{}': {}", name, err)),
+            None => Err(anyhow!("no path parameter '{}'", name))
+        }
+    }
+
+    // header names are matched regardless of case
+    pub fn header<T: FromStr>( &self, name: &str ) -> Result<Option<T>,Error> where T::Err: std::fmt::Display {
+        match self.request.headers.iter().find(|(key,_)| key.eq_ignore_ascii_case(name)) {
+            Some((_,value)) => Ok(Option::Some(value.parse().map_err(|err: T::Err| anyhow!("header '{}': {}", name, err))?)),
+            None => Ok(Option::None)
+        }
+    }
+
+    pub fn query<T: DeserializeOwned>( &self ) -> Result<T,Error> {
+        Ok(serde_urlencoded::from_str(self.query.as_str())?)
+    }
+
+    pub fn body( &self ) -> Result<&[u8],Error> {
+        match &self.request.body {
+            Some(Bin::Raw(raw)) => Ok(raw.as_slice()),
+            Some(Bin::Src(_)) => Err(anyhow!("a bin src body cannot be read here")),
+            None => Ok(&[])
+        }
+    }
+
+    pub fn json<T: DeserializeOwned>( &self ) -> Result<T,Error> {
+        Ok(serde_json::from_slice(self.body()?)?)
+    }
+}
+
+pub fn json<T: Serialize>( code: usize, value: &T ) -> Result<HttpResponse,Error> {
+    let mut response = HttpResponse::new(code);
+    response.headers.insert("Content-Type".to_string(), "application/json".to_string());
+    response.body = Option::Some(Bin::Raw(Arc::new(serde_json::to_vec(value)?)));
+    Ok(response)
+}
+
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard(String)
+}
+
+struct PathPattern {
+    segments: Vec<Segment>
+}
+
+impl PathPattern {
+    fn new( path: &str ) -> Self {
+        let segments = path.split('/').filter(|segment| !segment.is_empty()).map(|segment| {
+            if let Some(name) = segment.strip_prefix(':') {
+                Segment::Param(name.to_string())
+            } else if let Some(name) = segment.strip_prefix('*') {
+                Segment::Wildcard(name.to_string())
+            } else {
+                Segment::Literal(segment.to_string())
+            }
+        }).collect();
+        Self {
+            segments
+        }
+    }
+
+    fn matches( &self, path: &str ) -> Option<HashMap<String,String>> {
+        let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        let mut params = HashMap::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Wildcard(name) => {
+                    let rest: Vec<String> = parts.iter().skip(index).map(|part| decode(part)).collect();
+                    params.insert(name.clone(), rest.join("/"));
+                    return Option::Some(params);
+                }
+                Segment::Literal(literal) => {
+                    if parts.get(index) != Some(&literal.as_str()) {
+                        return Option::None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), decode(parts.get(index)?));
+                }
+            }
+        }
+        if parts.len() == self.segments.len() {
+            Option::Some(params)
+        } else {
+            Option::None
+        }
+    }
+}
+
+// percent decoding of one path segment, anything malformed is left as it is
+fn decode( segment: &str ) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[index+1..index+3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = hex {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(decoded.as_slice()).to_string()
+}
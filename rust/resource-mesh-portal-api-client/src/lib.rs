@@ -7,19 +7,18 @@ extern crate anyhow;
 
 
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Error;
 use dashmap::DashMap;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
 
-use std::prelude::rust_2021::TryFrom;
-use std::ops::Deref;
 use std::collections::HashMap;
-use tokio::sync::watch::Receiver;
 use client::{Request,RequestContext};
+use resource_mesh_portal_metrics as metrics;
+use resource_mesh_portal_metrics::PortalMetrics;
 use resource_mesh_portal_serde::std_logger;
 use resource_mesh_portal_serde::version::latest::http::{HttpRequest, HttpResponse};
 use resource_mesh_portal_serde::version::latest::portal::{inlet, outlet};
@@ -30,11 +29,18 @@ use resource_mesh_portal_serde::version::latest::log::Log;
 use resource_mesh_portal_serde::version::latest::operation::{ExtOperation, PortOperation};
 use resource_mesh_portal_serde::version::latest::delivery::Entity;
 use resource_mesh_portal_serde::version::latest::delivery::ResponseEntity;
+use resource_mesh_portal_serde::version::latest::frame::CloseReason;
+use resource_mesh_portal_serde::version::latest::fail::{mesh, Fail};
+use resource_mesh_portal_serde::version::latest::id::Identifier;
 
+use crate::middleware::{Dispatch, Incoming, Middleware, Next};
+use crate::retry::{Admission, DedupCache, RetryPolicy, Retryable};
+use crate::state::StateHandle;
 
-struct EmptySkel {
-
-}
+pub mod http;
+pub mod middleware;
+pub mod retry;
+pub mod state;
 
 #[async_trait]
 pub trait PortalCtrl: Sync+Send {
@@ -47,20 +53,65 @@ pub trait PortalCtrl: Sync+Send {
         HashMap::new()
     }
 
-    async fn http_request( &self, request: Request<HttpRequest> ) -> Result<HttpResponse,Error> {
-        let response = HttpResponse {
-            headers: Default::default(),
-            code: 404,
-            body: None
-        };
-        Ok(response)
+    // wrapped around the dispatch of every request to http_request() and to the ports,
+    // the first is outermost
+    fn middleware(&self) -> Vec<Arc<dyn Middleware>> {
+        vec![]
+    }
+
+    // a ctrl that keeps its State through skel.state has it loaded before init()
+    fn persists_state(&self) -> bool {
+        false
+    }
+
+    // see http::HttpRouter for routing requests by method & path
+    async fn http_request( &self, _request: Request<HttpRequest> ) -> Result<HttpResponse,Error> {
+        Ok(HttpResponse::not_found())
+    }
+
+    // the hooks below are called one at a time in the order their events happened
+
+    // called once init() succeeded and the portal reported Ready
+    async fn on_ready( &self ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // every Status the portal itself moves to, not those set by the ctrl through its skel
+    async fn on_status_change( &self, _status: Status ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // the server has closed the portal, nothing more will reach it after this
+    async fn on_close( &self, _reason: CloseReason ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // follows on_close(), the last chance to release resources or flush state.  whatever
+    // is still running at deadline is abandoned
+    async fn on_shutdown( &self, _deadline: Instant ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // how long on_shutdown() is given
+    fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    // the transport went away without the portal being closed
+    async fn on_disconnect( &self ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // a new transport took over, see Portal::reconnect()
+    async fn on_reconnect( &self ) -> Result<(), Error> {
+        Ok(())
     }
 }
 
 
 #[async_trait]
 pub trait PortCtrl: Sync+Send {
-    async fn request( &self, request: Request<PortOperation> ) -> Result<Option<ResponseEntity>,Error>{
+    async fn request( &self, _request: Request<PortOperation> ) -> Result<Option<ResponseEntity>,Error>{
         Ok(Option::None)
     }
 }
@@ -75,7 +126,7 @@ pub trait Inlet: Sync+Send {
 }
 
 pub trait Outlet: Sync+Send {
-    fn receive(&mut self, frame: outlet::Frame);
+    fn receive(&self, frame: outlet::Frame);
 }
 
 pub struct StatusChamber {
@@ -100,12 +151,14 @@ pub struct PortalSkel {
     pub inlet: Arc<dyn Inlet>,
     pub logger: fn(message: &str),
     pub exchanges: Exchanges,
-    pub status: PortalStatus
+    pub status: PortalStatus,
+    pub metrics: PortalMetrics,
+    pub state: StateHandle
 }
 
 impl PortalSkel {
     pub fn status(&self) -> Status {
-        (*self.status.read().expect("expected status read lock")).status.clone()
+        self.status.read().expect("expected status read lock").status.clone()
     }
 
     pub fn set_status(&mut self, status: Status) {
@@ -116,7 +169,7 @@ impl PortalSkel {
     }
 
     pub fn api(&self) -> InletApi {
-        InletApi::new( self.info.clone(), self.inlet.clone(), self.exchanges.clone(), std_logger )
+        InletApi::new( self.info.clone(), self.inlet.clone(), self.exchanges.clone(), self.metrics.clone(), std_logger )
     }
 
 }
@@ -126,186 +179,376 @@ pub struct Portal {
     pub skel: PortalSkel,
     pub ctrl: Arc<dyn PortalCtrl>,
     pub ports: Arc<HashMap<String,Box<dyn PortCtrl>>>,
+    middleware: Arc<Vec<Arc<dyn Middleware>>>,
+    inlet: Arc<MeteredInlet>,
+    lifecycle_tx: mpsc::UnboundedSender<Lifecycle>,
+    dedup: Arc<DedupCache>
+}
+
+// what the ctrl's lifecycle hooks are called for
+enum Lifecycle {
+    Ready,
+    StatusChange(Status),
+    Close(CloseReason),
+    Disconnect,
+    Reconnect
 }
 
 impl Portal {
     pub async fn new(
         info: Info,
         inlet: Box<dyn Inlet>,
+        outlet_rx: mpsc::Receiver<outlet::Frame>,
         ctrl_factory: fn(skel: PortalSkel) -> Box<dyn PortalCtrl>,
         logger: fn(message: &str)
     ) -> Result<Arc<Portal>, Error> {
 
-        let inlet :Arc<dyn Inlet>= inlet.into();
+        let metrics = PortalMetrics::new(metrics::global(), "client", info.key.as_str(), info.address.as_str());
+        let metered = Arc::new(MeteredInlet {
+            inlet: RwLock::new(inlet),
+            metrics: metrics.clone()
+        });
         let status = Arc::new(RwLock::new(StatusChamber::new( Status::Initializing )));
-        inlet.send_frame(inlet::Frame::Status(Status::Initializing));
-        let exchanges = Arc::new(DashMap::new());
+        metered.send_frame(inlet::Frame::Status(Status::Initializing));
+        let exchanges: Exchanges = Arc::new(DashMap::new());
+        let state = StateHandle::new(info.clone(), metered.clone(), exchanges.clone(), metrics.clone(), status.clone());
         let skel =  PortalSkel {
             info: info.clone(),
-            inlet,
+            inlet: metered.clone(),
             logger,
             exchanges,
-            status
+            status,
+            metrics,
+            state
         };
 
+        let (portal_tx, portal_rx) = oneshot::channel();
+        Self::pump(skel.clone(), outlet_rx, portal_rx);
+
         let mut ctrl = ctrl_factory(skel.clone());
         let ports = Arc::new(ctrl.ports());
-        ctrl.init().await?;
-        let ctrl = ctrl.into();
-        let portal = Self {
+        let middleware = Arc::new(ctrl.middleware());
+        if ctrl.persists_state() {
+            // a portal that cannot load its state still starts, only without what it saved before
+            if let Err(err) = skel.state.load().await {
+                (logger)(format!("WARN: state of {} could not be loaded: {}", info.address, err).as_str());
+            }
+        }
+        if let Err(err) = ctrl.init().await {
+            let mut skel = skel.clone();
+            skel.set_status(Status::Panic(err.to_string()));
+            return Err(err);
+        }
+        let ctrl: Arc<dyn PortalCtrl> = ctrl.into();
+        let (lifecycle_tx, lifecycle_rx) = mpsc::unbounded_channel();
+        Self::lifecycle(ctrl.clone(), skel.clone(), lifecycle_rx);
+        let portal = Arc::new(Self {
             skel: skel.clone(),
             ctrl,
-            ports
+            ports,
+            middleware,
+            inlet: metered,
+            lifecycle_tx,
+            dedup: Arc::new(DedupCache::new(Duration::from_secs(300), 1024))
+        });
+
+        {
+            let mut skel = skel.clone();
+            skel.set_status(Status::Ready);
+        }
+        portal.hook(Lifecycle::StatusChange(Status::Ready));
+        portal.hook(Lifecycle::Ready);
+        portal_tx.send(portal.clone()).unwrap_or_default();
+
+        Ok(portal)
+    }
+
+    // hands the portal over to a new transport, for instance after the old one dropped.  the
+    // exchanges in flight are kept, whether their responses still arrive is up to the transport
+    pub fn reconnect( self: &Arc<Self>, inlet: Box<dyn Inlet>, outlet_rx: mpsc::Receiver<outlet::Frame> ) {
+        *self.inlet.inlet.write().expect("expected inlet write lock") = inlet;
+        self.hook(Lifecycle::Reconnect);
+        Self::relay(self.clone(), outlet_rx);
+    }
+
+    fn hook( &self, lifecycle: Lifecycle ) {
+        self.lifecycle_tx.send(lifecycle).unwrap_or_default();
+    }
+
+    fn lifecycle( ctrl: Arc<dyn PortalCtrl>, skel: PortalSkel, lifecycle_rx: mpsc::UnboundedReceiver<Lifecycle> ) {
+        tokio::spawn(async move {
+            let mut lifecycle_rx = lifecycle_rx;
+            while let Option::Some(lifecycle) = lifecycle_rx.recv().await {
+                let (hook, result) = match lifecycle {
+                    Lifecycle::Ready => ("on_ready", ctrl.on_ready().await),
+                    Lifecycle::StatusChange(status) => ("on_status_change", ctrl.on_status_change(status).await),
+                    Lifecycle::Disconnect => ("on_disconnect", ctrl.on_disconnect().await),
+                    Lifecycle::Reconnect => ("on_reconnect", ctrl.on_reconnect().await),
+                    Lifecycle::Close(reason) => {
+                        if let Err(err) = ctrl.on_close(reason).await {
+                            (skel.logger)(format!("ERROR: portal on_close: {}", err).as_str());
+                        }
+                        let deadline = Instant::now() + ctrl.shutdown_timeout();
+                        let result = match tokio::time::timeout_at(deadline.into(), ctrl.on_shutdown(deadline)).await {
+                            Ok(result) => result,
+                            Err(_) => Err(anyhow!("missed its deadline"))
+                        };
+                        if let Err(err) = result {
+                            (skel.logger)(format!("ERROR: portal on_shutdown: {}", err).as_str());
+                        }
+                        // nothing follows a close
+                        return;
+                    }
+                };
+                if let Err(err) = result {
+                    (skel.logger)(format!("ERROR: portal {}: {}", hook, err).as_str());
+                }
+            }
+        });
+    }
+
+    // responses must reach their exchanges while ctrl.init() is still running,
+    // every other frame is held back until the portal is Ready
+    fn pump(skel: PortalSkel, outlet_rx: mpsc::Receiver<outlet::Frame>, portal_rx: oneshot::Receiver<Arc<Portal>>) {
+        tokio::spawn(async move {
+            let mut outlet_rx = outlet_rx;
+            let mut portal_rx = portal_rx;
+            let mut pending = vec![];
+            let portal = loop {
+                tokio::select! {
+                    portal = &mut portal_rx => {
+                        match portal {
+                            Ok(portal) => break portal,
+                            Err(_) => return
+                        }
+                    }
+                    frame = outlet_rx.recv() => {
+                        if let Option::Some(frame) = &frame {
+                            skel.metrics.frames_in.inc();
+                            skel.metrics.bytes_in.add(bincode::serialized_size(frame).unwrap_or_default());
+                        }
+                        match frame {
+                            Some(outlet::Frame::Response(response)) => {
+                                if let Option::Some((_,tx)) = skel.exchanges.remove(&response.exchange_id) {
+                                    tx.send(response).unwrap_or(());
+                                } else {
+                                    (skel.logger)("SEVERE: do not have a matching exchange_id for response");
+                                }
+                            }
+                            Some(frame) => pending.push(frame),
+                            None => {
+                                // the transport is gone already, the portal hears of it once it is Ready
+                                match (&mut portal_rx).await {
+                                    Ok(portal) => break portal,
+                                    Err(_) => return
+                                }
+                            }
+                        }
+                    }
+                }
+            };
+
+            for frame in pending {
+                portal.receive(frame);
+            }
+
+            Self::relay(portal, outlet_rx);
+        });
+    }
+
+    // hands every frame to the portal until the transport is gone
+    fn relay(portal: Arc<Portal>, outlet_rx: mpsc::Receiver<outlet::Frame>) {
+        tokio::spawn(async move {
+            let mut outlet_rx = outlet_rx;
+            while let Option::Some(frame) = outlet_rx.recv().await {
+                portal.skel.metrics.frames_in.inc();
+                portal.skel.metrics.bytes_in.add(bincode::serialized_size(&frame).unwrap_or_default());
+                portal.receive(frame);
+            }
+            // a transport is expected to go after a Close frame, anything else is a disconnect
+            if let Status::Done = portal.skel.status() {
+            } else {
+                portal.hook(Lifecycle::Disconnect);
+            }
+        });
+    }
+
+    // saves the state and then closes the portal from this side
+    pub async fn shutdown( &self ) {
+        if let Err(err) = self.skel.state.checkpoint().await {
+            (self.skel.logger)(format!("ERROR: final state checkpoint of {} failed: {}", self.skel.info.address, err).as_str());
+        }
+        self.skel.inlet.send_frame(inlet::Frame::Close(CloseReason::Done));
+        self.close(CloseReason::Done);
+    }
+
+    // passes request through the middleware to whatever handles it, the signal is what to
+    // respond if the request expects a response
+    async fn handle( dispatch: Dispatch, middleware: Arc<Vec<Arc<dyn Middleware>>>, skel: PortalSkel, request: outlet::Request ) -> Option<ResponseEntity> {
+        let context = RequestContext::new(skel.info.clone(), skel.logger );
+        let kind = request.kind.clone();
+        let request = match &request.operation {
+            ExtOperation::Http(_) => {
+                if let ExchangeKind::RequestResponse(_) = &kind {
+                } else {
+                    (skel.logger)("FATAL: http request MUST be of ExchangeKind::RequestResponse");
+                    return Option::None;
+                }
+                match Request::try_from_http(request, context) {
+                    Ok(request) => Incoming::Http(request),
+                    Err(err) => {
+                        (skel.logger)(format!("FATAL: could not modify HttpRequest into Request<HttpRequest>: {}", err).as_str());
+                        return Option::None;
+                    }
+                }
+            }
+            ExtOperation::Port(_) => {
+                match Request::try_from_port(request, context) {
+                    Ok(request) => Incoming::Port(request),
+                    Err(err) => {
+                        let message = format!("FATAL: could not modify PortOperation into Request<PortOperation>: {}", err);
+                        (skel.logger)(message.as_str());
+                        return Option::Some(ResponseEntity::Error(message));
+                    }
+                }
+            }
         };
 
-        Ok(Arc::new(portal))
+        match request {
+            Incoming::Http(request) => {
+                let path = request.path.clone();
+                match Next::new(middleware.as_slice(), &dispatch).run(Incoming::Http(request)).await {
+                    Ok(Some(signal)) => Option::Some(signal),
+                    Ok(None) => {
+                        (skel.logger)(format!("ERROR: HttpRequest.path: '{}' generated no response", path).as_str());
+                        Option::Some(ResponseEntity::Ok(Entity::HttpResponse(HttpResponse::server_side_error())))
+                    }
+                    Err(err) => {
+                        (skel.logger)(format!("ERROR: HttpRequest.path: '{}' error: '{}' ",  path, err).as_str());
+                        Option::Some(ResponseEntity::Ok(Entity::HttpResponse(HttpResponse::server_side_error())))
+                    }
+                }
+            }
+            Incoming::Port(request) => {
+                let port = request.port.clone();
+                match Next::new(middleware.as_slice(), &dispatch).run(Incoming::Port(request)).await {
+                    Ok(Some(signal)) => {
+                        if let ExchangeKind::RequestResponse(_) = &kind {
+                            Option::Some(signal)
+                        } else {
+                            (skel.logger)(format!("WARN: PortOperation.port '{}' generated a response to a ExchangeKind::Notification", port).as_str());
+                            Option::None
+                        }
+                    }
+                    Ok(None) => {
+                        let message = format!("ERROR: PortOperation.port '{}' generated no response", port);
+                        (skel.logger)(message.as_str());
+                        Option::Some(ResponseEntity::Error(message))
+                    }
+                    Err(err) => {
+                        let message = format!("ERROR: PortOperation.port '{}' message: '{}'", port, err);
+                        (skel.logger)(message.as_str());
+                        Option::Some(ResponseEntity::Error(message))
+                    }
+                }
+            }
+        }
     }
 
     pub fn log( &self, log: Log ) {
         self.skel.inlet.send_frame(inlet::Frame::Log(log));
     }
 
+    fn close( &self, reason: CloseReason ) {
+        {
+            let mut status = self.skel.status.write().expect("expected to get status write lock");
+            if let Status::Done = status.status {
+                return;
+            }
+            status.status = Status::Done;
+        }
+        // whoever is still waiting on an exchange will not get a response now
+        self.skel.exchanges.clear();
+        self.hook(Lifecycle::StatusChange(Status::Done));
+        self.hook(Lifecycle::Close(reason));
+    }
+
 }
 
 #[async_trait]
 impl Outlet for Portal {
-    fn receive(&mut self, frame: outlet::Frame) {
+    fn receive(&self, frame: outlet::Frame) {
+        if let outlet::Frame::Close(reason) = frame {
+            self.close(reason);
+            return;
+        }
+        if let outlet::Frame::Checkpoint(exchange_id) = frame {
+            // the server is about to close the portal, whatever it has not saved yet goes now
+            let skel = self.skel.clone();
+            tokio::spawn(async move {
+                let signal = match skel.state.checkpoint().await {
+                    Ok(_) => ResponseEntity::Ok(Entity::Empty),
+                    Err(err) => {
+                        (skel.logger)(format!("ERROR: final state checkpoint of {} failed: {}", skel.info.address, err).as_str());
+                        ResponseEntity::Error(err.to_string())
+                    }
+                };
+                skel.api().respond(inlet::Response {
+                    to: Identifier::Address(skel.info.address.clone()),
+                    exchange_id,
+                    signal
+                });
+            });
+            return;
+        }
         match self.skel.status() {
             Status::Ready => match frame {
                 outlet::Frame::CommandEvent(_) => {}
                 outlet::Frame::Request(request) => {
-                    let ctrl = self.ctrl.clone();
-                    let inlet_api = self.skel.api();
-                    let skel = self.skel.clone();
-                    let context = RequestContext::new(skel.info.clone(), skel.logger );
-                    let ports = self.ports.clone();
                     let from = request.from.clone();
                     let kind = request.kind.clone();
-                    tokio::spawn( async move {
-                        match request.operation.clone() {
-                            ExtOperation::Http(_) => {
-                                if let ExchangeKind::RequestResponse(exchange_id) = &kind
-                                {
-                                    let result = Request::try_from_http(request, context);
-                                    match result {
-                                        Ok(request) => {
-                                            let path = request.path.clone();
-                                            let result = ctrl.http_request(request).await;
-                                            match result {
-                                                Ok(response) => {
-                                                    let response = inlet::Response {
-                                                        to: from,
-                                                        exchange_id:exchange_id.clone(),
-                                                        signal: ResponseEntity::Ok(Entity::HttpResponse(response))
-                                                    };
-                                                    inlet_api.respond( response );
-                                                }
-                                                Err(err) => {
-                                                    (skel.logger)(format!("ERROR: HttpRequest.path: '{}' error: '{}' ",  path, err.to_string()).as_str());
-                                                    let response = inlet::Response {
-                                                        to: from,
-                                                        exchange_id:exchange_id.clone(),
-                                                        signal: ResponseEntity::Ok(Entity::HttpResponse(HttpResponse::server_side_error()))
-                                                    };
-                                                    inlet_api.respond( response );
-                                                }
-                                            }
-                                        }
-                                        Err(err) => {
-                                            (skel.logger)(format!("FATAL: could not modify HttpRequest into Request<HttpRequest>: {}", err.to_string()).as_str());
-                                        }
-                                    }
-                                } else {
-                                    (skel.logger)("FATAL: http request MUST be of ExchangeKind::RequestResponse");
+                    let key = request.idempotency_key.clone();
+                    let inlet_api = self.skel.api();
+                    if let Option::Some(key) = &key {
+                        match self.dedup.admit(&from, key, &kind) {
+                            Admission::Run => {}
+                            Admission::Replay(signal) => {
+                                if let (ExchangeKind::RequestResponse(exchange_id), Option::Some(signal)) = (&kind, signal) {
+                                    inlet_api.respond(inlet::Response {
+                                        to: from,
+                                        exchange_id: exchange_id.clone(),
+                                        signal
+                                    });
                                 }
+                                return;
                             }
-                            ExtOperation::Port(port_request) => {
-                                match ports.get(&port_request.port ) {
-                                    Some(port) => {
-                                        let result = Request::try_from_port(request, context );
-                                        match result {
-                                            Ok(request) => {
-                                                let request_from = request.from.clone();
-                                                let result = port.request(request).await;
-                                                match result {
-                                                    Ok(response) => {
-                                                        match response {
-                                                            Some(signal) => {
-                                                                if let ExchangeKind::RequestResponse(exchange_id) = &kind
-                                                                {
-                                                                   let response = inlet::Response {
-                                                                       to: request_from,
-                                                                       exchange_id: exchange_id.clone(),
-                                                                       signal
-                                                                   };
-
-                                                                   inlet_api.respond(response);
-                                                                } else {
-                                                                    let message = format!("WARN: PortOperation.port '{}' generated a response to a ExchangeKind::Notification", port_request.port);
-                                                                    (skel.logger)(message.as_str());
-                                                                }
-                                                            }
-                                                            None => {
-                                                                let message = format!("ERROR: PortOperation.port '{}' generated no response", port_request.port);
-                                                                (skel.logger)(message.as_str());
-                                                                if let ExchangeKind::RequestResponse(exchange_id) = &kind
-                                                                {
-                                                                    let response = inlet::Response {
-                                                                        to: request_from,
-                                                                        exchange_id: exchange_id.clone(),
-                                                                        signal: ResponseEntity::Error(message)
-                                                                    };
-                                                                    inlet_api.respond(response);
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                    Err(err) => {
-                                                        let message = format!("ERROR: PortOperation.port '{}' message: '{}'", port_request.port, err.to_string());
-                                                        (skel.logger)(message.as_str());
-                                                        if let ExchangeKind::RequestResponse(exchange_id) = &kind
-                                                        {
-                                                            let response = inlet::Response {
-                                                                to: request_from,
-                                                                exchange_id: exchange_id.clone(),
-                                                                signal: ResponseEntity::Error(message)
-                                                            };
-                                                            inlet_api.respond(response);
-                                                        }
-                                                    }
-                                                }
-
-                                            }
-                                            Err(err) => {
-                                                let message = format!("FATAL: could not modify PortOperation into Request<PortOperation>: {}", err.to_string());
-                                                (skel.logger)(message.as_str());
-                                                if let ExchangeKind::RequestResponse(exchange_id) = &kind
-                                                {
-                                                    let response = inlet::Response {
-                                                        to: from,
-                                                        exchange_id: exchange_id.clone(),
-                                                        signal: ResponseEntity::Error(message)
-                                                    };
-                                                    inlet_api.respond(response);
-                                                }
-                                            }
-                                        }
-
-                                    }
-                                    None => {
-                                        let message =format!("ERROR: message port: '{}' not defined ", port_request.port );
-                                        (skel.logger)(message.as_str());
-                                        if let ExchangeKind::RequestResponse(exchange_id) = &kind
-                                        {
-                                            let response = inlet::Response {
-                                                to: from,
-                                                exchange_id: exchange_id.clone(),
-                                                signal: ResponseEntity::Error(message)
-                                            };
-                                            inlet_api.respond(response);
-                                        }
-                                    }
-                                }
+                            Admission::Wait => return
+                        }
+                    }
+                    let dispatch = Dispatch {
+                        ctrl: self.ctrl.clone(),
+                        ports: self.ports.clone()
+                    };
+                    let middleware = self.middleware.clone();
+                    let skel = self.skel.clone();
+                    let dedup = self.dedup.clone();
+                    tokio::spawn( async move {
+                        let signal = Self::handle(dispatch, middleware, skel, request).await;
+                        let mut exchanges = vec![];
+                        if let ExchangeKind::RequestResponse(exchange_id) = &kind {
+                            exchanges.push(exchange_id.clone());
+                        }
+                        // retries that arrived while the request was handled get the same response
+                        if let Option::Some(key) = &key {
+                            exchanges.append(&mut dedup.complete(&from, key, signal.clone()));
+                        }
+                        if let Option::Some(signal) = signal {
+                            for exchange_id in exchanges {
+                                inlet_api.respond(inlet::Response {
+                                    to: from.clone(),
+                                    exchange_id,
+                                    signal: signal.clone()
+                                });
                             }
                         }
                     });
@@ -320,36 +563,60 @@ impl Outlet for Portal {
                     }
                 }
                 outlet::Frame::BinParcel(_) => {}
-                outlet::Frame::Close(_) => {}
                 _ => {
-                    (self.skel.logger)(format!("SEVERE: frame ignored because status: '{}' does not allow handling of frame '{}'",self.skel.status().to_string(),frame.to_string()).as_str());
+                    (self.skel.logger)(format!("SEVERE: frame ignored because status: '{}' does not allow handling of frame '{}'",self.skel.status(),frame).as_str());
                 }
             },
             _ => {
-                (self.skel.logger)(format!("SEVERE: frame ignored because status: '{}' does not allow handling of frame '{}'",self.skel.status().to_string(),frame.to_string()).as_str());
+                (self.skel.logger)(format!("SEVERE: frame ignored because status: '{}' does not allow handling of frame '{}'",self.skel.status(),frame).as_str());
             }
         }
     }
 }
 
 
+// counts every frame the portal sends whatever the transport behind it,
+// the transport itself can be swapped by Portal::reconnect()
+struct MeteredInlet {
+    inlet: RwLock<Box<dyn Inlet>>,
+    metrics: PortalMetrics
+}
+
+impl Inlet for MeteredInlet {
+    fn send_frame(&self, frame: inlet::Frame) {
+        self.metrics.frames_out.inc();
+        self.metrics.bytes_out.add(bincode::serialized_size(&frame).unwrap_or_default());
+        self.inlet.read().expect("expected inlet read lock").send_frame(frame);
+    }
+}
+
 pub struct InletApi {
     info: Info,
     inlet: Arc<dyn Inlet>,
     exchanges: Exchanges,
-    logger: fn( log: Log )
+    metrics: PortalMetrics,
+    logger: fn( log: Log ),
+    retry: RetryPolicy
 }
 
 impl InletApi {
-    pub fn new(info: Info, inlet: Arc<dyn Inlet>, exchanges: Exchanges, logger: fn( log: Log ) ) -> Self {
+    pub fn new(info: Info, inlet: Arc<dyn Inlet>, exchanges: Exchanges, metrics: PortalMetrics, logger: fn( log: Log ) ) -> Self {
         Self {
             info,
             inlet,
             exchanges,
-            logger
+            metrics,
+            logger,
+            retry: RetryPolicy::none()
         }
     }
 
+    // applies to every exchange() from now on
+    pub fn with_retry( mut self, retry: RetryPolicy ) -> Self {
+        self.retry = retry;
+        self
+    }
+
 
     pub fn notify(&self, request: inlet::Request) {
         let mut request = request;
@@ -369,15 +636,59 @@ impl InletApi {
         } else {
             self.inlet.send_frame(inlet::Frame::Log(Log::Warn("ExchangeKind is replaced in 'notify' or 'exchange' method and should be preset to ExchangeKind::None".to_string())));
         }
+        let mut request = request;
+        // every attempt carries the same key so the recipient handles the request only once
+        if self.retry.max_attempts > 1 && request.idempotency_key.is_none() {
+            request.idempotency_key = Option::Some(Uuid::new_v4().to_string());
+        }
+
+        let mut attempt = 1;
+        loop {
+            let result = self.attempt(request.clone()).await;
+            let retryable = match &result {
+                Ok(response) => RetryPolicy::classify(&response.signal),
+                Err(err) if err.is::<tokio::time::error::Elapsed>() => Option::Some(Retryable::Timeout),
+                Err(_) => Option::None
+            };
+            match retryable {
+                Some(retryable) if attempt < self.retry.max_attempts && self.retry.retries(&retryable) => {
+                    let mut delay = self.retry.backoff.delay(attempt);
+                    if let Ok(outlet::Response{ signal: ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Throttled(throttled))), .. }) = &result {
+                        delay = delay.max(Duration::from_millis(throttled.retry_after_millis));
+                    }
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Some(Retryable::Timeout) => {
+                    return Err(anyhow!("no response from {:?} within {} seconds after {} attempt(s)", request.to, self.info.config.response_timeout, attempt));
+                }
+                _ => return result
+            }
+        }
+    }
+
+    async fn attempt( &mut self, request: inlet::Request ) -> Result<outlet::Response, Error> {
         let mut request = request;
         let exchange_id: ExchangeId = Uuid::new_v4().to_string();
         request.kind = ExchangeKind::RequestResponse(exchange_id.clone());
         let (tx,rx) = oneshot::channel();
-        self.exchanges.insert(exchange_id, tx);
+        self.exchanges.insert(exchange_id.clone(), tx);
         self.inlet.send_frame(inlet::Frame::Request(request));
-
-        let result = tokio::time::timeout(Duration::from_secs(self.info.config.response_timeout.clone()),rx).await;
-        Ok(result??)
+        self.metrics.exchanges_started.inc();
+
+        let started = Instant::now();
+        match tokio::time::timeout(Duration::from_secs(self.info.config.response_timeout),rx).await {
+            Ok(response) => {
+                self.metrics.exchanges_completed.inc();
+                self.metrics.exchange_seconds.observe_duration(started.elapsed());
+                Ok(response?)
+            }
+            Err(err) => {
+                self.exchanges.remove(&exchange_id);
+                self.metrics.exchanges_timed_out.inc();
+                Err(err.into())
+            }
+        }
     }
 
     pub fn respond( &self, response: inlet::Response ) {
@@ -393,7 +704,6 @@ pub mod client {
     use resource_mesh_portal_serde::version::latest::operation::{ExtOperation, PortOperation};
     use resource_mesh_portal_serde::version::latest::config::Info;
     use resource_mesh_portal_serde::version::latest::http::HttpRequest;
-    use resource_mesh_portal_serde::version::latest::delivery::ResponseEntity;
 
     #[derive(Clone)]
     pub struct RequestContext {
@@ -460,8 +770,8 @@ pub mod example {
     use anyhow::Error;
 
 
-    use crate::{InletApi, PortalCtrl, PortalSkel, Request, inlet};
-    use std::collections::HashMap;
+    use crate::{InletApi, PortalCtrl, PortalSkel, inlet};
+
     use resource_mesh_portal_serde::version::latest::operation::{Operation, ExtOperation, PortOperation};
     use resource_mesh_portal_serde::version::latest::delivery::{Entity, ResponseEntity,Payload};
     use resource_mesh_portal_serde::version::latest::id::Identifier;
@@ -504,10 +814,4 @@ pub mod example {
 
 
     }
-
-    impl HelloCtrl {
-        fn hello_world( &self, request: Request<PortOperation> ) {
-
-        }
-    }
 }
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Error;
+
+use resource_mesh_portal_serde::version::latest::delivery::{Entity, ResponseEntity};
+use resource_mesh_portal_serde::version::latest::http::{HttpRequest, HttpResponse};
+use resource_mesh_portal_serde::version::latest::id::Identifier;
+use resource_mesh_portal_serde::version::latest::operation::PortOperation;
+
+use crate::{PortalCtrl, PortCtrl};
+use crate::client::{Request, RequestContext};
+
+// a request on its way to PortalCtrl::http_request() or to a PortCtrl
+pub enum Incoming {
+    Http(Request<HttpRequest>),
+    Port(Request<PortOperation>)
+}
+
+impl Incoming {
+    pub fn context( &self ) -> &RequestContext {
+        match self {
+            Incoming::Http(request) => &request.context,
+            Incoming::Port(request) => &request.context
+        }
+    }
+
+    pub fn from( &self ) -> &Identifier {
+        match self {
+            Incoming::Http(request) => &request.from,
+            Incoming::Port(request) => &request.from
+        }
+    }
+}
+
+// wraps the dispatch of every request the portal receives, in the order PortalCtrl::middleware()
+// returns them with the first outermost.  a middleware passes the request on with next.run()
+// or answers it itself, an Incoming::Http is expected to be answered with an Entity::HttpResponse
+#[async_trait]
+pub trait Middleware: Sync+Send {
+    async fn handle( &self, request: Incoming, next: Next<'_> ) -> Result<Option<ResponseEntity>,Error>;
+}
+
+// the rest of the chain
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn Middleware>],
+    dispatch: &'a Dispatch
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new( middleware: &'a [Arc<dyn Middleware>], dispatch: &'a Dispatch ) -> Self {
+        Self {
+            middleware,
+            dispatch
+        }
+    }
+
+    pub async fn run( self, request: Incoming ) -> Result<Option<ResponseEntity>,Error> {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => middleware.handle(request, Next::new(rest, self.dispatch)).await,
+            None => self.dispatch.dispatch(request).await
+        }
+    }
+}
+
+// the end of every chain, hands the request to whatever the PortalCtrl has for it
+pub(crate) struct Dispatch {
+    pub ctrl: Arc<dyn PortalCtrl>,
+    pub ports: Arc<HashMap<String,Box<dyn PortCtrl>>>
+}
+
+impl Dispatch {
+    async fn dispatch( &self, request: Incoming ) -> Result<Option<ResponseEntity>,Error> {
+        match request {
+            Incoming::Http(request) => {
+                let response = self.ctrl.http_request(request).await?;
+                Ok(Option::Some(ResponseEntity::Ok(Entity::HttpResponse(response))))
+            }
+            Incoming::Port(request) => match self.ports.get(&request.port) {
+                Some(port) => port.request(request).await,
+                None => {
+                    let message = format!("ERROR: message port: '{}' not defined ", request.port);
+                    (request.context.logger)(message.as_str());
+                    Ok(Option::Some(ResponseEntity::Error(message)))
+                }
+            }
+        }
+    }
+}
+
+// gives up on a request that is not handled within timeout, http is answered with a 504
+pub struct Timeout {
+    pub timeout: Duration
+}
+
+impl Timeout {
+    pub fn new( timeout: Duration ) -> Self {
+        Self {
+            timeout
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for Timeout {
+    async fn handle( &self, request: Incoming, next: Next<'_> ) -> Result<Option<ResponseEntity>,Error> {
+        let http = matches!(request, Incoming::Http(_));
+        match tokio::time::timeout(self.timeout, next.run(request)).await {
+            Ok(result) => result,
+            Err(_) if http => {
+                let response = HttpResponse {
+                    headers: Default::default(),
+                    code: 504,
+                    body: Option::None
+                };
+                Ok(Option::Some(ResponseEntity::Ok(Entity::HttpResponse(response))))
+            }
+            Err(_) => Err(anyhow!("not handled within {}ms", self.timeout.as_millis()))
+        }
+    }
+}
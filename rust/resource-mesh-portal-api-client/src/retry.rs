@@ -0,0 +1,183 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use resource_mesh_portal_serde::version::latest::delivery::ResponseEntity;
+use resource_mesh_portal_serde::version::latest::fail::{mesh, Fail};
+use resource_mesh_portal_serde::version::latest::id::Identifier;
+use resource_mesh_portal_serde::version::latest::messaging::{ExchangeId, ExchangeKind, IdempotencyKey};
+
+// the failures an exchange may be attempted again for
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Retryable {
+    // no response within the portal's response_timeout
+    Timeout,
+    // never retried sooner than the server's retry_after_millis
+    Throttled,
+    QueueOverflow,
+    // mesh::Fail::Error
+    MeshError
+}
+
+#[derive(Debug, Clone)]
+pub enum Backoff {
+    Fixed(Duration),
+    // doubles with every retry up to max
+    Exponential{ initial: Duration, max: Duration }
+}
+
+impl Backoff {
+    // the wait before the given retry, the first retry is 1
+    pub fn delay( &self, retry: u32 ) -> Duration {
+        match self {
+            Backoff::Fixed(delay) => *delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+                match initial.checked_mul(factor) {
+                    Some(delay) => delay.min(*max),
+                    None => *max
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+    pub retry_on: Vec<Retryable>
+}
+
+impl RetryPolicy {
+    // a single attempt, which is what InletApi::exchange() makes unless told otherwise
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Backoff::Fixed(Duration::from_millis(0)),
+            retry_on: vec![]
+        }
+    }
+
+    // retries timeouts & throttling, waiting 100ms doubling up to 5s in between
+    pub fn new( max_attempts: u32 ) -> Self {
+        Self {
+            max_attempts,
+            backoff: Backoff::Exponential {
+                initial: Duration::from_millis(100),
+                max: Duration::from_secs(5)
+            },
+            retry_on: vec![Retryable::Timeout, Retryable::Throttled]
+        }
+    }
+
+    pub fn with_backoff( mut self, backoff: Backoff ) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_retry_on( mut self, retry_on: Vec<Retryable> ) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    pub fn retries( &self, retryable: &Retryable ) -> bool {
+        self.retry_on.contains(retryable)
+    }
+
+    // which Retryable a response is, if any
+    pub fn classify( signal: &ResponseEntity ) -> Option<Retryable> {
+        match signal {
+            ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Throttled(_))) => Option::Some(Retryable::Throttled),
+            ResponseEntity::Fail(Fail::Mesh(mesh::Fail::QueueOverflow)) => Option::Some(Retryable::QueueOverflow),
+            ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Error(_))) => Option::Some(Retryable::MeshError),
+            _ => Option::None
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+pub(crate) enum Admission {
+    // the first time this key was seen, the request is handled
+    Run,
+    // handled already, this is what it responded
+    Replay(Option<ResponseEntity>),
+    // still being handled, the response goes to this exchange too once it is ready
+    Wait
+}
+
+enum Dedup {
+    InFlight(Vec<ExchangeId>),
+    Done(Option<ResponseEntity>)
+}
+
+type DedupKey = (Identifier,IdempotencyKey);
+type DedupEntries = (HashMap<DedupKey,Dedup>,VecDeque<(DedupKey,Instant)>);
+
+// the requests with an idempotency key a portal received lately & what it responded, so
+// a retry is answered without its handler running again.  keys of answered requests are
+// forgotten after ttl or, when more than capacity are remembered, oldest first
+pub(crate) struct DedupCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<DedupEntries>
+}
+
+impl DedupCache {
+    pub(crate) fn new( ttl: Duration, capacity: usize ) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Mutex::new((HashMap::new(), VecDeque::new()))
+        }
+    }
+
+    pub(crate) fn admit( &self, from: &Identifier, key: &IdempotencyKey, kind: &ExchangeKind ) -> Admission {
+        let mut entries = self.entries.lock().expect("expected dedup lock");
+        let (map, order) = &mut *entries;
+        // only responses are forgotten, a request still being handled keeps its key or
+        // its retries would run the handler again
+        let mut excess = (map.len() + 1).saturating_sub(self.capacity);
+        order.retain(|(oldest, seen)| match map.get(oldest) {
+            Some(Dedup::Done(_)) if excess > 0 || seen.elapsed() > self.ttl => {
+                map.remove(oldest);
+                excess = excess.saturating_sub(1);
+                false
+            }
+            _ => true
+        });
+
+        let dedup_key = (from.clone(), key.clone());
+        match map.get_mut(&dedup_key) {
+            Some(Dedup::InFlight(waiting)) => {
+                if let ExchangeKind::RequestResponse(exchange_id) = kind {
+                    waiting.push(exchange_id.clone());
+                }
+                Admission::Wait
+            }
+            Some(Dedup::Done(signal)) => Admission::Replay(signal.clone()),
+            None => {
+                map.insert(dedup_key.clone(), Dedup::InFlight(vec![]));
+                order.push_back((dedup_key, Instant::now()));
+                Admission::Run
+            }
+        }
+    }
+
+    // remembers the response, returns the exchanges of the retries that arrived in the meantime
+    pub(crate) fn complete( &self, from: &Identifier, key: &IdempotencyKey, signal: Option<ResponseEntity> ) -> Vec<ExchangeId> {
+        let mut entries = self.entries.lock().expect("expected dedup lock");
+        match entries.0.get_mut(&(from.clone(), key.clone())) {
+            Some(entry) => match std::mem::replace(entry, Dedup::Done(signal)) {
+                Dedup::InFlight(waiting) => waiting,
+                Dedup::Done(_) => vec![]
+            },
+            None => vec![]
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use resource_mesh_portal_metrics::PortalMetrics;
+use resource_mesh_portal_serde::std_logger;
+use resource_mesh_portal_serde::version::latest::State;
+use resource_mesh_portal_serde::version::latest::bin::Bin;
+use resource_mesh_portal_serde::version::latest::config::Info;
+use resource_mesh_portal_serde::version::latest::delivery::{Entity, ResourceEntity, ResponseEntity};
+use resource_mesh_portal_serde::version::latest::id::Identifier;
+use resource_mesh_portal_serde::version::latest::log::Log;
+use resource_mesh_portal_serde::version::latest::operation::{Operation, ResourceOperation};
+use resource_mesh_portal_serde::version::latest::portal::inlet;
+use resource_mesh_portal_serde::version::latest::resource::Status;
+
+use crate::{Exchanges, Inlet, InletApi, PortalStatus};
+
+// the portal's own State as the server keeps it for the portal's address.  it is loaded
+// before ctrl.init() when the ctrl persists_state() and saved by checkpoint(), by checkpoint_every() and by Portal::shutdown().
+// a server that shuts down asks for a last checkpoint before it closes the portal, a server
+// that closes the portal for any other reason does not, so whatever changed since the last checkpoint is lost
+#[derive(Clone)]
+pub struct StateHandle {
+    info: Info,
+    inlet: Arc<dyn Inlet>,
+    exchanges: Exchanges,
+    metrics: PortalMetrics,
+    status: PortalStatus,
+    chamber: Arc<Mutex<StateChamber>>
+}
+
+struct StateChamber {
+    state: State,
+    // bumped by every change, the state is dirty until a checkpoint of that version is saved
+    version: u64,
+    saved: u64
+}
+
+impl StateHandle {
+    pub(crate) fn new( info: Info, inlet: Arc<dyn Inlet>, exchanges: Exchanges, metrics: PortalMetrics, status: PortalStatus ) -> Self {
+        Self {
+            info,
+            inlet,
+            exchanges,
+            metrics,
+            status,
+            chamber: Arc::new(Mutex::new(StateChamber {
+                state: State::new(),
+                version: 0,
+                saved: 0
+            }))
+        }
+    }
+
+    pub fn get<T: DeserializeOwned>( &self, key: &str ) -> Result<Option<T>,Error> {
+        let chamber = self.chamber.lock().expect("expected state lock");
+        match chamber.state.get(key) {
+            Some(Bin::Raw(raw)) => Ok(Option::Some(bincode::deserialize(raw.as_slice())?)),
+            Some(Bin::Src(_)) => Err(anyhow!("state '{}' is a bin src and cannot be read here", key)),
+            None => Ok(Option::None)
+        }
+    }
+
+    pub fn set<T: Serialize>( &self, key: &str, value: &T ) -> Result<(),Error> {
+        let raw = bincode::serialize(value)?;
+        let mut chamber = self.chamber.lock().expect("expected state lock");
+        chamber.state.insert(key.to_string(), Bin::Raw(Arc::new(raw)));
+        chamber.version += 1;
+        Ok(())
+    }
+
+    pub fn remove( &self, key: &str ) {
+        let mut chamber = self.chamber.lock().expect("expected state lock");
+        if chamber.state.remove(key).is_some() {
+            chamber.version += 1;
+        }
+    }
+
+    pub fn keys( &self ) -> Vec<String> {
+        self.chamber.lock().expect("expected state lock").state.keys().cloned().collect()
+    }
+
+    // true while there are changes no checkpoint has saved yet
+    pub fn is_dirty( &self ) -> bool {
+        let chamber = self.chamber.lock().expect("expected state lock");
+        chamber.version != chamber.saved
+    }
+
+    // replaces whatever is held with the State the server has for this portal's address
+    pub async fn load( &self ) -> Result<(),Error> {
+        let response = self.exchange(ResourceOperation::Get).await?;
+        match response {
+            ResponseEntity::Ok(Entity::Resource(ResourceEntity::State(state))) => {
+                let mut chamber = self.chamber.lock().expect("expected state lock");
+                chamber.state = state;
+                chamber.version = 0;
+                chamber.saved = 0;
+                Ok(())
+            }
+            ResponseEntity::Ok(_) => Err(anyhow!("expected State when loading the state of {}", self.info.address)),
+            ResponseEntity::Error(message) => Err(anyhow!(message)),
+            ResponseEntity::Fail(fail) => Err(anyhow!("could not load the state of {}: {:?}", self.info.address, fail))
+        }
+    }
+
+    // saves the state if anything changed since the last checkpoint, returns once the server has it
+    pub async fn checkpoint( &self ) -> Result<(),Error> {
+        let (state, version) = {
+            let chamber = self.chamber.lock().expect("expected state lock");
+            if chamber.version == chamber.saved {
+                return Ok(());
+            }
+            (chamber.state.clone(), chamber.version)
+        };
+        match self.exchange(ResourceOperation::Set(state)).await? {
+            ResponseEntity::Ok(_) => {
+                let mut chamber = self.chamber.lock().expect("expected state lock");
+                // changes made while the checkpoint was in flight keep the state dirty
+                if chamber.saved < version {
+                    chamber.saved = version;
+                }
+                Ok(())
+            }
+            ResponseEntity::Error(message) => Err(anyhow!(message)),
+            ResponseEntity::Fail(fail) => Err(anyhow!("could not checkpoint the state of {}: {:?}", self.info.address, fail))
+        }
+    }
+
+    // checkpoints every interval until the portal is Done
+    pub fn checkpoint_every( &self, interval: Duration ) {
+        let handle = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Status::Done = handle.status.read().expect("expected status read lock").status {
+                    break;
+                }
+                if let Err(err) = handle.checkpoint().await {
+                    std_logger(Log::Warn(format!("WARN: state checkpoint of {} failed: {}", handle.info.address, err)));
+                }
+            }
+        });
+    }
+
+    async fn exchange( &self, operation: ResourceOperation ) -> Result<ResponseEntity,Error> {
+        let mut request = inlet::Request::new(Operation::Resource(operation));
+        request.to.push(Identifier::Address(self.info.address.clone()));
+        let mut api = InletApi::new(self.info.clone(), self.inlet.clone(), self.exchanges.clone(), self.metrics.clone(), std_logger);
+        Ok(api.exchange(request).await?.signal)
+    }
+}
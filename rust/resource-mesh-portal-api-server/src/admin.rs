@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use resource_mesh_portal_metrics::PortalMetrics;
+use resource_mesh_portal_serde::version::latest::config::Info;
+use resource_mesh_portal_serde::version::latest::id::{Address, Key};
+use resource_mesh_portal_serde::version::latest::messaging::{ExchangeId, ExchangeKind};
+use resource_mesh_portal_serde::version::latest::portal::{inlet, outlet};
+use resource_mesh_portal_serde::version::latest::resource::Status;
+
+// the way the request of an exchange travelled.  Inlet: the portal asked and is
+// waiting for a response, Outlet: the portal was asked and still owes one
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ExchangeDirection {
+    Inlet,
+    Outlet
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingExchange {
+    pub id: ExchangeId,
+    pub direction: ExchangeDirection,
+    pub since: SystemTime
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PortalCounters {
+    pub frames_in: u64,
+    pub frames_out: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub exchanges_started: u64,
+    pub exchanges_completed: u64,
+    pub exchanges_timed_out: u64
+}
+
+impl From<&PortalMetrics> for PortalCounters {
+    fn from( metrics: &PortalMetrics ) -> Self {
+        Self {
+            frames_in: metrics.frames_in.get(),
+            frames_out: metrics.frames_out.get(),
+            bytes_in: metrics.bytes_in.get(),
+            bytes_out: metrics.bytes_out.get(),
+            exchanges_started: metrics.exchanges_started.get(),
+            exchanges_completed: metrics.exchanges_completed.get(),
+            exchanges_timed_out: metrics.exchanges_timed_out.get()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortalSnapshot {
+    pub info: Info,
+    // the last Status the portal reported
+    pub status: Status,
+    pub connected: SystemTime,
+    pub counters: PortalCounters,
+    pub pending: Vec<PendingExchange>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteEntry {
+    pub prefix: String,
+    pub target: String,
+    pub linked: bool
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingState {
+    // every address the muxer can deliver to, in address order
+    pub portals: Vec<(Address,Key)>,
+    pub routes: Vec<RouteEntry>
+}
+
+// what a portal's frames have told us so far, shared between the tasks that move its frames.
+// an exchange nobody answered within timeout has been given up on and is no longer pending
+pub(crate) struct Tracker {
+    pub status: Status,
+    pub pending: HashMap<ExchangeId,PendingExchange>,
+    timeout: Duration
+}
+
+impl Tracker {
+    pub fn new( timeout: Duration ) -> Self {
+        Self {
+            status: Status::Unknown,
+            pending: HashMap::new(),
+            timeout
+        }
+    }
+
+    pub fn inlet( &mut self, frame: &inlet::Frame ) {
+        match frame {
+            inlet::Frame::Request(request) => self.begin(&request.kind, ExchangeDirection::Inlet),
+            inlet::Frame::Response(response) => self.end(&response.exchange_id),
+            inlet::Frame::Status(status) => self.status = status.clone(),
+            _ => {}
+        }
+    }
+
+    pub fn outlet( &mut self, frame: &outlet::Frame ) {
+        match frame {
+            outlet::Frame::Request(request) => self.begin(&request.kind, ExchangeDirection::Outlet),
+            outlet::Frame::Response(response) => self.end(&response.exchange_id),
+            _ => {}
+        }
+    }
+
+    pub fn end( &mut self, exchange_id: &ExchangeId ) {
+        self.pending.remove(exchange_id);
+    }
+
+    fn begin( &mut self, kind: &ExchangeKind, direction: ExchangeDirection ) {
+        if let ExchangeKind::RequestResponse(exchange_id) = kind {
+            let timeout = self.timeout;
+            self.pending.retain(|_,pending| !expired(pending, timeout));
+            self.pending.insert(exchange_id.clone(), PendingExchange {
+                id: exchange_id.clone(),
+                direction,
+                since: SystemTime::now()
+            });
+        }
+    }
+
+    pub fn pending( &self ) -> Vec<PendingExchange> {
+        let mut pending: Vec<PendingExchange> = self.pending.values().filter(|pending| !expired(pending, self.timeout)).cloned().collect();
+        pending.sort_by_key(|a| a.since);
+        pending
+    }
+}
+
+fn expired( pending: &PendingExchange, timeout: Duration ) -> bool {
+    pending.since.elapsed().is_ok_and(|elapsed| elapsed > timeout)
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use resource_mesh_portal_serde::version::latest::id::{Address, Key};
+use resource_mesh_portal_serde::version::latest::resource::Status;
+
+use crate::MuxCall;
+
+// something that happened to one portal, every subscriber of MuxerHandle::events() hears of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortalEvent {
+    pub key: Key,
+    pub address: Address,
+    pub kind: PortalEventKind
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PortalEventKind {
+    Added,
+    Removed,
+    // every Status the portal reports
+    Status(Status),
+    // the portal panicked before it ever reported Ready
+    InitPanic(String),
+    // the transport received something it could not turn into a frame
+    FrameError(String)
+}
+
+// reports a portal's events through the same channel as the rest of its muxer calls so
+// they arrive in the order they happened, and only once the portal was added to a muxer.
+// the sender is weak so a reporter never keeps a dead portal's channel open
+#[derive(Clone)]
+pub struct PortalReporter {
+    key: Key,
+    address: Address,
+    mux_tx: mpsc::WeakSender<MuxCall>
+}
+
+impl PortalReporter {
+    pub(crate) fn new( key: Key, address: Address, mux_tx: &mpsc::Sender<MuxCall> ) -> Self {
+        Self {
+            key,
+            address,
+            mux_tx: mux_tx.downgrade()
+        }
+    }
+
+    pub fn frame_error( &self, message: String ) {
+        self.report(PortalEventKind::FrameError(message));
+    }
+
+    pub(crate) fn report( &self, kind: PortalEventKind ) {
+        let event = PortalEvent {
+            key: self.key.clone(),
+            address: self.address.clone(),
+            kind
+        };
+        if let Option::Some(mux_tx) = self.mux_tx.upgrade() {
+            mux_tx.try_send(MuxCall::Event(event)).unwrap_or_default();
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use resource_mesh_portal_serde::version::latest::config::Info;
+use resource_mesh_portal_serde::version::latest::id::{Address, Identifier, Key};
+use resource_mesh_portal_serde::version::latest::resource::{ResourceStub, Selector};
+
+// a Selector narrows the search through the indexes, the filter (if any) is then
+// applied to whatever the Selector let through
+pub type Filter = Box<dyn Fn(&Info)->bool+Send+Sync>;
+
+pub struct Query {
+    pub selector: Selector,
+    pub filter: Option<Filter>
+}
+
+impl Query {
+    pub fn all() -> Self {
+        Self::selector(Selector::new())
+    }
+
+    pub fn selector( selector: Selector ) -> Self {
+        Self {
+            selector,
+            filter: Option::None
+        }
+    }
+
+    pub fn filter( filter: impl Fn(&Info)->bool+Send+Sync+'static ) -> Self {
+        Self::all().with_filter(filter)
+    }
+
+    pub fn with_filter( mut self, filter: impl Fn(&Info)->bool+Send+Sync+'static ) -> Self {
+        self.filter = Option::Some(Box::new(filter));
+        self
+    }
+
+    pub fn matches( &self, info: &Info ) -> bool {
+        let selector = &self.selector;
+        if let Option::Some(kind) = &selector.kind {
+            if kind.to_string() != info.kind.to_string() {
+                return false;
+            }
+        }
+        if let Option::Some(owner) = &selector.owner {
+            if *owner != info.owner {
+                return false;
+            }
+        }
+        if let Option::Some(kind) = &selector.archetype_kind {
+            if *kind != info.archetype.kind {
+                return false;
+            }
+        }
+        if let Option::Some(specific) = &selector.specific {
+            if Option::Some(specific) != info.archetype.specific.as_ref() {
+                return false;
+            }
+        }
+        if let Option::Some(prefix) = &selector.address_prefix {
+            if !info.address.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        match &self.filter {
+            Some(filter) => filter(info),
+            None => true
+        }
+    }
+}
+
+impl From<Selector> for Query {
+    fn from( selector: Selector ) -> Self {
+        Self::selector(selector)
+    }
+}
+
+pub fn stub( info: &Info ) -> ResourceStub {
+    ResourceStub {
+        id: Identifier::Address(info.address.clone()),
+        key: info.key.clone(),
+        address: info.address.clone(),
+        archetype: info.archetype.clone()
+    }
+}
+
+// secondary indexes over the Info of every portal in the muxer so a Query only
+// ever looks at the portals that could possibly match it
+pub struct PortalIndex {
+    infos: HashMap<Key,Info>,
+    by_address: BTreeMap<Address,Key>,
+    by_kind: HashMap<String,HashSet<Key>>,
+    by_owner: HashMap<String,HashSet<Key>>,
+    by_archetype_kind: HashMap<String,HashSet<Key>>,
+    by_specific: HashMap<String,HashSet<Key>>
+}
+
+impl PortalIndex {
+    pub fn new() -> Self {
+        Self {
+            infos: HashMap::new(),
+            by_address: BTreeMap::new(),
+            by_kind: HashMap::new(),
+            by_owner: HashMap::new(),
+            by_archetype_kind: HashMap::new(),
+            by_specific: HashMap::new()
+        }
+    }
+
+    pub fn insert( &mut self, info: Info ) {
+        self.remove(&info.key);
+        let key = info.key.clone();
+        self.by_address.insert(info.address.clone(), key.clone());
+        insert(&mut self.by_kind, info.kind.to_string(), &key);
+        insert(&mut self.by_owner, info.owner.clone(), &key);
+        insert(&mut self.by_archetype_kind, info.archetype.kind.clone(), &key);
+        if let Option::Some(specific) = &info.archetype.specific {
+            insert(&mut self.by_specific, specific.clone(), &key);
+        }
+        self.infos.insert(key, info);
+    }
+
+    pub fn remove( &mut self, key: &Key ) -> Option<Info> {
+        let info = self.infos.remove(key)?;
+        self.by_address.remove(&info.address);
+        remove(&mut self.by_kind, &info.kind.to_string(), key);
+        remove(&mut self.by_owner, &info.owner, key);
+        remove(&mut self.by_archetype_kind, &info.archetype.kind, key);
+        if let Option::Some(specific) = &info.archetype.specific {
+            remove(&mut self.by_specific, specific, key);
+        }
+        Option::Some(info)
+    }
+
+    pub fn get( &self, key: &Key ) -> Option<&Info> {
+        self.infos.get(key)
+    }
+
+    pub fn key_for( &self, address: &Address ) -> Option<&Key> {
+        self.by_address.get(address)
+    }
+
+    pub fn addresses( &self ) -> impl Iterator<Item=(&Address,&Key)> {
+        self.by_address.iter()
+    }
+
+    pub fn len( &self ) -> usize {
+        self.infos.len()
+    }
+
+    pub fn is_empty( &self ) -> bool {
+        self.infos.is_empty()
+    }
+
+    pub fn select( &self, query: &Query ) -> Vec<ResourceStub> {
+        let selector = &query.selector;
+        // the wire is u64 whatever the platform, anything past usize is as good as unbounded
+        let offset = usize::try_from(selector.offset).unwrap_or(usize::MAX);
+        let limit = selector.limit.map_or(usize::MAX, |limit| usize::try_from(limit).unwrap_or(usize::MAX));
+
+        // an address prefix is a range of the address index, already in address order
+        if let Option::Some(prefix) = &selector.address_prefix {
+            return self.by_address.range(prefix.clone()..)
+                .take_while(|(address,_)| address.starts_with(prefix.as_str()))
+                .filter_map(|(_,key)| self.infos.get(key))
+                .filter(|info| query.matches(info))
+                .skip(offset)
+                .take(limit)
+                .map(stub)
+                .collect();
+        }
+
+        // otherwise start from the smallest candidate set any of the indexes offers
+        let mut candidates: Option<&HashSet<Key>> = Option::None;
+        let lookups = vec![
+            (&self.by_kind, selector.kind.as_ref().map(|kind| kind.to_string())),
+            (&self.by_owner, selector.owner.clone()),
+            (&self.by_archetype_kind, selector.archetype_kind.clone()),
+            (&self.by_specific, selector.specific.clone())
+        ];
+        for (index, value) in lookups {
+            if let Option::Some(value) = value {
+                match index.get(&value) {
+                    Some(keys) => {
+                        if candidates.is_none_or(|candidates| keys.len() < candidates.len()) {
+                            candidates = Option::Some(keys);
+                        }
+                    }
+                    None => return vec![]
+                }
+            }
+        }
+
+        match candidates {
+            Some(keys) => {
+                let mut infos: Vec<&Info> = keys.iter()
+                    .filter_map(|key| self.infos.get(key))
+                    .filter(|info| query.matches(info))
+                    .collect();
+                infos.sort_by(|a,b| a.address.cmp(&b.address));
+                infos.into_iter().skip(offset).take(limit).map(stub).collect()
+            }
+            None => {
+                self.by_address.values()
+                    .filter_map(|key| self.infos.get(key))
+                    .filter(|info| query.matches(info))
+                    .skip(offset)
+                    .take(limit)
+                    .map(stub)
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Default for PortalIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn insert( index: &mut HashMap<String,HashSet<Key>>, value: String, key: &Key ) {
+    index.entry(value).or_default().insert(key.clone());
+}
+
+fn remove( index: &mut HashMap<String,HashSet<Key>>, value: &String, key: &Key ) {
+    if let Option::Some(keys) = index.get_mut(value) {
+        keys.remove(key);
+        if keys.is_empty() {
+            index.remove(value);
+        }
+    }
+}
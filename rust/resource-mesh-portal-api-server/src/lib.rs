@@ -3,27 +3,48 @@ extern crate anyhow;
 
 use std::collections::HashMap;
 use std::future::Future;
-use std::prelude::rust_2021::TryInto;
-use std::sync::Arc;
-use std::time::Duration;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::Error;
-use futures::future::select_all;
-use futures::FutureExt;
-use tokio::sync::{mpsc, oneshot};
-use tokio::sync::mpsc::error::{SendError, SendTimeoutError};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_stream::{Stream, StreamExt, StreamMap};
+use tokio_stream::wrappers::ReceiverStream;
 use uuid::Uuid;
 
+use resource_mesh_portal_metrics as metrics;
+use resource_mesh_portal_metrics::{Gauge, PortalMetrics};
+
 use resource_mesh_portal_serde::message as request_message;
-use resource_mesh_portal_serde::version::latest::config::Info;
-use resource_mesh_portal_serde::version::latest::delivery::ResponseEntity;
+use resource_mesh_portal_serde::version::latest::config::{Config, Info};
+use resource_mesh_portal_serde::version::latest::delivery::{Entity, ResourceEntity, ResponseEntity};
+use resource_mesh_portal_serde::version::latest::fail::{mesh, resource, Fail, Standard, Timeout};
 use resource_mesh_portal_serde::version::latest::frame::CloseReason;
 use resource_mesh_portal_serde::version::latest::id::{Address, Identifier, Key};
 use resource_mesh_portal_serde::version::latest::log::Log;
 use resource_mesh_portal_serde::version::latest::messaging::{ExchangeId, ExchangeKind};
-use resource_mesh_portal_serde::version::latest::operation::{ExtOperation, Operation};
+use resource_mesh_portal_serde::version::latest::operation::{Operation, ResourceOperation};
 use resource_mesh_portal_serde::version::latest::portal::{inlet, outlet};
-use resource_mesh_portal_serde::version::latest::resource::Status;
+use resource_mesh_portal_serde::version::latest::resource::{ResourceStub, Status};
+use resource_mesh_portal_serde::version::WireVersion;
+
+use crate::admin::{PortalCounters, PortalSnapshot, RouteEntry, RoutingState, Tracker};
+use crate::event::{PortalEvent, PortalEventKind, PortalReporter};
+use crate::index::{PortalIndex, Query};
+use crate::policy::Policy;
+use crate::state::{MemStateStore, StateStore};
+
+pub mod admin;
+pub mod event;
+pub mod index;
+pub mod policy;
+pub mod router;
+pub mod state;
+pub mod throttle;
+
+pub use message::generic::Message;
+pub use resource_mesh_portal_serde::message::generic::{Request, Response};
 
 #[derive(Clone,Eq,PartialEq,Hash)]
 pub enum PortalStatus{
@@ -50,6 +71,13 @@ pub fn log( log: Log) {
     }
 }
 
+// the key of the host application when it talks to the portals itself, no portal may take it
+pub const HOST_KEY: &str = "host";
+
+pub fn host() -> Identifier {
+    Identifier::Key(HOST_KEY.to_string())
+}
+
 #[derive(Debug)]
 pub struct Exchange {
     pub id: ExchangeId,
@@ -60,13 +88,15 @@ pub struct Exchange {
 enum PortalCall {
     FrameIn(inlet::Frame),
     FrameOut(outlet::Frame),
-    Exchange(Exchange)
+    Exchange(Exchange),
+    // the transport is gone, nothing more will come in
+    InletClosed
 }
 
 pub struct Portal {
     pub info: Info,
     outlet_tx: mpsc::Sender<outlet::Frame>,
-    mux_tx: mpsc::Sender<MuxCall>,
+    transport_tx: mpsc::Sender<outlet::Frame>,
     pub log: fn(log:Log),
     status_tx: tokio::sync::broadcast::Sender<Status>,
 
@@ -75,7 +105,27 @@ pub struct Portal {
 
     call_tx: mpsc::Sender<PortalCall>,
     status: PortalStatus,
-    pub mux_rx: mpsc::Receiver<MuxCall>,
+    metrics: PortalMetrics,
+    tracker: Arc<Mutex<Tracker>>,
+    connected: SystemTime,
+    reporter: PortalReporter,
+    mux_rx: Option<mpsc::Receiver<MuxCall>>,
+    version: WireVersion
+}
+
+// the address a request target resolves to.  a Key is looked up in the muxer, which is only
+// worth the round trip when the policy has a rule that looks at targets
+async fn resolve( policy: &Policy, mux_tx: &mpsc::Sender<MuxCall>, to: &Identifier, timeout: u64 ) -> Option<Address> {
+    match to {
+        Identifier::Address(address) => Option::Some(address.clone()),
+        Identifier::Key(_) if !policy.targets() => Option::None,
+        Identifier::Key(_) => {
+            let (tx,rx) = oneshot::channel();
+            mux_tx.send(MuxCall::Lookup{ id: to.clone(), tx }).await.ok()?;
+            let info = tokio::time::timeout(Duration::from_secs(timeout), rx).await.ok()?.ok()??;
+            Option::Some(info.address)
+        }
+    }
 }
 
 impl Portal {
@@ -83,36 +133,81 @@ impl Portal {
         self.status.clone()
     }
 
-    pub fn new(info: Info, outlet_tx: mpsc::Sender<outlet::Frame>, inlet_rx: mpsc::Receiver<inlet::Frame>, logger: fn(log:Log) ) -> Self {
+    pub fn new(info: Info, outlet_tx: mpsc::Sender<outlet::Frame>, inlet_rx: mpsc::Receiver<inlet::Frame>, policy: Arc<Policy>, logger: fn(log:Log) ) -> Self {
 
         let (mux_tx,mux_rx) = tokio::sync::mpsc::channel(1024);
         let (status_tx,status_rx) = tokio::sync::broadcast::channel(8);
         let (call_tx,mut call_rx) = tokio::sync::mpsc::channel(1024);
+        let metrics = PortalMetrics::new(metrics::global(), "server", info.key.as_str(), info.address.as_str());
+        let tracker = Arc::new(Mutex::new(Tracker::new(Duration::from_secs(info.config.response_timeout))));
+        let reporter = PortalReporter::new(info.key.clone(), info.address.clone(), &mux_tx);
         {
             let command_tx = call_tx.clone();
+            let metrics = metrics.clone();
+            let tracker = tracker.clone();
+            let reporter = reporter.clone();
             let mut inlet_rx = inlet_rx;
             tokio::spawn(async move {
+                let mut ready = false;
                 while let Option::Some(frame) = inlet_rx.recv().await {
+                    metrics.frames_in.inc();
+                    metrics.bytes_in.add(bincode::serialized_size(&frame).unwrap_or_default());
+                    tracker.lock().expect("expected tracker lock").inlet(&frame);
+                    // reported from here so no status can arrive after the Remove that InletClosed leads to
+                    if let inlet::Frame::Status(status) = &frame {
+                        reporter.report(PortalEventKind::Status(status.clone()));
+                        match status {
+                            Status::Ready => ready = true,
+                            Status::Panic(message) if !ready => reporter.report(PortalEventKind::InitPanic(message.clone())),
+                            _ => {}
+                        }
+                    }
                     command_tx.send(PortalCall::FrameIn(frame)).await.unwrap_or_else(
                         |_err| {
                             logger(Log::Fatal("FATAL: could not send PortalCommand through command_tx channel".to_string()));
                         }
                     );
                 }
+                command_tx.send(PortalCall::InletClosed).await.unwrap_or_default();
             });
         }
 
+        let transport_tx = outlet_tx.clone();
+
+        // every outbound frame passes through here on its way to the transport so it can be counted
+        let outlet_tx = {
+            let (metered_tx, mut metered_rx) = mpsc::channel::<outlet::Frame>(1024);
+            let metrics = metrics.clone();
+            let tracker = tracker.clone();
+            tokio::spawn(async move {
+                while let Option::Some(frame) = metered_rx.recv().await {
+                    metrics.frames_out.inc();
+                    metrics.bytes_out.add(bincode::serialized_size(&frame).unwrap_or_default());
+                    tracker.lock().expect("expected tracker lock").outlet(&frame);
+                    if outlet_tx.send(frame).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            metered_tx
+        };
+
         {
             let mut exchanges:HashMap<ExchangeId,oneshot::Sender<inlet::Response>> =  HashMap::new();
-            let mux_tx= mux_tx.clone();
+            // the requests handed to this portal that still wait for its response, a response
+            // the portal sends for anything else is dropped rather than forwarded to the mesh
+            let mut delivered:HashMap<(Identifier,ExchangeId),Instant> = HashMap::new();
+            // this task holds the last mux_tx, once it ends the muxer sees this portal's calls end
+            let mux_tx = mux_tx;
             let outlet_tx = outlet_tx.clone();
             let info = info.clone();
             let status_tx = status_tx.clone();
+            let mut buckets = policy.throttle.portal_buckets(&info.config.rate_limit);
             tokio::spawn(async move {
 
                 match outlet_tx.send( outlet::Frame::Init(info.clone())).await {
                     Result::Ok(_) => {}
-                    Result::Err(err) => {
+                    Result::Err(_err) => {
                         logger(Log::Fatal("FATAL: could not send Frame::Init".to_string()));
                         mux_tx.try_send(MuxCall::Remove(Identifier::Key(info.key.clone()))).unwrap_or_default();
                         return;
@@ -127,14 +222,38 @@ impl Portal {
                                 }
                                 inlet::Frame::Command(_) => {}
                                 inlet::Frame::Request(request) => {
+                                    // a throttled request is answered right here and never reaches the muxer
+                                    let bytes = bincode::serialized_size(&request).unwrap_or_default();
+                                    if let Err(throttled) = policy.throttle.admit(&mut buckets, info.owner.as_str(), bytes) {
+                                        if let ExchangeKind::RequestResponse(exchange_id) = &request.kind {
+                                            // answered on behalf of the target the request never reached
+                                            let response = outlet::Response{
+                                                from: request.to.first().cloned().unwrap_or_else(|| Identifier::Key(info.key.clone())),
+                                                exchange_id: exchange_id.clone(),
+                                                signal: ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Throttled(throttled)))
+                                            };
+                                            let result = outlet_tx.send_timeout(outlet::Frame::Response(response), Duration::from_secs(info.config.frame_timeout) ).await;
+                                            if let Result::Err(_err) = result {
+                                                logger(Log::Fatal("FATAL: frame timeout error exit_tx".to_string()));
+                                            }
+                                        } else {
+                                            logger(Log::Warn(format!("WARN: notification from '{}' dropped, {} {} limit exceeded", info.address, throttled.scope, throttled.limit)));
+                                        }
+                                        continue;
+                                    }
                                     match &request.kind {
                                         ExchangeKind::None=> {
                                             logger(Log::Fatal("FATAL: received request with an invalid 'ExchangeKind::None'".to_string()))
                                         }
                                         ExchangeKind::Notification => {
                                             for to in &request.to {
+                                                let address = resolve(&policy, &mux_tx, to, info.config.frame_timeout).await;
+                                                if let Err(forbidden) = policy.evaluate(&info, to, address.as_ref(), &request.operation) {
+                                                    logger(Log::Warn(format!("WARN: notification {} from '{}' to '{}' forbidden by policy", forbidden.operation, forbidden.from, forbidden.to)));
+                                                    continue;
+                                                }
                                                 let request = request_message::inlet::Request::from( request.clone(), Identifier::Key(info.key.clone()), to.clone() );
-                                                let result = mux_tx.send_timeout(MuxCall::MessageIn(message::inlet::Message::Request(request)), Duration::from_secs(info.config.frame_timeout.clone())).await;
+                                                let result = mux_tx.send_timeout(MuxCall::MessageIn(message::inlet::Message::Request(request)), Duration::from_secs(info.config.frame_timeout)).await;
                                                 if let Result::Err(_err) = result {
                                                     logger(Log::Fatal("FATAL: send timeout error request_tx".to_string()))
                                                 }
@@ -147,14 +266,27 @@ impl Portal {
                                                     exchange_id: exchange_id.clone(),
                                                     signal: ResponseEntity::Error("a RequestResponse message must have one and only one to recipient.".to_string())
                                                 };
-                                                let result = outlet_tx.send_timeout(outlet::Frame::Response(response), Duration::from_secs(info.config.frame_timeout.clone()) ).await;
+                                                let result = outlet_tx.send_timeout(outlet::Frame::Response(response), Duration::from_secs(info.config.frame_timeout) ).await;
                                                 if let Result::Err(_err) = result {
                                                     logger(Log::Fatal("FATAL: frame timeout error exit_tx".to_string()));
                                                 }
                                             } else {
                                                 let to = request.to.first().expect("expected to identifier").clone();
+                                                let address = resolve(&policy, &mux_tx, &to, info.config.frame_timeout).await;
+                                                if let Err(forbidden) = policy.evaluate(&info, &to, address.as_ref(), &request.operation) {
+                                                    let response = outlet::Response{
+                                                        from: to,
+                                                        exchange_id: exchange_id.clone(),
+                                                        signal: ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Forbidden(forbidden)))
+                                                    };
+                                                    let result = outlet_tx.send_timeout(outlet::Frame::Response(response), Duration::from_secs(info.config.frame_timeout) ).await;
+                                                    if let Result::Err(_err) = result {
+                                                        logger(Log::Fatal("FATAL: frame timeout error exit_tx".to_string()));
+                                                    }
+                                                    continue;
+                                                }
                                                 let request = request_message::inlet::Request::from( request.clone(), Identifier::Key(info.key.clone()), to );
-                                                let result = mux_tx.send_timeout(MuxCall::MessageIn(message::inlet::Message::Request(request)), Duration::from_secs(info.config.frame_timeout.clone())).await;
+                                                let result = mux_tx.send_timeout(MuxCall::MessageIn(message::inlet::Message::Request(request)), Duration::from_secs(info.config.frame_timeout)).await;
                                                 if let Result::Err(_err) = result {
                                                     logger(Log::Fatal("FATAL: frame timeout error request_tx".to_string()));
                                                 }
@@ -166,11 +298,26 @@ impl Portal {
                                 inlet::Frame::Response(response) => {
                                     match exchanges.remove( &response.exchange_id ) {
                                         None => {
-                                            logger(Log::Fatal(format!("FATAL: missing request/response exchange id '{}'", response.exchange_id)));
+                                            // not an exchange this server started, so it must answer a request another portal sent
+                                            if delivered.remove(&(response.to.clone(), response.exchange_id.clone())).is_none() {
+                                                logger(Log::Warn(format!("WARN: dropped response for exchange '{}', no such request was delivered to '{}'", response.exchange_id, info.address)));
+                                                continue;
+                                            }
+                                            let to = response.to.clone();
+                                            let response = Response {
+                                                to,
+                                                from: Identifier::Key(info.key.clone()),
+                                                exchange_id: response.exchange_id,
+                                                signal: response.signal
+                                            };
+                                            let result = mux_tx.send_timeout(MuxCall::MessageIn(message::inlet::Message::Response(response)), Duration::from_secs(info.config.frame_timeout)).await;
+                                            if let Result::Err(_err) = result {
+                                                logger(Log::Fatal("FATAL: frame timeout error response_tx".to_string()));
+                                            }
                                         }
                                         Some(tx) => {
-                                            let tx = tx;
-                                            tx.send(response).expect("ability to send response");
+                                            // whoever started the exchange may have stopped waiting
+                                            tx.send(response).unwrap_or_default();
                                         }
                                     }
                                 }
@@ -178,16 +325,30 @@ impl Portal {
                                 inlet::Frame::Status(status) => {
                                     status_tx.send(status).unwrap_or_default();
                                 }
-                                inlet::Frame::Close(_) => {}
+                                inlet::Frame::Close(_) => {
+                                    // the client is shutting down, nothing will follow
+                                    mux_tx.try_send(MuxCall::Remove(Identifier::Key(info.key.clone()))).unwrap_or_default();
+                                }
                             }
                         }
                         PortalCall::Exchange(exchange) => {
                             exchanges.insert( exchange.id, exchange.tx );
                         }
+                        PortalCall::InletClosed => {
+                            // the portal can leave the muxer
+                            mux_tx.try_send(MuxCall::Remove(Identifier::Key(info.key.clone()))).unwrap_or_default();
+                            break;
+                        }
                         PortalCall::FrameOut(frame) => {
+                            if let outlet::Frame::Request(outlet::Request{ from, kind: ExchangeKind::RequestResponse(exchange_id), .. }) = &frame {
+                                // whoever asked has given up on requests older than the response_timeout
+                                let timeout = Duration::from_secs(info.config.response_timeout);
+                                delivered.retain(|_,delivered_at| delivered_at.elapsed() < timeout);
+                                delivered.insert((from.clone(), exchange_id.clone()), Instant::now());
+                            }
                             match outlet_tx.send_timeout(frame, Duration::from_secs(info.config.frame_timeout )).await {
                                 Ok(_) => {}
-                                Err(err) => {
+                                Err(_err) => {
                                     logger(Log::Fatal("FATAL: frame timeout error outlet_tx".to_string()));
                                 }
                             }
@@ -202,17 +363,28 @@ impl Portal {
             info,
             call_tx,
             outlet_tx,
+            transport_tx,
             status_tx,
             status_rx,
             status: PortalStatus::None,
             log: logger,
-            mux_tx,
-            mux_rx
+            metrics,
+            tracker,
+            connected: SystemTime::now(),
+            reporter,
+            mux_rx: Option::Some(mux_rx),
+            version: WireVersion::latest()
         }
     }
 
+    // the wire version the client speaks, a transport that talks to an older client says so
+    pub fn with_version(mut self, version: WireVersion ) -> Self {
+        self.version = version;
+        self
+    }
+
     pub async fn send(&self, frame: outlet::Frame ) -> Result<(), Error> {
-        self.outlet_tx.send_timeout(frame, Duration::from_secs( self.info.config.frame_timeout.clone() ) ).await?;
+        self.outlet_tx.send_timeout(frame, Duration::from_secs( self.info.config.frame_timeout ) ).await?;
         Ok(())
     }
 
@@ -222,22 +394,100 @@ impl Portal {
         request.kind = ExchangeKind::RequestResponse(exchange_id.clone());
         let (tx,rx) = tokio::sync::oneshot::channel();
         let exchange = Exchange {
-            id: exchange_id,
+            id: exchange_id.clone(),
             tx
         };
-        self.call_tx.send_timeout(PortalCall::Exchange(exchange), Duration::from_secs(self.info.config.frame_timeout.clone()) ).await?;
+        self.call_tx.send_timeout(PortalCall::Exchange(exchange), Duration::from_secs(self.info.config.frame_timeout) ).await?;
+        self.send(outlet::Frame::Request(request)).await?;
+        self.metrics.exchanges_started.inc();
 
-        Ok(rx.await?)
+        let started = Instant::now();
+        match tokio::time::timeout(Duration::from_secs(self.info.config.response_timeout), rx).await {
+            Ok(response) => {
+                self.metrics.exchanges_completed.inc();
+                self.metrics.exchange_seconds.observe_duration(started.elapsed());
+                Ok(response?)
+            }
+            Err(err) => {
+                self.metrics.exchanges_timed_out.inc();
+                self.tracker.lock().expect("expected tracker lock").end(&exchange_id);
+                Err(err.into())
+            }
+        }
     }
 
+    // for the transport, which alone knows when a frame could not be decoded
+    pub fn reporter(&self) -> PortalReporter {
+        self.reporter.clone()
+    }
+
+    pub fn snapshot(&self) -> PortalSnapshot {
+        let tracker = self.tracker.lock().expect("expected tracker lock");
+        PortalSnapshot {
+            info: self.info.clone(),
+            status: tracker.status.clone(),
+            connected: self.connected,
+            counters: PortalCounters::from(&self.metrics),
+            pending: tracker.pending()
+        }
+    }
 
     pub fn shutdown(&mut self) {
-        self.outlet_tx.try_send(outlet::Frame::Close(CloseReason::Done)).unwrap_or(());
+        self.close(CloseReason::Done);
+    }
+
+    pub fn close(&mut self, reason: CloseReason) {
+        self.outlet_tx.try_send(outlet::Frame::Close(reason)).unwrap_or(());
+    }
+
+    // asks the client to save its State and resolves once it has or the response_timeout is up.
+    // a 0.0.1 client cannot be asked & one that never got Ready has nothing to save, so there
+    // is nothing to wait for
+    pub fn checkpoint(&self) -> impl Future<Output=()> {
+        let call_tx = self.call_tx.clone();
+        let outlet_tx = self.outlet_tx.clone();
+        let ask = self.version != WireVersion::V0_0_1 && matches!(self.tracker.lock().expect("expected tracker lock").status, Status::Ready);
+        let address = self.info.address.clone();
+        let timeout = Duration::from_secs(self.info.config.response_timeout);
+        let logger = self.log;
+        async move {
+            if !ask {
+                return;
+            }
+            let exchange_id: ExchangeId = Uuid::new_v4().to_string();
+            let (tx,rx) = oneshot::channel();
+            let exchange = Exchange {
+                id: exchange_id.clone(),
+                tx
+            };
+            if call_tx.send(PortalCall::Exchange(exchange)).await.is_err() || outlet_tx.send(outlet::Frame::Checkpoint(exchange_id)).await.is_err() {
+                return;
+            }
+            match tokio::time::timeout(timeout, rx).await {
+                Ok(Ok(inlet::Response{ signal: ResponseEntity::Ok(_), .. })) => {}
+                Ok(Ok(inlet::Response{ signal, .. })) => {
+                    logger(Log::Error(format!("ERROR: final state checkpoint of {} failed: {:?}", address, signal)));
+                }
+                _ => {
+                    logger(Log::Warn(format!("WARN: {} did not checkpoint its state before it was closed", address)));
+                }
+            }
+        }
+    }
+
+    // resolves once the transport has stopped taking frames, which a transport
+    // does right after it has passed on a Close frame
+    pub fn flushed(&self) -> impl Future<Output=()> {
+        let transport_tx = self.transport_tx.clone();
+        let timeout = Duration::from_secs(self.info.config.frame_timeout);
+        async move {
+            tokio::time::timeout(timeout, transport_tx.closed()).await.unwrap_or_default();
+        }
     }
 
     pub async fn init(&mut self) -> Result<(), Error> {
         if self.status != PortalStatus::None {
-            let message = format!("{} has already received the init signal.",self.info.kind.to_string());
+            let message = format!("{} has already received the init signal.",self.info.kind);
             return Err(anyhow!(message));
         }
 
@@ -251,16 +501,16 @@ impl Portal {
         tokio::spawn( async move {
             loop {
                 let _status = if config.init_timeout > 0 {
-                    match tokio::time::timeout( Duration::from_secs(config.init_timeout.clone() ), status_rx.recv() ).await {
+                    match tokio::time::timeout( Duration::from_secs(config.init_timeout ), status_rx.recv() ).await {
                         Ok(Ok(status)) => {
                             status
                         }
                         Ok(Result::Err(err)) => {
-                            tx.send(Result::Err(format!("ERROR: when waiting for {} status: 'Ready' message: '{}'",kind.to_string(),err.to_string())) ).expect("ability to send error");
+                            tx.send(Result::Err(format!("ERROR: when waiting for {} status: 'Ready' message: '{}'",kind,err)) ).expect("ability to send error");
                             break;
                         }
                         Err(_err) => {
-                            tx.send(Result::Err(format!("PANIC: {} init timeout after '{}' seconds", kind.to_string(), config.init_timeout.clone() ).into()) ).expect("ability to send error");
+                            tx.send(Result::Err(format!("PANIC: {} init timeout after '{}' seconds", kind, config.init_timeout.clone() )) ).expect("ability to send error");
                             break;
                         }
                     }
@@ -268,7 +518,7 @@ impl Portal {
                     match status_rx.recv().await {
                         Ok(status) => status,
                         Err(err) => {
-                            tx.send(Result::Err(format!("ERROR: when waiting for {} status: 'Ready' message: '{}'",kind.to_string(),err.to_string())) ).expect("ability to send error");
+                            tx.send(Result::Err(format!("ERROR: when waiting for {} status: 'Ready' message: '{}'",kind,err)) ).expect("ability to send error");
                             break;
                         }
                     }
@@ -282,7 +532,7 @@ impl Portal {
                                 break;
                             }
                             Status::Panic(message) => {
-                                tx.send(Result::Err(format!("PANIC: {} panic on init message: '{}'", kind.to_string(), message).into()) ).expect("ability to send error");
+                                tx.send(Result::Err(format!("PANIC: {} panic on init message: '{}'", kind, message)) ).expect("ability to send error");
                                 break;
                             }
                             _ => {
@@ -291,7 +541,7 @@ impl Portal {
                         }
                     }
                     Err(err) => {
-                        tx.send(Result::Err(format!("ERROR: when waiting for {} status: 'Ready' message: '{}'", kind.to_string(), err.to_string()).into())).expect("ability to send error");
+                        tx.send(Result::Err(format!("ERROR: when waiting for {} status: 'Ready' message: '{}'", kind, err))).expect("ability to send error");
                         break;
                     }
                 }
@@ -316,12 +566,22 @@ impl Portal {
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum MuxCall {
     Add(Portal),
     Remove(Identifier),
-    Select{ selector: fn(info:&Info)->bool, tx: oneshot::Sender<Vec<Info>> },
-    MessageIn(inlet::Message),
-    MessageOut(outlet::Message)
+    Select{ query: Query, tx: oneshot::Sender<Vec<ResourceStub>> },
+    Lookup{ id: Identifier, tx: oneshot::Sender<Option<Info>> },
+    Portals(oneshot::Sender<Vec<PortalSnapshot>>),
+    Portal{ id: Identifier, tx: oneshot::Sender<Option<PortalSnapshot>> },
+    Close{ id: Identifier, reason: CloseReason, tx: oneshot::Sender<bool> },
+    Routing(oneshot::Sender<RoutingState>),
+    MessageIn(message::inlet::Message),
+    MessageOut(message::outlet::Message),
+    HostExchange{ request: Request<Operation>, tx: oneshot::Sender<Response> },
+    Event(PortalEvent),
+    Drain,
+    Shutdown
 }
 
 pub mod message {
@@ -370,131 +630,496 @@ pub mod message {
 
 
 pub trait Router: Send+Sync {
-    fn route( &self, message: inlet::Message );
+    fn route( &self, message: message::inlet::Message );
     fn logger( &self, message: &str ) {
         println!("{}", message );
     }
+
+    // routes beyond the portals of the muxer itself, if the router knows any
+    fn routes( &self ) -> Vec<RouteEntry> {
+        vec![]
+    }
+}
+
+// the only way to talk to a running PortalMuxer
+#[derive(Clone)]
+pub struct MuxerHandle {
+    tx: mpsc::Sender<MuxCall>,
+    events_tx: broadcast::Sender<PortalEvent>
+}
+
+impl MuxerHandle {
+    pub async fn add( &self, portal: Portal ) -> Result<(),Error> {
+        self.call(MuxCall::Add(portal)).await
+    }
+
+    pub async fn remove( &self, id: Identifier ) -> Result<(),Error> {
+        self.call(MuxCall::Remove(id)).await
+    }
+
+    pub async fn select( &self, query: impl Into<Query> ) -> Result<Vec<ResourceStub>,Error> {
+        let (tx,rx) = oneshot::channel();
+        self.call(MuxCall::Select{ query: query.into(), tx }).await?;
+        Ok(rx.await?)
+    }
+
+    pub async fn lookup( &self, id: Identifier ) -> Result<Option<Info>,Error> {
+        let (tx,rx) = oneshot::channel();
+        self.call(MuxCall::Lookup{ id, tx }).await?;
+        Ok(rx.await?)
+    }
+
+    pub async fn portals( &self ) -> Result<Vec<PortalSnapshot>,Error> {
+        let (tx,rx) = oneshot::channel();
+        self.call(MuxCall::Portals(tx)).await?;
+        Ok(rx.await?)
+    }
+
+    pub async fn portal( &self, id: Identifier ) -> Result<Option<PortalSnapshot>,Error> {
+        let (tx,rx) = oneshot::channel();
+        self.call(MuxCall::Portal{ id, tx }).await?;
+        Ok(rx.await?)
+    }
+
+    // returns false if there was no such portal
+    pub async fn close( &self, id: Identifier, reason: CloseReason ) -> Result<bool,Error> {
+        let (tx,rx) = oneshot::channel();
+        self.call(MuxCall::Close{ id, reason, tx }).await?;
+        Ok(rx.await?)
+    }
+
+    pub async fn routing( &self ) -> Result<RoutingState,Error> {
+        let (tx,rx) = oneshot::channel();
+        self.call(MuxCall::Routing(tx)).await?;
+        Ok(rx.await?)
+    }
+
+    pub async fn send( &self, message: message::outlet::Message ) -> Result<(),Error> {
+        self.call(MuxCall::MessageOut(message)).await
+    }
+
+    pub async fn route( &self, message: message::inlet::Message ) -> Result<(),Error> {
+        self.call(MuxCall::MessageIn(message)).await
+    }
+
+    // request enters the mesh from host() as if a portal had sent it, though no portal's Policy applies
+    pub async fn inject( &self, request: Request<Operation> ) -> Result<(),Error> {
+        let mut request = request;
+        request.from = host();
+        self.route(message::inlet::Message::Request(request)).await
+    }
+
+    // injects request as a RequestResponse exchange, the returned future resolves to its response
+    pub async fn exchange( &self, request: Request<Operation> ) -> Result<impl Future<Output=Result<Response,Error>>,Error> {
+        let mut request = request;
+        let exchange_id: ExchangeId = Uuid::new_v4().to_string();
+        request.from = host();
+        request.kind = ExchangeKind::RequestResponse(exchange_id.clone());
+        let (tx,rx) = oneshot::channel();
+        self.call(MuxCall::HostExchange{ request, tx }).await?;
+        Ok(async move {
+            rx.await.map_err(|_| anyhow!("host exchange '{}' was abandoned by the portal muxer", exchange_id))
+        })
+    }
+
+    // no more portals are admitted and the portals already in get until timeout to
+    // settle their pending exchanges, then every portal is closed with CloseReason::Done
+    pub async fn drain( &self, timeout: Duration ) -> Result<(),Error> {
+        self.call(MuxCall::Drain).await?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let pending: usize = self.portals().await?.iter().map(|portal| portal.pending.len()).sum();
+            if pending == 0 || Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        self.shutdown().await
+    }
+
+    // returns after every portal was asked for a last checkpoint of its State, was sent
+    // its Close frame and the muxer is gone
+    pub async fn shutdown( &self ) -> Result<(),Error> {
+        self.call(MuxCall::Shutdown).await?;
+        self.tx.closed().await;
+        Ok(())
+    }
+
+    pub fn events( &self ) -> broadcast::Receiver<PortalEvent> {
+        self.events_tx.subscribe()
+    }
+
+    pub fn is_closed( &self ) -> bool {
+        self.tx.is_closed()
+    }
+
+    async fn call( &self, call: MuxCall ) -> Result<(),Error> {
+        self.tx.send(call).await.map_err(|_| anyhow!("portal muxer has shutdown"))
+    }
 }
 
 pub struct PortalMuxer {
     portals: HashMap<Identifier,Portal>,
     router: Box<dyn Router>,
-    address_to_key: HashMap<Address,Key>,
-    key_to_address: HashMap<Key,Address>,
-    mux_tx: mpsc::Sender<MuxCall>,
+    index: PortalIndex,
+    portal_rxs: StreamMap<Key,Pin<Box<dyn Stream<Item=MuxCall>+Send>>>,
     mux_rx: mpsc::Receiver<MuxCall>,
+    connected: Arc<Gauge>,
+    queue_depth: Arc<Gauge>,
+    draining: bool,
+    events_tx: broadcast::Sender<PortalEvent>,
+    host_exchanges: HashMap<ExchangeId,HostWaiter>,
+    state: Arc<dyn StateStore>,
+    state_tx: mpsc::UnboundedSender<StateJob>
+}
+
+type StateJob = Box<dyn FnOnce()+Send>;
+
+// the host waiting on the response to a RequestResponse exchange it injected
+struct HostWaiter {
+    to: Identifier,
+    tx: oneshot::Sender<Response>,
+    timeout: Duration,
+    deadline: Instant
 }
 
 impl PortalMuxer {
-    pub fn new( mux_tx: mpsc::Sender<MuxCall>, mux_rx: mpsc::Receiver<MuxCall>, router: Box<dyn Router> ) {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new( router_factory: impl FnOnce(MuxerHandle) -> Box<dyn Router> ) -> MuxerHandle {
+        Self::new_with_state(router_factory, Arc::new(MemStateStore::new()))
+    }
+
+    // state keeps what every portal saves of its own State, the muxer itself answers
+    // a Get or Set a portal sends to its own address
+    pub fn new_with_state( router_factory: impl FnOnce(MuxerHandle) -> Box<dyn Router>, state: Arc<dyn StateStore> ) -> MuxerHandle {
+        let (mux_tx, mux_rx) = mpsc::channel(1024);
+        let (events_tx, _) = broadcast::channel(1024);
+        let handle = MuxerHandle {
+            tx: mux_tx,
+            events_tx: events_tx.clone()
+        };
+
+        // a store may block on its disk, so it is called off the muxer task.  the calls run one
+        // after the other so a portal's Get always sees the Set it sent before
+        let (state_tx, mut state_rx) = mpsc::unbounded_channel::<StateJob>();
+        tokio::spawn(async move {
+            while let Option::Some(job) = state_rx.recv().await {
+                tokio::task::spawn_blocking(job).await.unwrap_or_default();
+            }
+        });
 
         let mut muxer = Self {
             portals: HashMap::new(),
-            address_to_key: HashMap::new(),
-            key_to_address: HashMap::new(),
-            router,
-            mux_tx,
-            mux_rx
+            index: PortalIndex::new(),
+            router: router_factory(handle.clone()),
+            portal_rxs: StreamMap::new(),
+            mux_rx,
+            connected: metrics::global().gauge("portal_muxer_portals", "Portals connected to the muxer.", &[]),
+            queue_depth: metrics::global().gauge("portal_muxer_queue_depth", "Calls waiting in the muxer queue.", &[]),
+            draining: false,
+            events_tx,
+            host_exchanges: HashMap::new(),
+            state,
+            state_tx
         };
 
         tokio::spawn( async move {
+            while let Option::Some(call) = muxer.next().await {
+                muxer.queue_depth.set(muxer.mux_rx.len() as i64);
+                if let MuxCall::Shutdown = call {
+                    break;
+                }
+                muxer.handle(call);
+            }
 
-            let mut ids = vec![];
-            let mut futures = vec![];
+            // every portal saves its State one last time, the muxer goes on answering
+            // the Sets that takes until all of them have
+            muxer.draining = true;
+            let checkpoints = futures::future::join_all(muxer.portals.values().map(|portal| portal.checkpoint()).collect::<Vec<_>>());
+            tokio::pin!(checkpoints);
+            loop {
+                let call = tokio::select! {
+                    _ = &mut checkpoints => break,
+                    call = muxer.next() => call
+                };
+                match call {
+                    Some(MuxCall::Shutdown) => {}
+                    Some(call) => muxer.handle(call),
+                    None => break
+                }
+            }
 
-            for (key,portal) in &mut muxer.portals {
-                futures.push( portal.mux_rx.recv().boxed() );
-                ids.push(key.clone());
+            let mut flushed = vec![];
+            let portals: Vec<Portal> = muxer.portals.drain().map(|(_,portal)| portal).collect();
+            for mut portal in portals {
+                muxer.connected.dec();
+                metrics::global().forget("key", portal.info.key.as_str());
+                muxer.emit(&portal.info, PortalEventKind::Removed);
+                portal.shutdown();
+                flushed.push(portal.flushed());
             }
+            futures::future::join_all(flushed).await;
+            muxer.router.logger("INFO: portal muxer shutdown");
+        } );
 
-            futures.push( muxer.mux_rx.recv().boxed() );
+        handle
+    }
 
-            let (call, future_index, _) = select_all(futures).await;
+    // calls from the handle and calls from every connected portal are serviced alike
+    async fn next( &mut self ) -> Option<MuxCall> {
+        loop {
+            let expiry = self.host_exchanges.values().map(|waiter| waiter.deadline).min();
+            tokio::select! {
+                call = self.mux_rx.recv() => return call,
+                Some((_,call)) = self.portal_rxs.next(), if !self.portal_rxs.is_empty() => return Option::Some(call),
+                _ = tokio::time::sleep_until(expiry.unwrap_or_else(Instant::now).into()), if expiry.is_some() => self.expire_host_exchanges()
+            }
+        }
+    }
 
-            match call {
-                None => {
-                    if future_index >= ids.len() {
-                        // shutdown
-                        return;
-                    } else {
-                        let key = ids.get(future_index).expect("expected key");
-                        if let Option::Some(mut portal) = muxer.portals.remove(key) {
-                            portal.shutdown();
-                        }
+    fn handle( &mut self, call: MuxCall ) {
+        match call {
+            MuxCall::Add(mut portal) if self.draining => {
+                self.router.logger(format!("WARN: {} at address {} refused, the portal muxer is shutting down", portal.info.kind, portal.info.address ).as_str() );
+                portal.close(CloseReason::Error("portal muxer is shutting down".to_string()));
+            }
+            MuxCall::Add(mut portal) if portal.info.key == HOST_KEY => {
+                self.router.logger(format!("WARN: {} at address {} refused, key '{}' is reserved for the host", portal.info.kind, portal.info.address, HOST_KEY ).as_str() );
+                portal.close(CloseReason::Error(format!("key '{}' is reserved", HOST_KEY)));
+            }
+            MuxCall::Add(mut portal) => {
+                let kind = portal.info.kind.clone();
+                let address = portal.info.address.clone();
+                if let Option::Some(mux_rx) = portal.mux_rx.take() {
+                    // the calls end once the portal's tasks are gone, whatever is left of it is removed then
+                    let removal = tokio_stream::once(MuxCall::Remove(Identifier::Key(portal.info.key.clone())));
+                    self.portal_rxs.insert(portal.info.key.clone(), Box::pin(ReceiverStream::new(mux_rx).chain(removal)));
+                }
+                self.index.insert(portal.info.clone());
+                self.emit(&portal.info, PortalEventKind::Added);
+                if self.portals.insert(Identifier::Key(portal.info.key.clone()), portal ).is_none() {
+                    self.connected.inc();
+                }
+                self.router.logger(format!("INFO: {} add to portal muxer at address {}", kind, address ).as_str() );
+            }
+            MuxCall::Remove(id) => {
+                if let Option::Some(mut portal) = self.remove(&id) {
+                    portal.shutdown();
+                }
+            }
+            MuxCall::Close { id, reason, tx } => {
+                match self.remove(&id) {
+                    Some(mut portal) => {
+                        portal.close(reason);
+                        tx.send(true).unwrap_or_default();
+                    }
+                    None => {
+                        tx.send(false).unwrap_or_default();
                     }
                 }
-                Some(call) => {
-                    match call {
-                        MuxCall::Add(portal) => {
-                            let kind = portal.info.kind.clone();
-                            let address = portal.info.address.clone();
-                            muxer.key_to_address.insert(portal.info.key.clone(), portal.info.address.clone() );
-                            muxer.address_to_key.insert(portal.info.address.clone(), portal.info.key.clone() );
-                            muxer.portals.insert(Identifier::Key(portal.info.key.clone()), portal );
-                            muxer.router.logger(format!("INFO: {} add to portal muxer at address {}", kind.to_string(), address ).as_str() );
-                        }
-                        MuxCall::Remove(id) => {
-                            let key = match &id {
-                                Identifier::Key(key) => {
-                                    Option::Some(key)
-                                }
-                                Identifier::Address(address) => {
-                                    muxer.address_to_key.get(address)
-                                }
-                            };
-
-                            if let Option::Some( key ) = key {
-                                if let Option::Some(mut portal) = muxer.portals.remove(&Identifier::Key(key.clone()) ) {
-                                    muxer.key_to_address.remove(&portal.info.key);
-                                    muxer.address_to_key.remove(&portal.info.address);
-
-                                    muxer.router.logger(format!("INFO: {} removed from portal muxer at address {}", portal.info.kind.to_string(), portal.info.address ).as_str() );
-                                    portal.shutdown();
-                                }
-                            }
-                        }
-                        MuxCall::MessageIn(message) => {
-                            muxer.router.route( message );
-                        }
-                        MuxCall::MessageOut(message) => {
-                            match muxer.get_portal(&message.to())
-                            {
-                                Some(portal) => {
-                                    match message {
-                                        outlet::Message::Request(request) => {
-                                            portal.call_tx.try_send( PortalCall::FrameOut( outlet::Frame::Request(request.into())));
-                                        }
-                                        outlet::Message::Response(response) => {
-                                            portal.call_tx.try_send( PortalCall::FrameOut( outlet::Frame::Response(response.into())));
-                                        }
-                                    }
-                                }
-                                None => {}
-                            }
+            }
+            MuxCall::Portals(tx) => {
+                let portals = self.index.addresses()
+                    .filter_map(|(_,key)| self.portals.get(&Identifier::Key(key.clone())))
+                    .map(|portal| portal.snapshot())
+                    .collect();
+                tx.send(portals).unwrap_or_default();
+            }
+            MuxCall::Portal { id, tx } => {
+                tx.send(self.get_portal(&id).map(|portal| portal.snapshot())).unwrap_or_default();
+            }
+            MuxCall::Routing(tx) => {
+                let routing = RoutingState {
+                    portals: self.index.addresses().map(|(address,key)| (address.clone(), key.clone())).collect(),
+                    routes: self.router.routes()
+                };
+                tx.send(routing).unwrap_or_default();
+            }
+            MuxCall::MessageIn(message::inlet::Message::Response(response)) if response.to == host() => {
+                self.host_response(response);
+            }
+            MuxCall::MessageIn(message::inlet::Message::Request(request)) if self.is_own_state(&request) => {
+                self.own_state(request);
+            }
+            MuxCall::MessageIn(message) => {
+                self.router.route( message );
+            }
+            MuxCall::MessageOut(message::outlet::Message::Response(response)) if response.to == host() => {
+                self.host_response(response);
+            }
+            MuxCall::MessageOut(message) => {
+                if let Some(portal) = self.get_portal(&message.to()) {
+                    match message {
+                        message::outlet::Message::Request(request) => {
+                            portal.call_tx.try_send( PortalCall::FrameOut( outlet::Frame::Request(request.into()))).unwrap_or_default();
                         }
-                        MuxCall::Select { selector, tx } => {
-                            let mut rtn = vec![];
-                            for portal in muxer.portals.values() {
-                                if selector(&portal.info) {
-                                    rtn.push(portal.info.clone());
-                                }
-                            }
-                            tx.send(rtn).unwrap_or_default();
+                        message::outlet::Message::Response(response) => {
+                            portal.call_tx.try_send( PortalCall::FrameOut( outlet::Frame::Response(response.into()))).unwrap_or_default();
                         }
                     }
                 }
             }
+            MuxCall::Select { query, tx } => {
+                tx.send(self.index.select(&query)).unwrap_or_default();
+            }
+            MuxCall::Lookup { id, tx } => {
+                tx.send(self.get_portal(&id).map(|portal| portal.info.clone())).unwrap_or_default();
+            }
+            MuxCall::HostExchange { request, tx } => {
+                if let ExchangeKind::RequestResponse(exchange_id) = &request.kind {
+                    // whoever stopped waiting for a response left a closed sender behind
+                    self.host_exchanges.retain(|_,waiter| !waiter.tx.is_closed());
+                    // a target that is not in this muxer is given the default response_timeout
+                    let timeout = match self.get_portal(&request.to) {
+                        Some(portal) => portal.info.config.response_timeout,
+                        None => Config::default().response_timeout
+                    };
+                    let timeout = Duration::from_secs(timeout);
+                    let waiter = HostWaiter {
+                        to: request.to.clone(),
+                        tx,
+                        timeout,
+                        deadline: Instant::now() + timeout
+                    };
+                    self.host_exchanges.insert(exchange_id.clone(), waiter);
+                }
+                self.router.route( message::inlet::Message::Request(request) );
+            }
+            MuxCall::Event(event) => {
+                self.events_tx.send(event).unwrap_or_default();
+            }
+            MuxCall::Drain => {
+                self.draining = true;
+            }
+            MuxCall::Shutdown => {}
+        }
+    }
+
+    fn emit( &self, info: &Info, kind: PortalEventKind ) {
+        let event = PortalEvent {
+            key: info.key.clone(),
+            address: info.address.clone(),
+            kind
+        };
+        self.events_tx.send(event).unwrap_or_default();
+    }
+
+    fn is_own_state( &self, request: &Request<Operation> ) -> bool {
+        match &request.operation {
+            Operation::Resource(ResourceOperation::Get) | Operation::Resource(ResourceOperation::Set(_)) => {
+                match (self.get_portal(&request.to), self.get_portal(&request.from)) {
+                    (Some(to), Some(from)) => to.info.key == from.info.key,
+                    _ => false
+                }
+            }
+            _ => false
+        }
+    }
 
-        } );
+    fn own_state( &self, request: Request<Operation> ) {
+        let portal = match self.get_portal(&request.from) {
+            Some(portal) => portal,
+            None => return
+        };
+        let store = self.state.clone();
+        let address = portal.info.address.clone();
+        let call_tx = portal.call_tx.clone();
+        let logger = portal.log;
+        let job = move || {
+            let result = match request.operation {
+                Operation::Resource(ResourceOperation::Get) => {
+                    store.get(&address).map(|state| Entity::Resource(ResourceEntity::State(state.unwrap_or_default())))
+                }
+                Operation::Resource(ResourceOperation::Set(state)) => {
+                    store.set(&address, state).map(|_| Entity::Empty)
+                }
+                _ => return
+            };
+            let signal = match result {
+                Ok(entity) => ResponseEntity::Ok(entity),
+                Err(err) => {
+                    logger(Log::Error(format!("ERROR: state store failed for {}: {}", address, err)));
+                    ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Error(err.to_string())))
+                }
+            };
+            if let ExchangeKind::RequestResponse(exchange_id) = request.kind {
+                let response = Response {
+                    to: request.from,
+                    from: request.to,
+                    exchange_id,
+                    signal
+                };
+                call_tx.try_send( PortalCall::FrameOut( outlet::Frame::Response(response.into()))).unwrap_or_default();
+            }
+        };
+        self.state_tx.send(Box::new(job)).unwrap_or_default();
+    }
+
+    fn host_response( &mut self, response: Response ) {
+        match self.host_exchanges.remove(&response.exchange_id) {
+            Some(waiter) => {
+                waiter.tx.send(response).unwrap_or_default();
+            }
+            None => {
+                self.router.logger(format!("WARN: dropped response for exchange '{}', the host is not waiting for it", response.exchange_id).as_str());
+            }
+        }
+    }
+
+    // the host is answered with a Timeout for every exchange its target let run past the response_timeout
+    fn expire_host_exchanges( &mut self ) {
+        let now = Instant::now();
+        let expired: Vec<ExchangeId> = self.host_exchanges.iter()
+            .filter(|(_,waiter)| waiter.deadline <= now)
+            .map(|(exchange_id,_)| exchange_id.clone())
+            .collect();
+        for exchange_id in expired {
+            if let Option::Some(waiter) = self.host_exchanges.remove(&exchange_id) {
+                let message = format!("no response to host exchange '{}' within {}s", exchange_id, waiter.timeout.as_secs());
+                self.router.logger(format!("WARN: {}", message).as_str());
+                let timeout = Timeout {
+                    waited: waiter.timeout.as_secs() as i32,
+                    message
+                };
+                let response = Response {
+                    to: host(),
+                    from: waiter.to,
+                    exchange_id,
+                    signal: ResponseEntity::Fail(Fail::Resource(resource::Fail::Standard(Standard::Timeout(timeout))))
+                };
+                waiter.tx.send(response).unwrap_or_default();
+            }
+        }
+    }
+
+    fn remove( &mut self, id: &Identifier ) -> Option<Portal> {
+        let key = match id {
+            Identifier::Key(key) => {
+                Option::Some(key.clone())
+            }
+            Identifier::Address(address) => {
+                self.index.key_for(address).cloned()
+            }
+        }?;
+
+        self.portal_rxs.remove(&key);
+        let portal = self.portals.remove(&Identifier::Key(key.clone()) )?;
+        self.index.remove(&portal.info.key);
+        self.connected.dec();
+        metrics::global().forget("key", portal.info.key.as_str());
+        self.emit(&portal.info, PortalEventKind::Removed);
+
+        self.router.logger(format!("INFO: {} removed from portal muxer at address {}", portal.info.kind, portal.info.address ).as_str() );
+        Option::Some(portal)
     }
 
     fn get_portal( &self, id: &Identifier ) -> Option<&Portal> {
         match id {
-            Identifier::Key(key) => {
+            Identifier::Key(_) => {
                 self.portals.get(id)
             }
             Identifier::Address(address) => {
-                let key = self.address_to_key.get(address );
+                let key = self.index.key_for(address );
                 match key {
                     Some(key) => {
                         self.portals.get(&Identifier::Key(key.clone()))
@@ -507,4 +1132,4 @@ impl PortalMuxer {
         }
 
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,288 @@
+use resource_mesh_portal_serde::version::latest::config::{Info, PortalKind, RateLimit};
+use resource_mesh_portal_serde::version::latest::fail::mesh::Forbidden;
+use resource_mesh_portal_serde::version::latest::id::{identifier_name, Address, Identifier};
+use resource_mesh_portal_serde::version::latest::operation::{ExtOperation, Operation, ResourceOperation};
+
+use crate::throttle::Throttle;
+
+// a very small glob: '*' matches any run of characters (including none), everything else must match exactly
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pattern {
+    pattern: String
+}
+
+impl Pattern {
+    pub fn new( pattern: &str ) -> Self {
+        Self {
+            pattern: pattern.to_string()
+        }
+    }
+
+    pub fn any() -> Self {
+        Self::new("*")
+    }
+
+    pub fn matches( &self, value: &str ) -> bool {
+        let mut parts = self.pattern.split('*');
+        let first = parts.next().unwrap_or("");
+        if !value.starts_with(first) {
+            return false;
+        }
+        let mut remaining = &value[first.len()..];
+        let parts: Vec<&str> = parts.collect();
+        if parts.is_empty() {
+            return remaining.is_empty();
+        }
+        let (last, middle) = parts.split_last().expect("expected at least one part");
+        for part in middle {
+            match remaining.find(part) {
+                Some(index) => remaining = &remaining[index+part.len()..],
+                None => return false
+            }
+        }
+        remaining.ends_with(last)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum OperationPattern {
+    Any,
+    Create,
+    Select,
+    Get,
+    Set,
+    Delete,
+    Http,
+    Port(Pattern)
+}
+
+impl OperationPattern {
+    pub fn matches( &self, operation: &Operation ) -> bool {
+        match (self, operation) {
+            (OperationPattern::Any, _) => true,
+            (OperationPattern::Create, Operation::Resource(ResourceOperation::Create(_))) => true,
+            (OperationPattern::Select, Operation::Resource(ResourceOperation::Select(_))) => true,
+            (OperationPattern::Get, Operation::Resource(ResourceOperation::Get)) => true,
+            (OperationPattern::Set, Operation::Resource(ResourceOperation::Set(_))) => true,
+            (OperationPattern::Delete, Operation::Resource(ResourceOperation::Delete)) => true,
+            (OperationPattern::Http, Operation::Ext(ExtOperation::Http(_))) => true,
+            (OperationPattern::Port(pattern), Operation::Ext(ExtOperation::Port(port))) => pattern.matches(port.port.as_str()),
+            _ => false
+        }
+    }
+}
+
+pub fn operation_name( operation: &Operation ) -> String {
+    match operation {
+        Operation::Resource(ResourceOperation::Create(_)) => "Create".to_string(),
+        Operation::Resource(ResourceOperation::Select(_)) => "Select".to_string(),
+        Operation::Resource(ResourceOperation::Get) => "Get".to_string(),
+        Operation::Resource(ResourceOperation::Set(_)) => "Set".to_string(),
+        Operation::Resource(ResourceOperation::Delete) => "Delete".to_string(),
+        Operation::Ext(ExtOperation::Http(_)) => "Http".to_string(),
+        Operation::Ext(ExtOperation::Port(port)) => format!("Port({})", port.port)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Effect {
+    Allow,
+    Deny
+}
+
+// every condition left as None matches anything.  to is matched on the address the target resolved to,
+// so a target addressed by Key cannot slip past a rule written for its address
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub effect: Effect,
+    pub owner: Option<Pattern>,
+    pub from: Option<Pattern>,
+    pub kind: Option<PortalKind>,
+    pub to: Option<Pattern>,
+    pub operations: Vec<OperationPattern>
+}
+
+impl Rule {
+    pub fn allow( name: &str ) -> Self {
+        Self::new(name, Effect::Allow)
+    }
+
+    pub fn deny( name: &str ) -> Self {
+        Self::new(name, Effect::Deny)
+    }
+
+    fn new( name: &str, effect: Effect ) -> Self {
+        Self {
+            name: name.to_string(),
+            effect,
+            owner: None,
+            from: None,
+            kind: None,
+            to: None,
+            operations: vec![]
+        }
+    }
+
+    pub fn owner( mut self, owner: &str ) -> Self {
+        self.owner = Option::Some(Pattern::new(owner));
+        self
+    }
+
+    pub fn from( mut self, address: &str ) -> Self {
+        self.from = Option::Some(Pattern::new(address));
+        self
+    }
+
+    pub fn kind( mut self, kind: PortalKind ) -> Self {
+        self.kind = Option::Some(kind);
+        self
+    }
+
+    pub fn to( mut self, address: &str ) -> Self {
+        self.to = Option::Some(Pattern::new(address));
+        self
+    }
+
+    pub fn operation( mut self, operation: OperationPattern ) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    pub fn matches( &self, from: &Info, to: &Address, operation: &Operation ) -> bool {
+        self.matches_caller(&Caller::portal(from), to, operation)
+    }
+
+    fn matches_caller( &self, from: &Caller, to: &Address, operation: &Operation ) -> bool {
+        if let Option::Some(owner) = &self.owner {
+            if !owner.matches(from.owner) {
+                return false;
+            }
+        }
+        if let Option::Some(address) = &self.from {
+            if !address.matches(from.address) {
+                return false;
+            }
+        }
+        if let Option::Some(kind) = &self.kind {
+            // a caller from beyond a gateway link is of no kind this server knows
+            if from.kind.is_none_or(|from| kind.to_string() != from.to_string()) {
+                return false;
+            }
+        }
+        if let Option::Some(address) = &self.to {
+            if !address.matches(to.as_str()) {
+                return false;
+            }
+        }
+        self.operations.is_empty() || self.operations.iter().any(|pattern| pattern.matches(operation))
+    }
+}
+
+// whoever sent a request, as far as the rules are concerned
+struct Caller<'a> {
+    owner: &'a str,
+    address: &'a str,
+    kind: Option<&'a PortalKind>
+}
+
+impl <'a> Caller<'a> {
+    fn portal( info: &'a Info ) -> Self {
+        Self {
+            owner: info.owner.as_str(),
+            address: info.address.as_str(),
+            kind: Option::Some(&info.kind)
+        }
+    }
+}
+
+// rules are evaluated in order and the first match decides.  when nothing matches the default effect applies
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub rules: Vec<Rule>,
+    pub default: Effect,
+    pub throttle: Throttle
+}
+
+impl Policy {
+    pub fn allow_all() -> Self {
+        Self {
+            rules: vec![],
+            default: Effect::Allow,
+            throttle: Throttle::new()
+        }
+    }
+
+    pub fn deny_all() -> Self {
+        Self {
+            rules: vec![],
+            default: Effect::Deny,
+            throttle: Throttle::new()
+        }
+    }
+
+    pub fn with_rule( mut self, rule: Rule ) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn with_portal_limit( mut self, limit: RateLimit ) -> Self {
+        self.throttle = self.throttle.with_portal_limit(limit);
+        self
+    }
+
+    pub fn with_owner_limit( mut self, limit: RateLimit ) -> Self {
+        self.throttle = self.throttle.with_owner_limit(limit);
+        self
+    }
+
+    // true when some rule looks at the target, only then must a Key target be resolved to its address
+    pub fn targets( &self ) -> bool {
+        self.rules.iter().any(|rule| rule.to.is_some())
+    }
+
+    // address is what to resolved to, a target that could not be resolved is denied whenever a rule looks at targets
+    pub fn evaluate( &self, from: &Info, to: &Identifier, address: Option<&Address>, operation: &Operation ) -> Result<(),Forbidden> {
+        self.evaluate_caller(&Caller::portal(from), to, address, operation)
+    }
+
+    // a request that came over a gateway link is owned by the link it came over
+    pub fn evaluate_link( &self, link: &str, from: &Address, to: &Identifier, address: Option<&Address>, operation: &Operation ) -> Result<(),Forbidden> {
+        let caller = Caller {
+            owner: link,
+            address: from.as_str(),
+            kind: Option::None
+        };
+        self.evaluate_caller(&caller, to, address, operation)
+    }
+
+    fn evaluate_caller( &self, from: &Caller, to: &Identifier, address: Option<&Address>, operation: &Operation ) -> Result<(),Forbidden> {
+        let (effect, rule) = match address {
+            Some(address) => match self.rules.iter().find(|rule| rule.matches_caller(from, address, operation)) {
+                Some(rule) => (rule.effect.clone(), Option::Some(rule.name.clone())),
+                None => (self.default.clone(), Option::None)
+            },
+            None if self.targets() => (Effect::Deny, Option::None),
+            None => match self.rules.iter().find(|rule| rule.matches_caller(from, &identifier_name(to), operation)) {
+                Some(rule) => (rule.effect.clone(), Option::Some(rule.name.clone())),
+                None => (self.default.clone(), Option::None)
+            }
+        };
+
+        match effect {
+            Effect::Allow => Ok(()),
+            Effect::Deny => Err(Forbidden {
+                from: from.address.to_string(),
+                to: identifier_name(to),
+                operation: operation_name(operation),
+                rule
+            })
+        }
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::allow_all()
+    }
+}
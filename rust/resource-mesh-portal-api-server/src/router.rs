@@ -0,0 +1,130 @@
+use tokio::sync::mpsc;
+
+use resource_mesh_portal_serde::version::latest::delivery::{Entity, ResourceEntity, ResponseEntity};
+use resource_mesh_portal_serde::version::latest::fail::{mesh, resource, Fail, NotFound, Standard};
+use resource_mesh_portal_serde::version::latest::id::{identifier_name, Identifier};
+use resource_mesh_portal_serde::version::latest::messaging::ExchangeKind;
+use resource_mesh_portal_serde::version::latest::operation::{Operation, ResourceOperation};
+
+use crate::{message, MuxerHandle, Request, Response, Router};
+
+// delivers messages between the portals of a single muxer.  requests carrying an ExtOperation
+// are handed to the portal they address, responses find their way back to the requester by
+// the 'to' the requesting portal stamped on them.  there is no mesh behind this router
+// so the only ResourceOperation it can answer is Select (from the muxer's indexes)
+pub struct LocalRouter {
+    tx: mpsc::Sender<message::inlet::Message>,
+    logger: fn( message: &str )
+}
+
+impl LocalRouter {
+    pub fn new( muxer: MuxerHandle ) -> Self {
+        Self::new_with_logger(muxer, |message| println!("{}", message))
+    }
+
+    pub fn new_with_logger( muxer: MuxerHandle, logger: fn( message: &str ) ) -> Self {
+        let (tx, mut rx) = mpsc::channel(1024);
+        tokio::spawn(async move {
+            // one message at a time so nothing overtakes what was sent before it
+            while let Option::Some(message) = rx.recv().await {
+                match message {
+                    message::inlet::Message::Request(request) => {
+                        Self::route_request(&muxer, request).await;
+                    }
+                    message::inlet::Message::Response(response) => {
+                        Self::route_response(&muxer, response, logger).await;
+                    }
+                }
+            }
+        });
+
+        Self {
+            tx,
+            logger
+        }
+    }
+
+    async fn route_request( muxer: &MuxerHandle, request: Request<Operation> ) {
+        let request = match request.operation {
+            Operation::Ext(operation) => Request {
+                to: request.to,
+                from: request.from,
+                operation,
+                kind: request.kind,
+                idempotency_key: request.idempotency_key
+            },
+            Operation::Resource(ResourceOperation::Select(selector)) => {
+                match muxer.select(selector).await {
+                    Ok(stubs) => {
+                        Self::respond(muxer, &request.from, &request.to, &request.kind, ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(stubs)))).await;
+                    }
+                    Err(err) => {
+                        Self::respond(muxer, &request.from, &request.to, &request.kind, ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Error(err.to_string())))).await;
+                    }
+                }
+                return;
+            }
+            Operation::Resource(_) => {
+                let fail = Fail::Mesh(mesh::Fail::Error("resource operations other than Select require a mesh".to_string()));
+                Self::respond(muxer, &request.from, &request.to, &request.kind, ResponseEntity::Fail(fail)).await;
+                return;
+            }
+        };
+
+        match muxer.lookup(request.to.clone()).await {
+            Ok(Option::Some(_)) => {
+                muxer.send(message::outlet::Message::Request(request)).await.unwrap_or_default();
+            }
+            Ok(Option::None) => {
+                Self::respond(muxer, &request.from, &request.to, &request.kind, not_found(&request.to)).await;
+            }
+            Err(_) => {
+                // the muxer has shutdown
+            }
+        }
+    }
+
+    async fn route_response( muxer: &MuxerHandle, response: Response, logger: fn( message: &str ) ) {
+        match muxer.lookup(response.to.clone()).await {
+            Ok(Option::Some(_)) => {
+                muxer.send(message::outlet::Message::Response(response)).await.unwrap_or_default();
+            }
+            Ok(Option::None) => {
+                logger(format!("WARN: dropped response for exchange '{}', requester {} is gone", response.exchange_id, identifier_name(&response.to)).as_str());
+            }
+            Err(_) => {}
+        }
+    }
+
+    async fn respond( muxer: &MuxerHandle, requester: &Identifier, responder: &Identifier, kind: &ExchangeKind, signal: ResponseEntity ) {
+        if let ExchangeKind::RequestResponse(exchange_id) = kind {
+            let response = Response {
+                to: requester.clone(),
+                from: responder.clone(),
+                exchange_id: exchange_id.clone(),
+                signal
+            };
+            muxer.send(message::outlet::Message::Response(response)).await.unwrap_or_default();
+        }
+    }
+}
+
+impl Router for LocalRouter {
+    fn route( &self, message: message::inlet::Message ) {
+        if self.tx.try_send(message).is_err() {
+            self.logger("ERROR: local router queue is full, message dropped");
+        }
+    }
+
+    fn logger( &self, message: &str ) {
+        (self.logger)(message);
+    }
+}
+
+pub fn not_found( to: &Identifier ) -> ResponseEntity {
+    let not_found = match to {
+        Identifier::Key(key) => NotFound::Key(key.clone()),
+        Identifier::Address(address) => NotFound::Address(address.clone())
+    };
+    ResponseEntity::Fail(Fail::Resource(resource::Fail::Standard(Standard::NotFound(not_found))))
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Error;
+
+use resource_mesh_portal_serde::version::latest::State;
+use resource_mesh_portal_serde::version::latest::id::Address;
+
+// where the State of every portal is kept, by address so it outlives the portal's connection.
+// the muxer calls it on a blocking thread, one call at a time, so an implementation may wait on i/o
+pub trait StateStore: Send+Sync {
+    fn get( &self, address: &Address ) -> Result<Option<State>,Error>;
+    fn set( &self, address: &Address, state: State ) -> Result<(),Error>;
+}
+
+// lasts as long as the process, which is enough for a portal that reconnects
+pub struct MemStateStore {
+    states: Mutex<HashMap<Address,State>>
+}
+
+impl Default for MemStateStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemStateStore {
+    pub fn new() -> Self {
+        Self {
+            states: Mutex::new(HashMap::new())
+        }
+    }
+}
+
+impl StateStore for MemStateStore {
+    fn get( &self, address: &Address ) -> Result<Option<State>,Error> {
+        Ok(self.states.lock().expect("expected states lock").get(address).cloned())
+    }
+
+    fn set( &self, address: &Address, state: State ) -> Result<(),Error> {
+        self.states.lock().expect("expected states lock").insert(address.clone(), state);
+        Ok(())
+    }
+}
+
+// one bincode file per address under dir
+pub struct DirStateStore {
+    dir: PathBuf
+}
+
+impl DirStateStore {
+    pub fn new( dir: PathBuf ) -> Result<Self,Error> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir
+        })
+    }
+
+    // addresses are free to contain characters a file name cannot
+    fn path( &self, address: &Address ) -> PathBuf {
+        let mut name = String::new();
+        for c in address.chars() {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                name.push(c);
+            } else {
+                for b in c.to_string().as_bytes() {
+                    name.push_str(format!("_{:02x}", b).as_str());
+                }
+            }
+        }
+        self.dir.join(format!("{}.state", name))
+    }
+}
+
+impl StateStore for DirStateStore {
+    fn get( &self, address: &Address ) -> Result<Option<State>,Error> {
+        match std::fs::read(self.path(address)) {
+            Ok(data) => Ok(Option::Some(bincode::deserialize(data.as_slice())?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Option::None),
+            Err(err) => Err(err.into())
+        }
+    }
+
+    // written aside & renamed into place so a crash never leaves half a state behind
+    fn set( &self, address: &Address, state: State ) -> Result<(),Error> {
+        let path = self.path(address);
+        let tmp = path.with_extension("state.tmp");
+        std::fs::write(&tmp, bincode::serialize(&state)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
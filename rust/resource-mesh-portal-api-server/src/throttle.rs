@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use resource_mesh_portal_serde::version::latest::config::RateLimit;
+use resource_mesh_portal_serde::version::latest::fail::mesh::Throttled;
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    rate: f64,
+    last: Instant
+}
+
+impl TokenBucket {
+    // holds one second worth of tokens so short bursts are tolerated
+    pub fn new( per_sec: f64 ) -> Self {
+        Self {
+            capacity: per_sec,
+            tokens: per_sec,
+            rate: per_sec,
+            last: Instant::now()
+        }
+    }
+
+    fn refill( &mut self, now: Instant ) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last = now;
+    }
+
+    // an amount larger than the whole bucket is admitted once the bucket is full and
+    // leaves it in debt, otherwise it could never be admitted at all
+    fn check( &mut self, amount: f64, now: Instant ) -> Result<(),Duration> {
+        self.refill(now);
+        let needed = amount.min(self.capacity);
+        if self.tokens >= needed {
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((needed - self.tokens) / self.rate))
+        }
+    }
+
+    fn take( &mut self, amount: f64 ) {
+        self.tokens -= amount;
+    }
+
+    // a full bucket is no different from a new one
+    fn is_full( &mut self, now: Instant ) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Buckets {
+    requests: Option<TokenBucket>,
+    bytes: Option<TokenBucket>
+}
+
+impl Buckets {
+    pub fn new( limit: &RateLimit ) -> Self {
+        Self {
+            requests: if limit.requests_per_sec > 0 { Option::Some(TokenBucket::new(limit.requests_per_sec as f64)) } else { Option::None },
+            bytes: if limit.bytes_per_sec > 0 { Option::Some(TokenBucket::new(limit.bytes_per_sec as f64)) } else { Option::None }
+        }
+    }
+
+    fn check( &mut self, scope: &str, bytes: u64, now: Instant ) -> Result<(),Throttled> {
+        if let Option::Some(requests) = &mut self.requests {
+            requests.check(1.0, now).map_err(|wait| throttled(scope, "requests_per_sec", wait))?;
+        }
+        if let Option::Some(bucket) = &mut self.bytes {
+            bucket.check(bytes as f64, now).map_err(|wait| throttled(scope, "bytes_per_sec", wait))?;
+        }
+        Ok(())
+    }
+
+    fn take( &mut self, bytes: u64 ) {
+        if let Option::Some(requests) = &mut self.requests {
+            requests.take(1.0);
+        }
+        if let Option::Some(bucket) = &mut self.bytes {
+            bucket.take(bytes as f64);
+        }
+    }
+
+    fn is_idle( &mut self, now: Instant ) -> bool {
+        self.requests.as_mut().is_none_or(|bucket| bucket.is_full(now)) && self.bytes.as_mut().is_none_or(|bucket| bucket.is_full(now))
+    }
+}
+
+// how often the owner buckets are swept for the ones that have filled up again
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct Owners {
+    buckets: HashMap<String,Buckets>,
+    swept: Instant
+}
+
+impl Owners {
+    fn new() -> Self {
+        Self {
+            buckets: HashMap::new(),
+            swept: Instant::now()
+        }
+    }
+
+    // an idle bucket is dropped since it would be created again just as it was
+    fn sweep( &mut self, now: Instant ) {
+        if now.saturating_duration_since(self.swept) >= SWEEP_INTERVAL {
+            self.buckets.retain(|_,buckets| !buckets.is_idle(now));
+            self.swept = now;
+        }
+    }
+}
+
+fn throttled( scope: &str, limit: &str, wait: Duration ) -> Throttled {
+    Throttled {
+        scope: scope.to_string(),
+        limit: limit.to_string(),
+        retry_after_millis: wait.as_millis() as u64
+    }
+}
+
+// owner buckets are shared by every portal of the same owner, so one Throttle
+// should be shared by every portal a server creates
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    pub portal_limit: Option<RateLimit>,
+    pub owner_limit: Option<RateLimit>,
+    owners: Arc<Mutex<Owners>>
+}
+
+impl Throttle {
+    pub fn new() -> Self {
+        Self {
+            portal_limit: Option::None,
+            owner_limit: Option::None,
+            owners: Arc::new(Mutex::new(Owners::new()))
+        }
+    }
+
+    pub fn with_portal_limit( mut self, limit: RateLimit ) -> Self {
+        self.portal_limit = Option::Some(limit);
+        self
+    }
+
+    pub fn with_owner_limit( mut self, limit: RateLimit ) -> Self {
+        self.owner_limit = Option::Some(limit);
+        self
+    }
+
+    // the server's portal limit wins over whatever the portal's Config asks for
+    pub fn portal_buckets( &self, config_limit: &RateLimit ) -> Buckets {
+        match &self.portal_limit {
+            Some(limit) => Buckets::new(limit),
+            None => Buckets::new(config_limit)
+        }
+    }
+
+    // nothing is taken from any bucket unless every bucket admits the request
+    pub fn admit( &self, portal: &mut Buckets, owner: &str, bytes: u64 ) -> Result<(),Throttled> {
+        let now = Instant::now();
+        portal.check("portal", bytes, now)?;
+        if let Some(limit) = &self.owner_limit {
+            let mut owners = self.owners.lock().expect("expected owner buckets lock");
+            owners.sweep(now);
+            let owner = owners.buckets.entry(owner.to_string()).or_insert_with(|| Buckets::new(limit));
+            owner.check("owner", bytes, now)?;
+            owner.take(bytes);
+        }
+        portal.take(bytes);
+        Ok(())
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,9 +1,12 @@
+#[cfg(test)]
 #[macro_use]
 extern crate async_trait;
 
+#[cfg(test)]
 #[macro_use]
 extern crate anyhow;
 
+#[cfg(test)]
 #[macro_use]
 extern crate lazy_static;
 
@@ -16,45 +19,77 @@ lazy_static! {
         tokio::sync::broadcast::channel(128).0
     };
 
+    // addresses of the DrainingPortalCtrls that had their on_close hook called
+    static ref SHUTDOWN_TX : tokio::sync::broadcast::Sender<String> = {
+        tokio::sync::broadcast::channel(128).0
+    };
+
+    static ref LIFECYCLE_TX : tokio::sync::broadcast::Sender<(String,String)> = {
+        tokio::sync::broadcast::channel(128).0
+    };
+
 }
 
+    use std::net::SocketAddr;
     use std::sync::atomic::{AtomicU32, Ordering};
     use std::sync::Arc;
 
     use tokio::net::TcpStream;
-    use tokio::runtime::{Builder, Runtime};
+
     use tokio::sync::{mpsc, oneshot};
 
     use anyhow::Error;
-    use resource_mesh_portal_api_client::{InletApi, PortalCtrl, PortalSkel, client, PortCtrl};
-    use resource_mesh_portal_api_server::{Message, MuxCall, Portal, PortalMuxer, Router};
+    use resource_mesh_portal_api_client::{Inlet, PortalCtrl, PortalSkel, client, PortCtrl};
+    use resource_mesh_portal_api_client::http::{self, HttpCall, HttpRouter};
+    use resource_mesh_portal_api_client::middleware::{self, Incoming, Middleware, Next};
+    use resource_mesh_portal_api_client::retry::{Backoff, RetryPolicy};
+    use resource_mesh_portal_api_server::{host, Message, MuxerHandle, PortalMuxer, Router};
 
+    use resource_mesh_portal_api_server::event::{PortalEvent, PortalEventKind};
+    use resource_mesh_portal_api_server::index::Query;
+    use resource_mesh_portal_api_server::policy::{OperationPattern, Pattern, Policy, Rule};
+    use resource_mesh_portal_api_server::router::LocalRouter;
+    use resource_mesh_portal_api_server::state::DirStateStore;
+    use resource_mesh_portal_mem::PortalMemClient;
+    use resource_mesh_portal_metrics as metrics;
     use resource_mesh_portal_tcp_client::{PortalClient, PortalTcpClient};
+    use resource_mesh_portal_tcp_client::auth::{BearerCredentials, Credentials, HmacCredentials, PasswordCredentials};
+    use resource_mesh_portal_tcp_client::uds::PortalUdsClient;
+    use resource_mesh_portal_tcp_server::admin::{self, AdminClient};
+    use resource_mesh_portal_tcp_server::gateway::{Gateway, LinkFrame, LinkRequest, LinkResponse};
+    use resource_mesh_portal_tcp_server::http::HttpGateway;
+    use resource_mesh_portal_tcp_server::auth::{Authenticators, BearerAuthenticator, CredentialStore, HmacAuthenticator};
+    use resource_mesh_portal_tcp_server::uds::{PortalUdsServer, UdsServerConfig};
     use resource_mesh_portal_tcp_common::{
-        FrameReader, FrameWriter, PrimitiveFrameReader, PrimitiveFrameWriter,
+        FrameReader, FrameTooLarge, PrimitiveFrameReader, PrimitiveFrameWriter,
     };
-    use resource_mesh_portal_tcp_server::{Call, Event, PortalServer, PortalTcpServer};
+    use resource_mesh_portal_tcp_server::{Call, Event, EventResult, PortalAcceptor, PortalServer, PortalTcpServer};
+    use resource_mesh_portal_tcp_common::tls::{ClientAuth, PeerIdentity, TlsClientConfig, TlsServerConfig};
     use std::collections::HashMap;
-    use std::convert::TryInto;
-    use std::io::Write;
-    use std::thread;
-    use tokio::io;
-    use tokio::io::AsyncWriteExt;
+    use std::convert::{TryFrom, TryInto};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::sync::broadcast::Receiver;
-    use tokio::sync::mpsc::Sender;
-    use tokio::sync::oneshot::error::RecvError;
+
     use tokio::time::Duration;
-    use resource_mesh_portal_serde::version::latest::resource::{Status, ResourceStub, Selector};
+    use resource_mesh_portal_serde::version::latest::resource::{Status, Selector};
     use resource_mesh_portal_serde::version::latest::operation::{Operation, ResourceOperation, ExtOperation, PortOperation};
-    use resource_mesh_portal_serde::version::latest::config::{Info, PortalKind};
+    use resource_mesh_portal_serde::version::latest::config::{Info, PortalKind, RateLimit};
     use resource_mesh_portal_serde::version::latest::id::Identifier;
     use resource_mesh_portal_serde::version::latest::messaging::ExchangeKind;
     use resource_mesh_portal_serde::version::latest::delivery::{Entity, Payload, ResponseEntity};
     use resource_mesh_portal_serde::version::latest::resource::Archetype;
     use resource_mesh_portal_serde::version::latest::delivery::ResourceEntity;
-    use resource_mesh_portal_serde::version::latest::portal::inlet;
+    use resource_mesh_portal_serde::version::latest::portal::{inlet, outlet};
+    use resource_mesh_portal_serde::version::latest::frame::{CloseReason, PrimitiveFrame};
+    use resource_mesh_portal_serde::version::latest::http::{HttpRequest, HttpResponse};
+    use resource_mesh_portal_serde::version::latest::bin::Bin;
+    use serde::{Deserialize, Serialize};
+    use resource_mesh_portal_serde::version::latest::fail::{mesh, resource, Fail, NotFound, Standard};
+    use resource_mesh_portal_serde::version::{v0_0_1, WireVersion};
 
     #[derive(Clone)]
+    #[allow(dead_code)]
     pub enum GlobalEvent {
         Finished(String),
         Fail(String),
@@ -63,22 +98,24 @@ lazy_static! {
 
     #[tokio::test]
     async fn server_up() -> Result<(), Error> {
-        let port = 32355;
-        let server = PortalTcpServer::new(port, Box::new(TestPortalServer::new()));
+        // the builder binds before it returns so the clients below cannot beat the listener
+        let handle = PortalTcpServer::builder(Box::new(TestPortalServer::new())).start().await?;
+        let addr = handle.addr();
+        let server = handle.call_tx();
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
 
         {
             let server = server.clone();
             tokio::spawn(async move {
                 let mut finished_count = 0;
-                let mut GLOBAL_RX = GLOBAL_TX.subscribe();
-                while let Result::Ok(event) = GLOBAL_RX.recv().await {
+                let mut global_rx = GLOBAL_TX.subscribe();
+                while let Result::Ok(event) = global_rx.recv().await {
                     match event {
                         GlobalEvent::Finished(_) => {
-                            finished_count = finished_count+1;
+                            finished_count += 1;
                         }
                         GlobalEvent::Fail(_) => {
-                            finished_count = finished_count+1;
+                            finished_count += 1;
                         }
                         GlobalEvent::Shutdown => {
                             server.send( Call::Shutdown ).await.unwrap_or_default();
@@ -97,64 +134,1520 @@ lazy_static! {
             let server = server.clone();
             tokio::spawn(async move {
                 let (listen_tx, listen_rx) = tokio::sync::oneshot::channel();
-                server.send(Call::ListenEvents(listen_tx)).await;
+                server.send(Call::ListenEvents(listen_tx)).await.unwrap_or_default();
                 let mut broadcast_rx = listen_rx.await.unwrap();
                 while let Result::Ok(event) = broadcast_rx.recv().await {
                     if let Event::Status(status) = &event {
-                        println!("event: Status({})", status.to_string());
+                        println!("event: Status({})", status);
                     } else {
-                        println!("event: {}", event.to_string());
+                        println!("event: {}", event);
                     }
                     match event {
                         Event::Status(Status::Done) => {
-                            shutdown_tx.send(());
+                            shutdown_tx.send(()).unwrap_or_default();
                             return;
                         }
                         Event::Status(Status::Panic(error)) => {
                             eprintln!("PANIC: {}", error);
-                            shutdown_tx.send(());
+                            shutdown_tx.send(()).unwrap_or_default();
                             return;
                         }
 
-                        _ => {}
-                    }
-                }
-            });
-        }
+                        _ => {}
+                    }
+                }
+            });
+        }
+
+        let client1 = Box::new(TestPortalClient::new("scott".to_string()));
+        let client2 = Box::new(TestPortalClient::new("fred".to_string()));
+
+        let _client1 = PortalTcpClient::new(addr.to_string(), client1).await?;
+        let _client2 = PortalTcpClient::new(addr.to_string(), client2).await?;
+
+        tokio::spawn( async move {
+            tokio::time::sleep( Duration::from_secs( 5 ) ).await;
+            GLOBAL_TX.send( GlobalEvent::Shutdown ).unwrap_or_default();
+        });
+
+        shutdown_rx.await.unwrap_or_default();
+        tokio::time::timeout(Duration::from_secs(15), handle.join()).await??;
+
+        println!("got to the end...");
+        Ok(())
+    }
+
+    // subscribes to the server's events, then waits for Ready unless the server got there first
+    async fn ready_events(server: &mpsc::Sender<Call>) -> Result<Receiver<Event>, Error> {
+        let (listen_tx, listen_rx) = oneshot::channel();
+        server.send(Call::ListenEvents(listen_tx)).await.map_err(|_| anyhow!("server has shutdown"))?;
+        let mut events = listen_rx.await?;
+        let (status_tx, status_rx) = oneshot::channel();
+        server.send(Call::Status(status_tx)).await.map_err(|_| anyhow!("server has shutdown"))?;
+        if let Status::Ready = status_rx.await? {
+            return Ok(events);
+        }
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match events.recv().await? {
+                    Event::Status(Status::Ready) => return Ok(()),
+                    Event::Status(Status::Panic(err)) => return Err(anyhow!(err)),
+                    _ => {}
+                }
+            }
+        }).await??;
+        Ok(events)
+    }
+
+    // the user of the next Authorization event
+    async fn authorized_user(events: &mut Receiver<Event>) -> Result<String, Error> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match events.recv().await? {
+                    Event::Authorization(EventResult::Ok(user)) => return Ok(user),
+                    Event::Authorization(EventResult::Err(err)) => return Err(anyhow!(err)),
+                    _ => {}
+                }
+            }
+        }).await?
+    }
+
+    #[tokio::test]
+    async fn tls_server_up() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join(format!("resource-mesh-portal-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+
+        let mut ca_params = rcgen::CertificateParams::new(vec![]);
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        ca_params.distinguished_name.push(rcgen::DnType::CommonName, "test-ca");
+        let ca = rcgen::Certificate::from_params(ca_params)?;
+
+        let server_cert = rcgen::Certificate::from_params(rcgen::CertificateParams::new(vec!["localhost".to_string()]))?;
+
+        let mut client_params = rcgen::CertificateParams::new(vec![]);
+        client_params.distinguished_name.push(rcgen::DnType::CommonName, "scott");
+        let client_cert = rcgen::Certificate::from_params(client_params)?;
+
+        std::fs::write(dir.join("ca.pem"), ca.serialize_pem()?)?;
+        std::fs::write(dir.join("server.pem"), server_cert.serialize_pem_with_signer(&ca)?)?;
+        std::fs::write(dir.join("server.key"), server_cert.serialize_private_key_pem())?;
+        std::fs::write(dir.join("client.pem"), client_cert.serialize_pem_with_signer(&ca)?)?;
+        std::fs::write(dir.join("client.key"), client_cert.serialize_private_key_pem())?;
+
+        let tls = TlsServerConfig::new(dir.join("server.pem"), dir.join("server.key"))
+            .with_client_auth(ClientAuth::Required(dir.join("ca.pem")));
+        let handle = PortalTcpServer::builder(Box::new(TestPortalServer::new())).with_tls(tls).start().await?;
+        let port = handle.addr().port();
+        let server = handle.call_tx();
+
+        let mut events = ready_events(&server).await?;
+
+        let tls = TlsClientConfig::new(dir.join("ca.pem"))
+            .with_client_cert(dir.join("client.pem"), dir.join("client.key"));
+        tokio::spawn(async move {
+            let client = Box::new(TestPortalClient::new("scott".to_string()));
+            PortalTcpClient::new_tls(format!("localhost:{}", port), client, tls).await
+        });
+
+        let user = authorized_user(&mut events).await;
+
+        server.send(Call::Shutdown).await.unwrap_or_default();
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+
+        assert_eq!(user?, "scott".to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn uds_server_up() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("resource-mesh-portal-{}.sock", std::process::id()));
+        let server = PortalUdsServer::new(UdsServerConfig::new(path.clone()).with_mode(0o600), Box::new(TestPortalServer::new()));
+
+        let mut events = ready_events(&server).await?;
+
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        }
+
+        {
+            let path = path.clone();
+            tokio::spawn(async move {
+                let client = Box::new(TestPortalClient::new("scott".to_string()));
+                PortalUdsClient::new(path, client).await
+            });
+        }
+
+        let user = authorized_user(&mut events).await;
+
+        server.send(Call::Shutdown).await.unwrap_or_default();
+
+        assert_eq!(user?, "scott".to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn duplex_handshake() -> Result<(), Error> {
+        let server: Arc<dyn PortalServer> = Arc::new(TestPortalServer::new());
+        let (broadcaster_tx, mut broadcast_rx) = tokio::sync::broadcast::channel(32);
+        let muxer = PortalMuxer::new(|muxer| server.router_factory(muxer));
+        let acceptor = PortalAcceptor::new(server, muxer, broadcaster_tx);
+
+        let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+
+        tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(server_stream);
+            acceptor.accept(reader, writer, Option::None).await
+        });
+
+        tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(client_stream);
+            let client = Box::new(TestPortalClient::new("scott".to_string()));
+            resource_mesh_portal_tcp_client::connect(reader, writer, client).await
+        });
+
+        let info = tokio::time::timeout(Duration::from_secs(5), async move {
+            while let Result::Ok(event) = broadcast_rx.recv().await {
+                if let Event::Info(result) = event {
+                    return result;
+                }
+            }
+            EventResult::Err("event broadcast closed".to_string())
+        }).await?;
+
+        match info {
+            EventResult::Ok(info) => {
+                assert_eq!(info.owner, "scott".to_string());
+                Ok(())
+            }
+            EventResult::Err(err) => Err(anyhow!(err))
+        }
+    }
+
+    #[tokio::test]
+    async fn mem_portal_select() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| server.router_factory(muxer));
+
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        let fred = PortalMemClient::new_with_codec(server.info("fred".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        assert!(matches!(scott.portal.skel.status(), Status::Ready));
+        assert!(matches!(fred.portal.skel.status(), Status::Ready));
+
+        for client in [scott, fred] {
+            let mut request = inlet::Request::new(Operation::Resource(
+                ResourceOperation::Select(Selector::new()),
+            ));
+            request.to.push(client.info.parent.clone());
+            let response = tokio::time::timeout(Duration::from_secs(5), client.portal.skel.api().exchange(request)).await??;
+            match response.signal {
+                ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(resources))) => {
+                    assert_eq!(resources.len(), 2);
+                    assert!(resources.iter().any(|resource| resource.key == client.info.key));
+                }
+                _ => return Err(anyhow!("unexpected response")),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn server_portal_exchanges_with_its_client() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let (outlet_tx, mut outlet_rx) = mpsc::channel(1024);
+        let (inlet_tx, inlet_rx) = mpsc::channel(1024);
+        let portal = resource_mesh_portal_api_server::Portal::new(server.info("scott".to_string()).await?, outlet_tx, inlet_rx, Arc::new(Policy::allow_all()), resource_mesh_portal_api_server::log);
+        assert!(matches!(outlet_rx.recv().await, Some(outlet::Frame::Init(_))));
+
+        let exchange = tokio::spawn(async move {
+            portal.exchange(outlet::Request {
+                from: host(),
+                operation: ExtOperation::Port(PortOperation {
+                    port: "greet".to_string(),
+                    entity: Entity::Empty,
+                }),
+                kind: ExchangeKind::None,
+                idempotency_key: Option::None,
+            }).await
+        });
+
+        // the exchange goes out to the client as a Request, not as anything else
+        let (from, exchange_id) = match tokio::time::timeout(Duration::from_secs(5), outlet_rx.recv()).await? {
+            Some(outlet::Frame::Request(outlet::Request { from, kind: ExchangeKind::RequestResponse(exchange_id), .. })) => (from, exchange_id),
+            frame => return Err(anyhow!("expected a RequestResponse Request frame but got {:?}", frame)),
+        };
+        inlet_tx.send(inlet::Frame::Response(inlet::Response {
+            to: from,
+            exchange_id,
+            signal: ResponseEntity::Ok(Entity::Empty),
+        })).await?;
+
+        let response = tokio::time::timeout(Duration::from_secs(5), exchange).await???;
+        assert!(matches!(response.signal, ResponseEntity::Ok(Entity::Empty)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn oversized_frame_rejected() -> Result<(), Error> {
+        let server: Arc<dyn PortalServer> = Arc::new(TestPortalServer::new());
+        let (broadcaster_tx, _) = tokio::sync::broadcast::channel(32);
+        let muxer = PortalMuxer::new(|muxer| server.router_factory(muxer));
+        let acceptor = PortalAcceptor::new(server, muxer, broadcaster_tx);
+
+        let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+        let (reader, mut writer) = tokio::io::split(client_stream);
+
+        let accepted = tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(server_stream);
+            acceptor.accept(reader, writer, Option::None).await
+        });
+
+        // a length prefix claiming a gigabyte must be refused before anything is allocated
+        writer.write_u32(1024 * 1024 * 1024).await?;
+
+        let err = accepted.await?.expect_err("expected oversized frame to be rejected");
+        assert!(err.downcast_ref::<FrameTooLarge>().is_some());
+
+        let mut reader: FrameReader<outlet::Frame> = FrameReader::new(PrimitiveFrameReader::boxed(reader));
+        match reader.read().await? {
+            outlet::Frame::Close(CloseReason::Error(_)) => Ok(()),
+            frame => Err(anyhow!("expected Close frame but got {}", frame)),
+        }
+    }
+
+    async fn authenticate(authenticators: Arc<Authenticators>, credentials: Box<dyn Credentials>) -> Result<String, Error> {
+        let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(server_stream);
+            let mut reader = PrimitiveFrameReader::boxed(reader);
+            let mut writer = PrimitiveFrameWriter::boxed(writer);
+            authenticators.auth(&mut reader, &mut writer).await
+        });
+        let (reader, writer) = tokio::io::split(client_stream);
+        let mut reader = PrimitiveFrameReader::boxed(reader);
+        let mut writer = PrimitiveFrameWriter::boxed(writer);
+        credentials.authenticate(&mut reader, &mut writer).await?;
+        server.await?
+    }
+
+    #[tokio::test]
+    async fn builtin_authenticators() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("resource-mesh-portal-credentials-{}", std::process::id()));
+        std::fs::write(&path, format!("# test credentials\n{}\n", CredentialStore::entry("fred", "opensesame")))?;
+        let store = CredentialStore::load(path.clone())?;
+        std::fs::remove_file(&path).unwrap_or_default();
+
+        let authenticators = Arc::new(
+            Authenticators::new()
+                .with(Box::new(BearerAuthenticator::new().with_token("secret-token".to_string(), "scott".to_string())))
+                .with(Box::new(HmacAuthenticator::new().with_secret("jane".to_string(), b"shared-secret".to_vec())))
+                .with(Box::new(store)),
+        );
+
+        assert_eq!(authenticate(authenticators.clone(), Box::new(BearerCredentials::new("secret-token".to_string()))).await?, "scott".to_string());
+        assert!(authenticate(authenticators.clone(), Box::new(BearerCredentials::new("wrong-token".to_string()))).await.is_err());
+
+        assert_eq!(authenticate(authenticators.clone(), Box::new(HmacCredentials::new("jane".to_string(), b"shared-secret".to_vec()))).await?, "jane".to_string());
+        assert!(authenticate(authenticators.clone(), Box::new(HmacCredentials::new("jane".to_string(), b"wrong-secret".to_vec()))).await.is_err());
+
+        assert_eq!(authenticate(authenticators.clone(), Box::new(PasswordCredentials::new("fred".to_string(), "opensesame".to_string()))).await?, "fred".to_string());
+        assert!(authenticate(authenticators.clone(), Box::new(PasswordCredentials::new("fred".to_string(), "guess".to_string()))).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn server_authenticates_clients() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("resource-mesh-portal-server-credentials-{}", std::process::id()));
+        std::fs::write(&path, format!("{}\n", CredentialStore::entry("fred", "opensesame")))?;
+        let store = CredentialStore::load(path.clone())?;
+        std::fs::remove_file(&path).unwrap_or_default();
+
+        let authenticators = Authenticators::new()
+            .with(Box::new(BearerAuthenticator::new().with_token("secret-token".to_string(), "scott".to_string())))
+            .with(Box::new(store));
+        let handle = PortalTcpServer::builder(Box::new(AuthenticatingPortalServer { server: TestPortalServer::new(), authenticators })).start().await?;
+        let server = handle.call_tx();
+        let mut events = ready_events(&server).await?;
+
+        let attempts: Vec<(Box<dyn Credentials>, Option<&str>)> = vec![
+            (Box::new(BearerCredentials::new("secret-token".to_string())), Option::Some("scott")),
+            (Box::new(BearerCredentials::new("wrong-token".to_string())), Option::None),
+            (Box::new(PasswordCredentials::new("fred".to_string(), "opensesame".to_string())), Option::Some("fred")),
+            (Box::new(PasswordCredentials::new("fred".to_string(), "guess".to_string())), Option::None),
+            (Box::new(PasswordCredentials::new("nobody".to_string(), "opensesame".to_string())), Option::None),
+        ];
+        for (credentials, expected) in attempts {
+            let addr = handle.addr().to_string();
+            tokio::spawn(async move {
+                PortalTcpClient::new(addr, Box::new(AuthenticatingPortalClient { credentials })).await
+            });
+            match expected {
+                Some(user) => assert_eq!(authorized_user(&mut events).await?, user.to_string()),
+                None => assert!(authorized_user(&mut events).await.is_err()),
+            }
+        }
+
+        server.send(Call::Shutdown).await.unwrap_or_default();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn policy_forbids_select() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| server.router_factory(muxer));
+
+        let policy = Arc::new(Policy::allow_all().with_rule(Rule::deny("no-select-for-fred").owner("fred").operation(OperationPattern::Select)));
+
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, policy.clone(), test_logger).await?;
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, policy.clone(), test_logger).await?;
+
+        for client in [scott, fred] {
+            let mut request = inlet::Request::new(Operation::Resource(
+                ResourceOperation::Select(Selector::new()),
+            ));
+            request.to.push(client.info.parent.clone());
+            let response = tokio::time::timeout(Duration::from_secs(5), client.portal.skel.api().exchange(request)).await??;
+            match (client.info.owner.as_str(), response.signal) {
+                ("scott", ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(_)))) => {}
+                ("fred", ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Forbidden(forbidden)))) => {
+                    assert_eq!(forbidden.rule, Option::Some("no-select-for-fred".to_string()));
+                    assert_eq!(forbidden.operation, "Select".to_string());
+                }
+                (owner, _) => return Err(anyhow!("unexpected response for {}", owner)),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn policy_resolves_keys_to_addresses() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| server.router_factory(muxer));
+
+        let fred_info = server.info("fred".to_string()).await?;
+        let policy = Arc::new(Policy::allow_all().with_rule(Rule::deny("not-fred").to(fred_info.address.as_str())));
+
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, policy.clone(), test_logger).await?;
+        let _fred = PortalMemClient::new(fred_info.clone(), muxer.clone(), quiet_portal_ctrl_factory, policy.clone(), test_logger).await?;
+
+        let targets = vec![
+            (Identifier::Address(fred_info.address.clone()), Option::Some("not-fred".to_string())),
+            (Identifier::Key(fred_info.key.clone()), Option::Some("not-fred".to_string())),
+            (Identifier::Key("nobody".to_string()), Option::None)
+        ];
+        for (to, rule) in targets {
+            let mut request = inlet::Request::new(Operation::Resource(ResourceOperation::Get));
+            request.to.push(to.clone());
+            let response = tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(request)).await??;
+            match response.signal {
+                ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Forbidden(forbidden))) => assert_eq!(forbidden.rule, rule),
+                _ => return Err(anyhow!("expected {:?} to be forbidden", to)),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn muxer_tracks_portals_until_shutdown() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| server.router_factory(muxer));
+
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        assert_eq!(muxer.select(Query::all()).await?.len(), 1);
+
+        // portals added after the muxer started are serviced just like the first
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        assert_eq!(muxer.select(Query::all()).await?.len(), 2);
+        match select(&fred).await? {
+            ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(resources))) => assert_eq!(resources.len(), 2),
+            _ => return Err(anyhow!("unexpected response")),
+        }
+
+        muxer.remove(Identifier::Key(scott.info.key.clone())).await?;
+        assert_eq!(muxer.select(Query::all()).await?.len(), 1);
+
+        muxer.shutdown().await?;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !muxer.is_closed() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await?;
+        assert!(muxer.select(Query::all()).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn muxer_selects_through_indexes() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| server.router_factory(muxer));
+
+        let mut clients = vec![];
+        for user in ["scott", "scott", "scott", "fred", "fred"] {
+            clients.push(PortalMemClient::new(server.info(user.to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?);
+        }
+
+        assert_eq!(muxer.select(Selector::new().with_owner("scott".to_string())).await?.len(), 3);
+        assert_eq!(muxer.select(Selector::new().with_owner("nobody".to_string())).await?.len(), 0);
+        assert_eq!(muxer.select(Selector::new().with_kind(PortalKind::Portal).with_archetype_kind("Portal".to_string())).await?.len(), 5);
+        assert_eq!(muxer.select(Selector::new().with_specific("none".to_string())).await?.len(), 0);
+
+        let first = muxer.select(Selector::new().with_owner("scott".to_string()).with_page(0, 2)).await?;
+        let second = muxer.select(Selector::new().with_owner("scott".to_string()).with_page(2, 2)).await?;
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 1);
+        assert!(first.iter().all(|stub| stub.address < second[0].address));
+
+        let fred = &clients[4];
+        let stubs = muxer.select(Selector::new().with_address_prefix(fred.info.address.clone())).await?;
+        assert_eq!(stubs.len(), 1);
+        assert_eq!(stubs[0].key, fred.info.key);
+
+        // unlike a fn pointer a filter can capture whatever it needs
+        let key = fred.info.key.clone();
+        assert_eq!(muxer.select(Query::filter(move |info| info.key == key)).await?.len(), 1);
+
+        // the router answers Select requests with the same indexes
+        let mut request = inlet::Request::new(Operation::Resource(ResourceOperation::Select(
+            Selector::new().with_owner("fred".to_string()),
+        )));
+        request.to.push(fred.info.parent.clone());
+        let response = tokio::time::timeout(Duration::from_secs(5), fred.portal.skel.api().exchange(request)).await??;
+        match response.signal {
+            ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(resources))) => assert_eq!(resources.len(), 2),
+            _ => return Err(anyhow!("unexpected response")),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn local_router_delivers_between_portals() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        let greet = |to: Identifier| {
+            let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Port(PortOperation {
+                port: "greet".to_string(),
+                entity: Entity::Payload(Payload::Text("scott".to_string())),
+            })));
+            request.to.push(to);
+            request
+        };
+
+        // by address and by key the request reaches fred and fred's response finds its way back
+        for to in [Identifier::Address(fred.info.address.clone()), Identifier::Key(fred.info.key.clone())] {
+            let response = tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(greet(to))).await??;
+            match response.signal {
+                ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => assert_eq!(text, format!("Hello scott, this is {}", fred.info.address)),
+                _ => return Err(anyhow!("unexpected response")),
+            }
+        }
+
+        let response = tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(greet(Identifier::Address("nowhere".to_string())))).await??;
+        match response.signal {
+            ResponseEntity::Fail(Fail::Resource(resource::Fail::Standard(Standard::NotFound(NotFound::Address(address))))) => assert_eq!(address, "nowhere".to_string()),
+            _ => return Err(anyhow!("expected a not found failure")),
+        }
+
+        match select(&fred).await? {
+            ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(resources))) => assert_eq!(resources.len(), 2),
+            _ => return Err(anyhow!("unexpected response")),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unrequested_responses_dropped() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        // fred waits on an exchange scott was never asked about
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        fred.portal.skel.exchanges.insert("exchange-1".to_string(), tx);
+        scott.portal.skel.api().respond(inlet::Response {
+            to: Identifier::Address(fred.info.address.clone()),
+            exchange_id: "exchange-1".to_string(),
+            signal: ResponseEntity::Ok(Entity::Empty),
+        });
+        assert!(tokio::time::timeout(Duration::from_millis(500), rx).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn host_injects_requests() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        let greet = |to: Identifier| {
+            resource_mesh_portal_api_server::Request::new(to, host(), Operation::Ext(ExtOperation::Port(PortOperation {
+                port: "greet".to_string(),
+                entity: Entity::Payload(Payload::Text("host".to_string())),
+            })))
+        };
+
+        // the host is no portal, yet the response finds its way back to it
+        for to in [Identifier::Address(fred.info.address.clone()), Identifier::Key(fred.info.key.clone())] {
+            let response = tokio::time::timeout(Duration::from_secs(5), muxer.exchange(greet(to)).await?).await??;
+            assert_eq!(response.to, host());
+            match response.signal {
+                ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => assert_eq!(text, format!("Hello host, this is {}", fred.info.address)),
+                _ => return Err(anyhow!("unexpected response")),
+            }
+        }
+
+        let response = tokio::time::timeout(Duration::from_secs(5), muxer.exchange(greet(Identifier::Address("nowhere".to_string()))).await?).await??;
+        match response.signal {
+            ResponseEntity::Fail(Fail::Resource(resource::Fail::Standard(Standard::NotFound(NotFound::Address(address))))) => assert_eq!(address, "nowhere".to_string()),
+            _ => return Err(anyhow!("expected a not found failure")),
+        }
+
+        // the same goes for the tcp server's Call api
+        let server = PortalTcpServer::builder(Box::new(TestPortalServer::new())).start().await?;
+        let _client = PortalTcpClient::new(server.addr().to_string(), Box::new(TestPortalClient::new("scott".to_string()))).await?;
+        let address = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let (tx,rx) = oneshot::channel();
+                server.send(Call::Portals(tx)).await?;
+                if let Some(portal) = rx.await?.first() {
+                    return Ok::<_,Error>(portal.info.address.clone());
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }).await??;
+
+        let response = tokio::time::timeout(Duration::from_secs(5), server.exchange(greet(Identifier::Address(address))).await?).await??;
+        match response.signal {
+            ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => assert_eq!(text, "Hello, <username>".to_string()),
+            _ => return Err(anyhow!("unexpected response")),
+        }
+
+        server.shutdown().await?;
+        tokio::time::timeout(Duration::from_secs(15), server.join()).await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn portal_events_reported() -> Result<(), Error> {
+        let server = PortalTcpServer::builder(Box::new(TestPortalServer::new())).start().await?;
+        let mut events = server.listen_events().await?;
+
+        // a hand rolled client so it can misbehave
+        let (reader, writer) = TcpStream::connect(server.addr()).await?.into_split();
+        let mut reader = PrimitiveFrameReader::boxed(reader);
+        let mut writer = PrimitiveFrameWriter::boxed(writer);
+        writer.write_string("test".to_string()).await?;
+        assert_eq!(reader.read_string().await?, "Ok".to_string());
+        writer.write_string("scott".to_string()).await?;
+        assert_eq!(reader.read_string().await?, "Ok".to_string());
+
+        writer.write(inlet::Frame::Status(Status::Panic("could not init".to_string())).try_into()?).await?;
+        let mut portal_events = portal_events_until(&mut events, |kind| matches!(kind, PortalEventKind::InitPanic(_))).await?;
+
+        // garbage where a frame should be costs the client its portal
+        writer.write(PrimitiveFrame { data: vec![255u8; 8] }).await?;
+        portal_events.append(&mut portal_events_until(&mut events, |kind| matches!(kind, PortalEventKind::Removed)).await?);
+
+        let events = portal_events;
+
+        assert!(events.iter().all(|event| event.key == events[0].key && event.address == events[0].address));
+        let kinds: Vec<PortalEventKind> = events.into_iter().map(|event| event.kind).collect();
+        assert!(matches!(kinds.as_slice(), [
+            PortalEventKind::Added,
+            PortalEventKind::Status(Status::Panic(_)),
+            PortalEventKind::InitPanic(_),
+            PortalEventKind::FrameError(_),
+            PortalEventKind::Removed
+        ]), "unexpected portal events {:?}", kinds);
+
+        server.shutdown().await?;
+        tokio::time::timeout(Duration::from_secs(15), server.join()).await??;
+        Ok(())
+    }
+
+    // a hand rolled client that has done the handshake in the given flavor and read its Init
+    async fn wire_client(addr: SocketAddr, flavor: &str) -> Result<(PrimitiveFrameReader, PrimitiveFrameWriter, PrimitiveFrame), Error> {
+        let (reader, writer) = TcpStream::connect(addr).await?.into_split();
+        let mut reader = PrimitiveFrameReader::boxed(reader);
+        let mut writer = PrimitiveFrameWriter::boxed(writer);
+        writer.write_string(flavor.to_string()).await?;
+        assert_eq!(reader.read_string().await?, "Ok".to_string());
+        writer.write_string("scott".to_string()).await?;
+        assert_eq!(reader.read_string().await?, "Ok".to_string());
+        let init = reader.read().await?;
+        Ok((reader, writer, init))
+    }
+
+    #[tokio::test]
+    async fn server_speaks_the_clients_wire_version() -> Result<(), Error> {
+        let server = PortalTcpServer::builder(Box::new(TestPortalServer::new())).start().await?;
+
+        // a client that names no version is a 0.0.1 client & gets 0.0.1 frames
+        let (mut reader, mut writer, init) = wire_client(server.addr(), "test").await?;
+        let info = match v0_0_1::generic::portal::outlet::Frame::<String, String, String>::try_from(init)? {
+            v0_0_1::generic::portal::outlet::Frame::Init(info) => info,
+            frame => return Err(anyhow!("expected Init frame but got {}", frame)),
+        };
+        let mut request = v0_0_1::generic::portal::inlet::Request::new(v0_0_1::generic::operation::Operation::Resource(
+            v0_0_1::generic::operation::ResourceOperation::<String, String, String>::Select(v0_0_1::generic::resource::Selector::new()),
+        ));
+        request.to.push(info.parent.clone());
+        request.kind = ExchangeKind::RequestResponse("exchange-1".to_string());
+        writer.write(v0_0_1::generic::portal::inlet::Frame::Request(request).try_into()?).await?;
+        match v0_0_1::generic::portal::outlet::Frame::<String, String, String>::try_from(reader.read().await?)? {
+            v0_0_1::generic::portal::outlet::Frame::Response(response) => {
+                assert!(matches!(response.signal, v0_0_1::generic::delivery::ResponseEntity::Ok(_)));
+            }
+            frame => return Err(anyhow!("expected Response frame but got {}", frame)),
+        }
+
+        // one that names 0.0.2 gets to page its selection
+        let (mut reader, mut writer, init) = wire_client(server.addr(), "test@0.0.2").await?;
+        let info = match WireVersion::V0_0_2.decode_outlet(init)? {
+            outlet::Frame::Init(info) => info,
+            frame => return Err(anyhow!("expected Init frame but got {}", frame)),
+        };
+        let mut request = inlet::Request::new(Operation::Resource(ResourceOperation::Select(Selector::new().with_page(0, 1))));
+        request.to.push(info.parent.clone());
+        request.kind = ExchangeKind::RequestResponse("exchange-2".to_string());
+        writer.write(WireVersion::V0_0_2.encode_inlet(inlet::Frame::Request(request))?).await?;
+        match WireVersion::V0_0_2.decode_outlet(reader.read().await?)? {
+            outlet::Frame::Response(response) => match response.signal {
+                ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(stubs))) => assert_eq!(stubs.len(), 1),
+                signal => return Err(anyhow!("unexpected response {:?}", signal)),
+            },
+            frame => return Err(anyhow!("expected Response frame but got {}", frame)),
+        }
+
+        // & a version the server does not know is refused
+        let (reader, writer) = TcpStream::connect(server.addr()).await?.into_split();
+        let mut reader = PrimitiveFrameReader::boxed(reader);
+        let mut writer = PrimitiveFrameWriter::boxed(writer);
+        writer.write_string("test@9.9.9".to_string()).await?;
+        assert_ne!(reader.read_string().await?, "Ok".to_string());
+
+        server.shutdown().await?;
+        tokio::time::timeout(Duration::from_secs(15), server.join()).await??;
+        Ok(())
+    }
+
+    // every portal event up to and including the first one last() is true for
+    async fn portal_events_until(events: &mut Receiver<Event>, last: fn(&PortalEventKind) -> bool) -> Result<Vec<PortalEvent>, Error> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            let mut portal_events = vec![];
+            loop {
+                if let Event::Portal(event) = events.recv().await? {
+                    let done = last(&event.kind);
+                    portal_events.push(event);
+                    if done {
+                        return Ok(portal_events);
+                    }
+                }
+            }
+        }).await?
+    }
+
+    #[tokio::test]
+    async fn gateway_links_servers() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+
+        let policy = Policy::allow_all().with_rule(Rule::deny("no-farewells-from-beta").owner("beta").operation(OperationPattern::Port(Pattern::new("farewell"))));
+        let alpha = Gateway::new("alpha".to_string(), test_logger)
+            .with_route("beta-", "beta")
+            .with_authenticators(Authenticators::new().with(Box::new(HmacAuthenticator::new().with_secret("beta".to_string(), b"link-secret".to_vec()))))
+            .with_policy(Arc::new(policy));
+        let beta = Gateway::new("beta".to_string(), test_logger)
+            .with_route("alpha-", "alpha")
+            .with_credentials(Box::new(HmacCredentials::new("beta".to_string(), b"link-secret".to_vec())));
+        let alpha_muxer = PortalMuxer::new(|muxer| alpha.router(muxer.clone(), Box::new(LocalRouter::new(muxer))));
+        let beta_muxer = PortalMuxer::new(|muxer| beta.router(muxer.clone(), Box::new(LocalRouter::new(muxer))));
+
+        let addr = alpha.listen("127.0.0.1:0").await?;
+
+        // a gateway must prove it is the one it says Hello as
+        let impostor = Gateway::new("beta".to_string(), test_logger).with_credentials(Box::new(HmacCredentials::new("beta".to_string(), b"wrong-secret".to_vec())));
+        assert!(impostor.connect(addr.to_string().as_str()).await.is_err());
+        let impostor = Gateway::new("gamma".to_string(), test_logger).with_credentials(Box::new(HmacCredentials::new("beta".to_string(), b"link-secret".to_vec())));
+        assert!(impostor.connect(addr.to_string().as_str()).await.is_err());
+        assert!(alpha.links().is_empty());
+
+        assert_eq!(beta.connect(addr.to_string().as_str()).await?, "alpha".to_string());
+        // the live link is not replaced by a second one
+        assert!(beta.connect(addr.to_string().as_str()).await.is_err());
+
+        let mut info = server.info("scott".to_string()).await?;
+        info.address = format!("alpha-{}", info.address);
+        let scott = PortalMemClient::new(info, alpha_muxer, greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        let mut info = server.info("fred".to_string()).await?;
+        info.address = format!("beta-{}", info.address);
+        let fred = PortalMemClient::new(info, beta_muxer, greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        let greet = |from: &PortalMemClient, to: String| {
+            let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Port(PortOperation {
+                port: "greet".to_string(),
+                entity: Entity::Payload(Payload::Text(from.info.owner.clone())),
+            })));
+            request.to.push(Identifier::Address(to));
+            request
+        };
+
+        // requests cross the link in both directions and each response finds its requester
+        let response = tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(greet(&scott, fred.info.address.clone()))).await??;
+        match response.signal {
+            ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => assert_eq!(text, format!("Hello scott, this is {}", fred.info.address)),
+            _ => return Err(anyhow!("unexpected response")),
+        }
+        let response = tokio::time::timeout(Duration::from_secs(5), fred.portal.skel.api().exchange(greet(&fred, scott.info.address.clone()))).await??;
+        match response.signal {
+            ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => assert_eq!(text, format!("Hello fred, this is {}", scott.info.address)),
+            _ => return Err(anyhow!("unexpected response")),
+        }
+
+        let response = tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(greet(&scott, "beta-nowhere".to_string()))).await??;
+        match response.signal {
+            ResponseEntity::Fail(Fail::Resource(resource::Fail::Standard(Standard::NotFound(NotFound::Address(address))))) => assert_eq!(address, "beta-nowhere".to_string()),
+            _ => return Err(anyhow!("expected a not found failure from the remote server")),
+        }
+
+        // requests from beyond the link are held to the policy of the server they arrive at
+        let mut farewell = greet(&fred, scott.info.address.clone());
+        if let Operation::Ext(ExtOperation::Port(port)) = &mut farewell.operation {
+            port.port = "farewell".to_string();
+        }
+        let response = tokio::time::timeout(Duration::from_secs(5), fred.portal.skel.api().exchange(farewell)).await??;
+        match response.signal {
+            ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Forbidden(forbidden))) => assert_eq!(forbidden.rule, Option::Some("no-farewells-from-beta".to_string())),
+            _ => return Err(anyhow!("expected the policy to forbid the request")),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gateway_distrusts_its_links() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let alpha = Gateway::new("alpha".to_string(), test_logger)
+            .with_route("beta-", "beta")
+            .with_authenticators(Authenticators::new().with(Box::new(HmacAuthenticator::new().with_secret("beta".to_string(), b"link-secret".to_vec()))))
+            .with_handshake_timeout(Duration::from_secs(1));
+        let muxer = PortalMuxer::new(|muxer| alpha.router(muxer.clone(), Box::new(LocalRouter::new(muxer))));
+        let addr = alpha.listen("127.0.0.1:0").await?;
+
+        // a connection that never says Hello is let go after the handshake_timeout
+        let (reader, _writer) = TcpStream::connect(addr).await?.into_split();
+        let mut idle = PrimitiveFrameReader::boxed(reader);
+        assert!(tokio::time::timeout(Duration::from_secs(5), idle.read()).await?.is_err());
+
+        let mut info = server.info("scott".to_string()).await?;
+        info.address = format!("alpha-{}", info.address);
+        let scott = PortalMemClient::new(info, muxer, greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        // a hand rolled beta that sends whatever it likes over the link
+        let (reader, writer) = TcpStream::connect(addr).await?.into_split();
+        let mut reader = PrimitiveFrameReader::boxed(reader);
+        let mut writer = PrimitiveFrameWriter::boxed(writer);
+        writer.write_frame(LinkFrame::Hello("beta".to_string())).await?;
+        HmacCredentials::new("beta".to_string(), b"link-secret".to_vec()).authenticate(&mut reader, &mut writer).await?;
+        assert!(matches!(LinkFrame::try_from(reader.read().await?)?, LinkFrame::Hello(_)));
+
+        let greet = |to: Identifier, from: &str, exchange_id: &str| LinkFrame::Request(LinkRequest {
+            to,
+            from: Identifier::Address(from.to_string()),
+            operation: ExtOperation::Port(PortOperation {
+                port: "greet".to_string(),
+                entity: Entity::Payload(Payload::Text("beta".to_string())),
+            }),
+            kind: ExchangeKind::RequestResponse(exchange_id.to_string()),
+            idempotency_key: Option::None
+        });
+
+        // keys mean nothing across the link and a requester must be routed back over it
+        let mut key_from = greet(Identifier::Address(scott.info.address.clone()), "beta-fred", "key-from");
+        if let LinkFrame::Request(request) = &mut key_from {
+            request.from = Identifier::Key("fred".to_string());
+        }
+        for frame in [greet(Identifier::Key(scott.info.key.clone()), "beta-fred", "key-to"), greet(Identifier::Address(scott.info.address.clone()), "alpha-fred", "spoofed"), key_from] {
+            writer.write_frame(frame).await?;
+            match LinkFrame::try_from(tokio::time::timeout(Duration::from_secs(5), reader.read()).await??)? {
+                LinkFrame::Response(response) => assert!(matches!(response.signal, ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Error(_))))),
+                _ => return Err(anyhow!("expected the request to be refused")),
+            }
+        }
+
+        writer.write_frame(greet(Identifier::Address(scott.info.address.clone()), "beta-fred", "greeted")).await?;
+        match LinkFrame::try_from(tokio::time::timeout(Duration::from_secs(5), reader.read()).await??)? {
+            LinkFrame::Response(response) => match response.signal {
+                ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => assert_eq!(text, format!("Hello beta, this is {}", scott.info.address)),
+                _ => return Err(anyhow!("unexpected response")),
+            },
+            _ => return Err(anyhow!("expected a response")),
+        }
+
+        // only the response to a request alpha sent over the link is let in
+        let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Port(PortOperation {
+            port: "greet".to_string(),
+            entity: Entity::Payload(Payload::Text("scott".to_string())),
+        })));
+        request.to.push(Identifier::Address("beta-fred".to_string()));
+        let mut api = scott.portal.skel.api();
+        let exchange = tokio::spawn(async move { api.exchange(request).await });
+        let (to, exchange_id) = match LinkFrame::try_from(tokio::time::timeout(Duration::from_secs(5), reader.read()).await??)? {
+            LinkFrame::Request(LinkRequest{ from, kind: ExchangeKind::RequestResponse(exchange_id), .. }) => (from, exchange_id),
+            _ => return Err(anyhow!("expected a request")),
+        };
+        let answer = |exchange_id: String, text: &str| LinkFrame::Response(LinkResponse {
+            to: to.clone(),
+            from: Identifier::Address("beta-fred".to_string()),
+            exchange_id,
+            signal: ResponseEntity::Ok(Entity::Payload(Payload::Text(text.to_string())))
+        });
+        writer.write_frame(answer("forged".to_string(), "forged")).await?;
+        writer.write_frame(answer(exchange_id, "Hello scott")).await?;
+        let response = tokio::time::timeout(Duration::from_secs(5), exchange).await???;
+        match response.signal {
+            ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => assert_eq!(text, "Hello scott".to_string()),
+            _ => return Err(anyhow!("unexpected response")),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn metrics_exported() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+
+        // the registry is shared by every test, so these portals get addresses of their own
+        let mut info = server.info("scott".to_string()).await?;
+        info.address = format!("metrics-{}", info.address);
+        let scott = PortalMemClient::new(info, muxer.clone(), greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        let mut info = server.info("fred".to_string()).await?;
+        info.address = format!("metrics-{}", info.address);
+        let fred = PortalMemClient::new(info, muxer.clone(), greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Port(PortOperation {
+            port: "greet".to_string(),
+            entity: Entity::Payload(Payload::Text("scott".to_string())),
+        })));
+        request.to.push(Identifier::Address(fred.info.address.clone()));
+        tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(request)).await??;
+
+        let rendered = metrics::global().render();
+        for series in [format!("portal_exchanges_started_total{{side=\"client\",key=\"{}\",portal=\"{}\"}} 1", scott.info.key, scott.info.address),
+            format!("portal_exchanges_completed_total{{side=\"client\",key=\"{}\",portal=\"{}\"}} 1", scott.info.key, scott.info.address),
+            format!("portal_exchange_seconds_count{{side=\"client\",key=\"{}\",portal=\"{}\"}} 1", scott.info.key, scott.info.address),
+            format!("portal_frames_in_total{{side=\"server\",key=\"{}\",portal=\"{}\"}}", fred.info.key, fred.info.address),
+            "# TYPE portal_muxer_portals gauge".to_string()] {
+            assert!(rendered.contains(series.as_str()), "missing '{}' in:\n{}", series, rendered);
+        }
+
+        let addr = metrics::serve("127.0.0.1:0").await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await?;
+        let mut response = String::new();
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut response)).await??;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(format!("portal_frames_out_total{{side=\"client\",key=\"{}\",portal=\"{}\"}}", scott.info.key, scott.info.address).as_str()));
+
+        // a portal elsewhere at the same address keeps its series when fred's are forgotten
+        let other_muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+        let mut info = server.info("jane".to_string()).await?;
+        info.address = fred.info.address.clone();
+        let jane = PortalMemClient::new(info, other_muxer, greeter_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        muxer.remove(Identifier::Key(fred.info.key.clone())).await?;
+        assert!(muxer.lookup(Identifier::Key(fred.info.key.clone())).await?.is_none());
+        let rendered = metrics::global().render();
+        assert!(!rendered.contains(format!("side=\"server\",key=\"{}\"", fred.info.key).as_str()));
+        assert!(rendered.contains(format!("portal_frames_out_total{{side=\"server\",key=\"{}\",portal=\"{}\"}}", jane.info.key, fred.info.address).as_str()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn admin_socket_introspects_portals() -> Result<(), Error> {
+        let server = PortalTcpServer::builder(Box::new(TestPortalServer::new())).start().await?;
+
+        let path = std::env::temp_dir().join(format!("resource-mesh-portal-admin-{}.sock", std::process::id()));
+        let path = admin::serve(UdsServerConfig::new(path).with_mode(0o600), server.call_tx()).await?;
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        }
+        let mut admin = AdminClient::connect(&path).await?;
+
+        let _client = PortalTcpClient::new(server.addr().to_string(), Box::new(TestPortalClient::new("scott".to_string()))).await?;
+        let portals = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let portals = admin.portals().await?;
+                if !portals.is_empty() {
+                    return Ok::<_,Error>(portals);
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }).await??;
+        assert_eq!(portals.len(), 1);
+        assert_eq!(portals[0].info.owner, "scott".to_string());
+        assert!(portals[0].counters.frames_out > 0);
+
+        let address = portals[0].info.address.clone();
+        assert!(admin.portal(Identifier::Address(address.clone())).await?.is_some());
+        assert!(admin.routing().await?.portals.iter().any(|(a,_)| *a == address));
+
+        // a forced close takes the portal out of the muxer right away
+        assert!(admin.close(Identifier::Address(address.clone()), CloseReason::Error("closed by admin".to_string())).await?);
+        assert!(admin.portal(Identifier::Address(address.clone())).await?.is_none());
+        assert!(!admin.close(Identifier::Address(address), CloseReason::Done).await?);
+
+        server.shutdown().await?;
+        tokio::time::timeout(Duration::from_secs(15), server.join()).await??;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while path.exists() {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pending_exchanges_expire() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let (outlet_tx, _outlet_rx) = mpsc::channel(1024);
+        let (inlet_tx, inlet_rx) = mpsc::channel(1024);
+        let mut info = server.info("scott".to_string()).await?;
+        info.config.response_timeout = 1;
+        let portal = resource_mesh_portal_api_server::Portal::new(info, outlet_tx, inlet_rx, Arc::new(Policy::allow_all()), resource_mesh_portal_api_server::log);
+
+        // nobody will ever answer this one
+        let mut request = inlet::Request::new(Operation::Resource(ResourceOperation::Get));
+        request.to.push(Identifier::Key("fred".to_string()));
+        request.kind = ExchangeKind::RequestResponse("exchange-1".to_string());
+        inlet_tx.send(inlet::Frame::Request(request)).await?;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while portal.snapshot().pending.is_empty() {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }).await?;
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(portal.snapshot().pending.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn host_exchanges_expire() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+        let (outlet_tx, _outlet_rx) = mpsc::channel(1024);
+        let (_inlet_tx, inlet_rx) = mpsc::channel(1024);
+        let mut info = server.info("fred".to_string()).await?;
+        info.config.response_timeout = 1;
+        let address = info.address.clone();
+        muxer.add(resource_mesh_portal_api_server::Portal::new(info, outlet_tx, inlet_rx, Arc::new(Policy::allow_all()), resource_mesh_portal_api_server::log)).await?;
+
+        // fred never answers, so the host is told it timed out instead of waiting forever
+        let request = resource_mesh_portal_api_server::Request::new(Identifier::Address(address.clone()), host(), Operation::Ext(ExtOperation::Port(PortOperation {
+            port: "greet".to_string(),
+            entity: Entity::Payload(Payload::Text("host".to_string())),
+        })));
+        let response = tokio::time::timeout(Duration::from_secs(5), muxer.exchange(request).await?).await??;
+        assert_eq!(response.from, Identifier::Address(address));
+        match response.signal {
+            ResponseEntity::Fail(Fail::Resource(resource::Fail::Standard(Standard::Timeout(timeout)))) => assert_eq!(timeout.waited, 1),
+            _ => return Err(anyhow!("expected a timeout failure")),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stalled_handshake_times_out() -> Result<(), Error> {
+        let mut portal_server = TestPortalServer::new();
+        portal_server.handshake_timeout = Duration::from_secs(2);
+        let server = PortalTcpServer::builder(Box::new(portal_server)).start().await?;
+        let mut events = server.listen_events().await?;
+
+        // connects and then never says a word
+        let _stalled = TcpStream::connect(server.addr()).await?;
+
+        // the stalled connection must not hold up anybody else's handshake
+        let _client = tokio::time::timeout(Duration::from_secs(1), PortalTcpClient::new(server.addr().to_string(), Box::new(TestPortalClient::new("scott".to_string())))).await??;
+
+        let message = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Event::HandshakeFailed(message) = events.recv().await? {
+                    return Ok::<_,Error>(message);
+                }
+            }
+        }).await??;
+        assert!(message.contains("did not complete"));
+
+        server.shutdown().await?;
+        tokio::time::timeout(Duration::from_secs(15), server.join()).await??;
+        Ok(())
+    }
+
+    // the next count hooks called on the LifecyclePortalCtrl at address
+    async fn lifecycle_hooks(lifecycle_rx: &mut Receiver<(String,String)>, address: &str, count: usize) -> Result<Vec<String>, Error> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            let mut hooks = vec![];
+            while hooks.len() < count {
+                let (hook_address, hook) = lifecycle_rx.recv().await?;
+                if hook_address == address {
+                    hooks.push(hook);
+                }
+            }
+            Ok(hooks)
+        }).await?
+    }
+
+    #[tokio::test]
+    async fn portal_lifecycle_hooks() -> Result<(), Error> {
+        let mut lifecycle_rx = LIFECYCLE_TX.subscribe();
+        let mut info = TestPortalServer::new().info("scott".to_string()).await?;
+        info.address = "lifecycle-portal".to_string();
+
+        let (outlet_tx, outlet_rx) = mpsc::channel(16);
+        let portal = resource_mesh_portal_api_client::Portal::new(info, Box::new(NullInlet {}), outlet_rx, lifecycle_portal_ctrl_factory, test_logger).await?;
+
+        // the transport drops, then another takes its place until the server closes the portal
+        drop(outlet_tx);
+        let mut hooks = lifecycle_hooks(&mut lifecycle_rx, "lifecycle-portal", 3).await?;
+
+        let (outlet_tx, outlet_rx) = mpsc::channel(16);
+        portal.reconnect(Box::new(NullInlet {}), outlet_rx);
+        outlet_tx.send(outlet::Frame::Close(CloseReason::Done)).await?;
+        hooks.append(&mut lifecycle_hooks(&mut lifecycle_rx, "lifecycle-portal", 4).await?);
+
+        assert_eq!(hooks, vec![
+            "status Ready".to_string(),
+            "ready".to_string(),
+            "disconnect".to_string(),
+            "reconnect".to_string(),
+            "status Done".to_string(),
+            "close Done".to_string(),
+            "shutdown".to_string(),
+        ]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_exchanges() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+        let mut shutdown_rx = SHUTDOWN_TX.subscribe();
+
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), draining_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), draining_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Port(PortOperation {
+            port: "slow".to_string(),
+            entity: Entity::Payload(Payload::Text("scott".to_string())),
+        })));
+        request.to.push(Identifier::Address(fred.info.address.clone()));
+        let mut api = scott.portal.skel.api();
+        let exchange = tokio::spawn(async move { api.exchange(request).await });
+
+        // wait until the exchange is in flight before draining
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while muxer.portals().await?.iter().all(|portal| portal.pending.is_empty()) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            Ok::<_,Error>(())
+        }).await??;
+
+        tokio::time::timeout(Duration::from_secs(5), muxer.drain(Duration::from_secs(5))).await??;
+        assert!(muxer.is_closed());
+
+        // the exchange that was in flight completed before the portals were closed
+        match exchange.await??.signal {
+            ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => assert_eq!(text, "done".to_string()),
+            _ => return Err(anyhow!("unexpected response")),
+        }
+
+        let mut closed = vec![];
+        while closed.len() < 2 {
+            closed.push(tokio::time::timeout(Duration::from_secs(5), shutdown_rx.recv()).await??);
+        }
+        assert!(closed.contains(&scott.info.address));
+        assert!(closed.contains(&fred.info.address));
+        assert_eq!(scott.portal.skel.status().to_string(), Status::Done.to_string());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn portal_state_survives_reconnect() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let dir = std::env::temp_dir().join(format!("portal-state-{}", std::process::id()));
+        let muxer = PortalMuxer::new_with_state(|muxer| Box::new(LocalRouter::new(muxer)), Arc::new(DirStateStore::new(dir.clone())?));
+
+        let mut info = server.info("scott".to_string()).await?;
+        info.address = "space:app:stateful".to_string();
+
+        // nothing was saved before the first start
+        let scott = PortalMemClient::new(info.clone(), muxer.clone(), stateful_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        assert_eq!(scott.portal.skel.state.get::<u32>("starts")?, Option::Some(1));
+        assert!(scott.portal.skel.state.is_dirty());
+        tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.state.checkpoint()).await??;
+        assert!(!scott.portal.skel.state.is_dirty());
+
+        // whatever changed since the checkpoint is saved when the portal shuts down
+        scott.portal.skel.state.set("greeting", &"hello".to_string())?;
+        tokio::time::timeout(Duration::from_secs(5), scott.portal.shutdown()).await?;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while muxer.lookup(Identifier::Key(info.key.clone())).await?.is_some() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            Ok::<_,Error>(())
+        }).await??;
+
+        let scott = PortalMemClient::new(info.clone(), muxer.clone(), stateful_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        assert_eq!(scott.portal.skel.state.get::<u32>("starts")?, Option::Some(2));
+        assert_eq!(scott.portal.skel.state.get::<String>("greeting")?, Option::Some("hello".to_string()));
+        assert!(dir.join("space_3aapp_3astateful.state").exists());
+
+        // a server that drains asks for a last checkpoint before it closes the portal
+        scott.portal.skel.state.set("farewell", &"bye".to_string())?;
+        tokio::time::timeout(Duration::from_secs(10), muxer.drain(Duration::from_secs(5))).await??;
+        assert!(!scott.portal.skel.state.is_dirty());
+
+        let muxer = PortalMuxer::new_with_state(|muxer| Box::new(LocalRouter::new(muxer)), Arc::new(DirStateStore::new(dir.clone())?));
+        let scott = PortalMemClient::new(info.clone(), muxer.clone(), stateful_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        assert_eq!(scott.portal.skel.state.get::<u32>("starts")?, Option::Some(3));
+        assert_eq!(scott.portal.skel.state.get::<String>("farewell")?, Option::Some("bye".to_string()));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    fn once(to: &PortalMemClient, text: &str) -> inlet::Request {
+        let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Port(PortOperation {
+            port: "once".to_string(),
+            entity: Entity::Payload(Payload::Text(text.to_string())),
+        })));
+        request.to.push(Identifier::Address(to.info.address.clone()));
+        request
+    }
+
+    fn text(signal: ResponseEntity) -> Result<String, Error> {
+        match signal {
+            ResponseEntity::Ok(Entity::Payload(Payload::Text(text))) => Ok(text),
+            _ => Err(anyhow!("unexpected response")),
+        }
+    }
+
+    #[tokio::test]
+    async fn retried_exchanges_run_once() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+
+        let mut info = server.info("scott".to_string()).await?;
+        info.config.response_timeout = 1;
+        let scott = PortalMemClient::new(info, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), counting_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        // the first attempt times out while fred is still at it, the retry gets the response of that first run
+        let mut api = scott.portal.skel.api().with_retry(RetryPolicy::new(3).with_backoff(Backoff::Fixed(Duration::from_millis(10))));
+        let response = tokio::time::timeout(Duration::from_secs(10), api.exchange(once(&fred, "slow"))).await??;
+        assert_eq!(text(response.signal)?, "handled 1".to_string());
+
+        // a retry after the response went out is answered from fred's cache
+        let mut api = scott.portal.skel.api();
+        for _ in 0..2 {
+            let mut request = once(&fred, "fast");
+            request.idempotency_key = Option::Some("scott-fast".to_string());
+            let response = tokio::time::timeout(Duration::from_secs(5), api.exchange(request)).await??;
+            assert_eq!(text(response.signal)?, "handled 2".to_string());
+        }
+        assert_eq!(HANDLED.load(Ordering::SeqCst), 2);
+
+        // without a retry policy there is one attempt only
+        match tokio::time::timeout(Duration::from_secs(5), api.exchange(once(&fred, "slow"))).await? {
+            Ok(_) => return Err(anyhow!("expected the exchange to time out")),
+            Err(err) => assert!(err.to_string().contains("after 1 attempt(s)")),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn idempotency_keys_need_0_0_2() -> Result<(), Error> {
+        let mut request = inlet::Request::new(Operation::Resource(ResourceOperation::Get));
+        request.to.push(Identifier::Key("fred".to_string()));
+        request.idempotency_key = Option::Some("scott-get".to_string());
+
+        let keep = |version: WireVersion| -> Result<Option<String>, Error> {
+            match version.decode_inlet(version.encode_inlet(inlet::Frame::Request(request.clone()))?)? {
+                inlet::Frame::Request(request) => Ok(request.idempotency_key),
+                frame => Err(anyhow!("expected Request frame but got {}", frame)),
+            }
+        };
+
+        // a 0.0.1 peer has nowhere to put the key so it arrives without one
+        assert_eq!(keep(WireVersion::V0_0_1)?, Option::None);
+        assert_eq!(keep(WireVersion::V0_0_2)?, Option::Some("scott-get".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn middleware_wraps_dispatch() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), guarded_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        let call = |port: &str| {
+            let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Port(PortOperation {
+                port: port.to_string(),
+                entity: Entity::Empty,
+            })));
+            request.to.push(Identifier::Address(fred.info.address.clone()));
+            request
+        };
+
+        // the outermost layer sees the response of everything within
+        let response = tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(call("greet"))).await??;
+        assert_eq!(text(response.signal)?, "<hello>".to_string());
+
+        // the guard answers before the port is reached
+        let response = tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(call("admin-reset"))).await??;
+        match response.signal {
+            ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Forbidden(forbidden))) => assert_eq!(forbidden.operation, "admin-reset".to_string()),
+            _ => return Err(anyhow!("expected the guard to forbid the request")),
+        }
+
+        let response = tokio::time::timeout(Duration::from_secs(5), scott.portal.skel.api().exchange(call("stall"))).await??;
+        match response.signal {
+            ResponseEntity::Error(message) => assert!(message.contains("not handled within 200ms")),
+            _ => return Err(anyhow!("expected the request to time out")),
+        }
+
+        Ok(())
+    }
+
+    async fn http_exchange(from: &PortalMemClient, to: &PortalMemClient, method: &str, path: &str, body: Option<&str>) -> Result<HttpResponse, Error> {
+        let mut headers = HashMap::new();
+        headers.insert("X-User".to_string(), from.info.owner.clone());
+        let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Http(HttpRequest {
+            method: method.to_string(),
+            headers,
+            path: path.to_string(),
+            body: body.map(|body| Bin::Raw(Arc::new(body.as_bytes().to_vec()))),
+        })));
+        request.to.push(Identifier::Address(to.info.address.clone()));
+        let response = tokio::time::timeout(Duration::from_secs(5), from.portal.skel.api().exchange(request)).await??;
+        match response.signal {
+            ResponseEntity::Ok(Entity::HttpResponse(response)) => Ok(response),
+            _ => Err(anyhow!("expected an http response")),
+        }
+    }
+
+    fn http_body(response: &HttpResponse) -> Result<String, Error> {
+        match &response.body {
+            Some(Bin::Raw(raw)) => Ok(String::from_utf8(raw.to_vec())?),
+            _ => Err(anyhow!("expected a raw body")),
+        }
+    }
+
+    #[tokio::test]
+    async fn http_router_routes_requests() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+        let scott = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), routed_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
+
+        let response = http_exchange(&scott, &fred, "GET", "/users/7?verbose=true", None).await?;
+        assert_eq!(response.code, 200);
+        assert_eq!(response.headers.get("Content-Type"), Some(&"application/json".to_string()));
+        assert_eq!(http_body(&response)?, r#"{"id":7,"name":"user-7","asked_by":"scott","verbose":true}"#.to_string());
+
+        let response = http_exchange(&scott, &fred, "POST", "/users", Some(r#"{"name":"fred"}"#)).await?;
+        assert_eq!(response.code, 201);
+        assert_eq!(http_body(&response)?, r#"{"id":1,"name":"fred","asked_by":"scott","verbose":false}"#.to_string());
+
+        let response = http_exchange(&scott, &fred, "get", "/files/docs/read%20me.txt", None).await?;
+        assert_eq!(http_body(&response)?, r#""docs/read me.txt""#.to_string());
+
+        // the path is known but not for this method
+        let response = http_exchange(&scott, &fred, "DELETE", "/users/7", None).await?;
+        assert_eq!(response.code, 405);
+        assert_eq!(response.headers.get("Allow"), Some(&"GET".to_string()));
+
+        // a handler that fails is a server side error
+        assert_eq!(http_exchange(&scott, &fred, "GET", "/users/seven", None).await?.code, 500);
+        assert_eq!(http_exchange(&scott, &fred, "GET", "/nowhere", None).await?.code, 404);
+
+        Ok(())
+    }
+
+    async fn gateway_request(addr: SocketAddr, method: &str, path: &str, host: Option<&str>, body: Option<&str>) -> Result<(u16, Option<String>, String), Error> {
+        let mut request = hyper::Request::builder()
+            .method(method)
+            .uri(format!("http://{}{}", addr, path))
+            .header("X-User", "scott");
+        if let Some(host) = host {
+            request = request.header("Host", host);
+        }
+        let request = request.body(body.map(|body| hyper::Body::from(body.to_string())).unwrap_or_else(hyper::Body::empty))?;
+        let response = tokio::time::timeout(Duration::from_secs(5), hyper::Client::new().request(request)).await??;
+        let code = response.status().as_u16();
+        let content_type = response.headers().get("Content-Type").map(|value| value.to_str().unwrap_or_default().to_string());
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        Ok((code, content_type, String::from_utf8(body.to_vec())?))
+    }
+
+    #[test]
+    fn http_methods_need_0_0_2() -> Result<(), Error> {
+        let mut request = inlet::Request::new(Operation::Ext(ExtOperation::Http(HttpRequest {
+            method: "POST".to_string(),
+            headers: HashMap::new(),
+            path: "/users".to_string(),
+            body: Option::None,
+        })));
+        request.to.push(Identifier::Key("fred".to_string()));
+
+        let method = |version: WireVersion| -> Result<String, Error> {
+            match version.decode_inlet(version.encode_inlet(inlet::Frame::Request(request.clone()))?)? {
+                inlet::Frame::Request(inlet::Request { operation: Operation::Ext(ExtOperation::Http(http)), .. }) => Ok(http.method),
+                frame => Err(anyhow!("expected an http Request frame but got {}", frame)),
+            }
+        };
+
+        // 0.0.1 carries no method, whatever it sends is read as a GET
+        assert_eq!(method(WireVersion::V0_0_1)?, "GET".to_string());
+        assert_eq!(method(WireVersion::V0_0_2)?, "POST".to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn http_gateway_serves_portals() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| Box::new(LocalRouter::new(muxer)));
+        let fred = PortalMemClient::new(server.info("fred".to_string()).await?, muxer.clone(), routed_portal_ctrl_factory, Arc::new(Policy::allow_all()), test_logger).await?;
 
-        let client1 = Box::new(TestPortalClient::new("scott".to_string()));
-        let client2 = Box::new(TestPortalClient::new("fred".to_string()));
+        let gateway = HttpGateway::new(muxer.clone(), test_logger)
+            .with_host("fred.example", fred.info.address.as_str())
+            .with_prefix("/fred", fred.info.address.as_str())
+            .with_prefix("/gone", "nowhere")
+            .with_timeout(Duration::from_millis(300))
+            .with_max_body(64)
+            .serve("127.0.0.1:0").await?;
+        let addr = gateway.addr();
 
-        let client1 = PortalTcpClient::new(format!("localhost:{}", port), client1).await?;
-        let client2 = PortalTcpClient::new(format!("localhost:{}", port), client2).await?;
+        // the prefix is stripped from the path the portal sees
+        let (code, content_type, body) = gateway_request(addr, "GET", "/fred/users/7?verbose=true", None, None).await?;
+        assert_eq!(code, 200);
+        assert_eq!(content_type, Option::Some("application/json".to_string()));
+        assert_eq!(body, r#"{"id":7,"name":"user-7","asked_by":"scott","verbose":true}"#.to_string());
 
-        tokio::spawn( async move {
-            tokio::time::sleep( Duration::from_secs( 5 ) ).await;
-            GLOBAL_TX.send( GlobalEvent::Shutdown ).unwrap_or_default();
-        });
+        let (code, _, body) = gateway_request(addr, "POST", "/fred/users", None, Some(r#"{"name":"fred"}"#)).await?;
+        assert_eq!(code, 201);
+        assert_eq!(body, r#"{"id":1,"name":"fred","asked_by":"scott","verbose":false}"#.to_string());
 
-        shutdown_rx.await;
+        // the host is mapped regardless of its port & the path is left as it is
+        let (code, _, body) = gateway_request(addr, "GET", "/files/docs/read%20me.txt", Some("Fred.Example:8080"), None).await?;
+        assert_eq!(code, 200);
+        assert_eq!(body, r#""docs/read me.txt""#.to_string());
+
+        // whatever the portal answers is passed on, a 404 of its own included
+        assert_eq!(gateway_request(addr, "GET", "/fred/nowhere", None, None).await?.0, 404);
+        assert_eq!(gateway_request(addr, "DELETE", "/fred/users/7", None, None).await?.0, 405);
+
+        // neither a host nor a prefix that is mapped
+        assert_eq!(gateway_request(addr, "GET", "/freddy/users/7", None, None).await?.0, 404);
+
+        // no portal at the address
+        assert_eq!(gateway_request(addr, "GET", "/gone/users/7", None, None).await?.0, 502);
+
+        assert_eq!(gateway_request(addr, "GET", "/fred/stall", None, None).await?.0, 504);
+
+        // a body over max_body never reaches fred
+        let name = "f".repeat(64);
+        assert_eq!(gateway_request(addr, "POST", "/fred/users", None, Some(format!(r#"{{"name":"{}"}}"#, name).as_str())).await?.0, 413);
+
+        // the portal's own connection headers are not passed on, they would not match the body served
+        let (code, _, body) = gateway_request(addr, "GET", "/fred/hops", None, None).await?;
+        assert_eq!(code, 200);
+        assert_eq!(body, r#""hops""#.to_string());
+
+        // the peer joins the proxies already in X-Forwarded-For and cookies stay one list
+        let request = hyper::Request::builder()
+            .uri(format!("http://{}/fred/forwarded", addr))
+            .header("X-Forwarded-For", "10.0.0.1")
+            .header("Cookie", "a=1")
+            .header("Cookie", "b=2")
+            .body(hyper::Body::empty())?;
+        let response = tokio::time::timeout(Duration::from_secs(5), hyper::Client::new().request(request)).await??;
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        assert_eq!(String::from_utf8(body.to_vec())?, r#"["10.0.0.1, 127.0.0.1","a=1; b=2"]"#.to_string());
+
+        tokio::time::timeout(Duration::from_secs(5), gateway.shutdown()).await??;
+        assert!(TcpStream::connect(addr).await.is_err());
 
-        println!("got to the end...");
         Ok(())
     }
 
-    pub struct TestRouter {}
+    async fn select(client: &PortalMemClient) -> Result<ResponseEntity, Error> {
+        let mut request = inlet::Request::new(Operation::Resource(
+            ResourceOperation::Select(Selector::new()),
+        ));
+        request.to.push(client.info.parent.clone());
+        let response = tokio::time::timeout(Duration::from_secs(5), client.portal.skel.api().exchange(request)).await??;
+        Ok(response.signal)
+    }
 
-    impl Router for TestRouter {
-        fn route(&self, message: Message<Operation>) {
-            todo!()
+    fn throttled_scope(signal: ResponseEntity) -> Option<String> {
+        match signal {
+            ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Throttled(throttled))) => Option::Some(throttled.scope),
+            _ => Option::None,
         }
     }
 
+    #[tokio::test]
+    async fn rate_limits_throttle_requests() -> Result<(), Error> {
+        let server = TestPortalServer::new();
+        let muxer = PortalMuxer::new(|muxer| server.router_factory(muxer));
+
+        let policy = Arc::new(Policy::allow_all()
+            .with_portal_limit(RateLimit { requests_per_sec: 2, bytes_per_sec: 0 })
+            .with_owner_limit(RateLimit { requests_per_sec: 3, bytes_per_sec: 0 }));
+
+        let first = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, policy.clone(), test_logger).await?;
+        let second = PortalMemClient::new(server.info("scott".to_string()).await?, muxer.clone(), quiet_portal_ctrl_factory, policy.clone(), test_logger).await?;
+
+        assert_eq!(throttled_scope(select(&first).await?), Option::None);
+        assert_eq!(throttled_scope(select(&first).await?), Option::None);
+        assert_eq!(throttled_scope(select(&first).await?), Option::Some("portal".to_string()));
+
+        assert_eq!(throttled_scope(select(&second).await?), Option::None);
+        assert_eq!(throttled_scope(select(&second).await?), Option::Some("owner".to_string()));
+
+        Ok(())
+    }
+
     pub struct TestPortalServer {
         pub atomic: AtomicU32,
+        pub handshake_timeout: Duration,
     }
 
     impl TestPortalServer {
         pub fn new() -> Self {
             Self {
                 atomic: AtomicU32::new(0),
+                handshake_timeout: Duration::from_secs(30),
             }
         }
     }
@@ -172,10 +1665,16 @@ lazy_static! {
         async fn auth(
             &self,
             reader: &mut PrimitiveFrameReader,
-            writer: &mut PrimitiveFrameWriter,
+            _writer: &mut PrimitiveFrameWriter,
+            peer: Option<PeerIdentity>,
         ) -> Result<String, anyhow::Error> {
             let username = reader.read_string().await?;
             tokio::time::sleep(Duration::from_secs(0)).await;
+            if let Option::Some(PeerIdentity{ common_name: Option::Some(common_name), .. }) = peer {
+                if common_name != username {
+                    return Err(anyhow!("certificate common name '{}' does not match user '{}'", common_name, username));
+                }
+            }
             Ok(username)
         }
 
@@ -206,17 +1705,21 @@ lazy_static! {
             test_logger
         }
 
-        fn router_factory(&self, mux_tx: Sender<MuxCall>) -> Box<dyn Router> {
-            Box::new(InYourFaceRouter { mux_tx })
+        fn router_factory(&self, muxer: MuxerHandle) -> Box<dyn Router> {
+            Box::new(InYourFaceRouter { muxer })
+        }
+
+        fn handshake_timeout(&self) -> Duration {
+            self.handshake_timeout
         }
     }
 
     pub struct InYourFaceRouter {
-        mux_tx: Sender<MuxCall>,
+        muxer: MuxerHandle,
     }
     impl Router for InYourFaceRouter {
         fn route(&self, message: Message<Operation>) {
-            let mux_tx = self.mux_tx.clone();
+            let muxer = self.muxer.clone();
             tokio::spawn(async move {
                 match message {
                     Message::Request(request) => {
@@ -227,25 +1730,10 @@ lazy_static! {
                                         if let ExchangeKind::RequestResponse(exchange_id) =
                                             &request.kind
                                         {
-                                            let (tx, rx) = oneshot::channel();
-
-                                            // this is clearly not an implementation of the Selector
-                                            // in this case it will always select everything from the Muxer
-                                            fn selector(info: &Info) -> bool {
-                                                true
-                                            }
-                                            let selector = MuxCall::Select { selector, tx };
-                                            mux_tx.try_send(selector);
-                                            let infos = rx.await.expect("expected infos");
-                                            let resources: Vec<ResourceStub> = infos
-                                                .iter()
-                                                .map(|i| ResourceStub {
-                                                    id: Identifier::Address(i.address.clone()),
-                                                    key: i.key.clone(),
-                                                    address: i.address.clone(),
-                                                    archetype: i.archetype.clone(),
-                                                })
-                                                .collect();
+                                            let resources = muxer
+                                                .select(selector.clone())
+                                                .await
+                                                .expect("expected resources");
                                             let signal = ResponseEntity::Ok(Entity::Resource(
                                                 ResourceEntity::Stubs(resources),
                                             ));
@@ -256,40 +1744,35 @@ lazy_static! {
                                                     exchange_id: exchange_id.clone(),
                                                     signal,
                                                 };
-                                            mux_tx
-                                                .try_send(MuxCall::MessageOut(Message::Response(
-                                                    response,
-                                                )))
+                                            muxer
+                                                .send(Message::Response(response))
+                                                .await
                                                 .unwrap_or_default();
                                         }
                                     }
-                                    _ => match &request.kind {
-                                        ExchangeKind::RequestResponse(exchange_id) => {
-                                            let response = resource_mesh_portal_api_server::Response {
-                                                to: request.from.clone(),
-                                                from: request.to.clone(),
-                                                exchange_id: exchange_id.clone(),
-                                                signal: ResponseEntity::Error("this is a primitive router that cannot handle resource commands other than Select".to_string())
-                                            };
-                                            mux_tx.try_send(MuxCall::MessageOut(
-                                                Message::Response(response),
-                                            ));
-                                        }
-                                        _ => {}
+                                    _ => if let ExchangeKind::RequestResponse(exchange_id) = &request.kind {
+                                        let response = resource_mesh_portal_api_server::Response {
+                                            to: request.from.clone(),
+                                            from: request.to.clone(),
+                                            exchange_id: exchange_id.clone(),
+                                            signal: ResponseEntity::Error("this is a primitive router that cannot handle resource commands other than Select".to_string())
+                                        };
+                                        muxer.send(Message::Response(response)).await.unwrap_or_default();
                                     },
                                 }
                             }
                             Operation::Ext(_) => {
                                 // since we are not connected to a mesh all inbound messages are just sent back to the outbound
-                                mux_tx.try_send(MuxCall::MessageOut(Message::Request(
-                                    request.try_into().unwrap(),
-                                )));
+                                muxer
+                                    .send(Message::Request(request.try_into().unwrap()))
+                                    .await
+                                    .unwrap_or_default();
                             }
                         }
                     }
                     Message::Response(response) => {
                         // since we are not connected to a mesh all inbound messages are just sent back to the outbound
-                        mux_tx.try_send(MuxCall::MessageOut(Message::Response(response)));
+                        muxer.send(Message::Response(response)).await.unwrap_or_default();
                     }
                 }
             });
@@ -308,12 +1791,12 @@ lazy_static! {
     #[async_trait]
     impl PortalClient for TestPortalClient {
         fn flavor(&self) -> String {
-            return "test".to_string();
+            "test".to_string()
         }
 
         async fn auth(
             &self,
-            reader: &mut PrimitiveFrameReader,
+            _reader: &mut PrimitiveFrameReader,
             writer: &mut PrimitiveFrameWriter,
         ) -> Result<(), Error> {
             writer.write_string(self.user.clone()).await?;
@@ -321,7 +1804,7 @@ lazy_static! {
         }
 
         fn portal_ctrl_factory(&self) -> fn(PortalSkel) -> Box<dyn PortalCtrl> {
-            return friendly_portal_ctrl_factory;
+            friendly_portal_ctrl_factory
         }
 
         fn logger(&self) -> fn(m: &str) {
@@ -332,6 +1815,399 @@ lazy_static! {
         }
     }
 
+    // a TestPortalServer that leaves auth to its Authenticators
+    pub struct AuthenticatingPortalServer {
+        pub server: TestPortalServer,
+        pub authenticators: Authenticators,
+    }
+
+    #[async_trait]
+    impl PortalServer for AuthenticatingPortalServer {
+        fn flavor(&self) -> String {
+            self.server.flavor()
+        }
+
+        async fn auth(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter, _peer: Option<PeerIdentity>) -> Result<String, Error> {
+            self.authenticators.auth(reader, writer).await
+        }
+
+        fn router_factory(&self, muxer: MuxerHandle) -> Box<dyn Router> {
+            self.server.router_factory(muxer)
+        }
+
+        fn logger(&self) -> fn(&str) {
+            self.server.logger()
+        }
+
+        async fn info(&self, user: String) -> Result<Info, Error> {
+            self.server.info(user).await
+        }
+    }
+
+    pub struct AuthenticatingPortalClient {
+        pub credentials: Box<dyn Credentials>,
+    }
+
+    #[async_trait]
+    impl PortalClient for AuthenticatingPortalClient {
+        fn flavor(&self) -> String {
+            "test".to_string()
+        }
+
+        async fn auth(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<(), Error> {
+            self.credentials.authenticate(reader, writer).await
+        }
+
+        fn portal_ctrl_factory(&self) -> fn(PortalSkel) -> Box<dyn PortalCtrl> {
+            quiet_portal_ctrl_factory
+        }
+
+        fn logger(&self) -> fn(m: &str) {
+            test_logger
+        }
+    }
+
+    fn quiet_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
+        Box::new(QuietPortalCtrl { skel })
+    }
+
+    pub struct QuietPortalCtrl {
+        #[allow(dead_code)]
+        pub skel: PortalSkel
+    }
+
+    impl PortalCtrl for QuietPortalCtrl {}
+
+    fn greeter_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
+        Box::new(GreeterPortalCtrl { skel })
+    }
+
+    pub struct GreeterPortalCtrl {
+        pub skel: PortalSkel
+    }
+
+    impl PortalCtrl for GreeterPortalCtrl {
+        fn ports(&self) -> HashMap<String,Box<dyn PortCtrl>> {
+            struct GreetPort {
+                address: String
+            }
+
+            #[async_trait]
+            impl PortCtrl for GreetPort {
+                async fn request( &self, request: client::Request<PortOperation> ) -> Result<Option<ResponseEntity>,Error>{
+                    match &request.entity {
+                        Entity::Payload(Payload::Text(name)) => Ok(Option::Some(ResponseEntity::Ok(
+                            Entity::Payload(Payload::Text(format!("Hello {}, this is {}", name, self.address))),
+                        ))),
+                        _ => Err(anyhow!("unexpected request entity")),
+                    }
+                }
+            }
+
+            let mut ports = HashMap::new();
+            let port : Box<dyn PortCtrl> = Box::new(GreetPort { address: self.skel.info.address.clone() });
+            ports.insert( "greet".to_string(), port );
+            ports
+        }
+    }
+
+    fn guarded_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
+        Box::new(GuardedPortalCtrl { skel })
+    }
+
+    // its ports are behind a decorating layer, a guard and a timeout
+    pub struct GuardedPortalCtrl {
+        #[allow(dead_code)]
+        pub skel: PortalSkel
+    }
+
+    // wraps every text response in <>
+    struct Decorate {}
+
+    #[async_trait]
+    impl Middleware for Decorate {
+        async fn handle( &self, request: Incoming, next: Next<'_> ) -> Result<Option<ResponseEntity>,Error> {
+            match next.run(request).await? {
+                Some(ResponseEntity::Ok(Entity::Payload(Payload::Text(text)))) => Ok(Option::Some(ResponseEntity::Ok(Entity::Payload(Payload::Text(format!("<{}>", text)))))),
+                signal => Ok(signal)
+            }
+        }
+    }
+
+    // admin ports are for the portal itself only
+    struct Guard {}
+
+    #[async_trait]
+    impl Middleware for Guard {
+        async fn handle( &self, request: Incoming, next: Next<'_> ) -> Result<Option<ResponseEntity>,Error> {
+            if let Incoming::Port(port) = &request {
+                if port.port.starts_with("admin-") && *request.from() != Identifier::Key(request.context().portal_info.key.clone()) {
+                    return Ok(Option::Some(ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Forbidden(mesh::Forbidden {
+                        from: format!("{:?}", request.from()),
+                        to: request.context().portal_info.address.clone(),
+                        operation: port.port.clone(),
+                        rule: Option::None
+                    })))));
+                }
+            }
+            next.run(request).await
+        }
+    }
+
+    #[async_trait]
+    impl PortalCtrl for GuardedPortalCtrl {
+        fn ports(&self) -> HashMap<String,Box<dyn PortCtrl>> {
+            struct TextPort {
+                text: String,
+                delay: Duration
+            }
+
+            #[async_trait]
+            impl PortCtrl for TextPort {
+                async fn request( &self, _request: client::Request<PortOperation> ) -> Result<Option<ResponseEntity>,Error>{
+                    tokio::time::sleep(self.delay).await;
+                    Ok(Option::Some(ResponseEntity::Ok(Entity::Payload(Payload::Text(self.text.clone())))))
+                }
+            }
+
+            let mut ports = HashMap::new();
+            for (name, text, delay) in [("greet", "hello", 0), ("admin-reset", "reset", 0), ("stall", "late", 1000)] {
+                let port : Box<dyn PortCtrl> = Box::new(TextPort { text: text.to_string(), delay: Duration::from_millis(delay) });
+                ports.insert( name.to_string(), port );
+            }
+            ports
+        }
+
+        fn middleware(&self) -> Vec<Arc<dyn Middleware>> {
+            vec![Arc::new(Decorate {}), Arc::new(Guard {}), Arc::new(middleware::Timeout::new(Duration::from_millis(200)))]
+        }
+    }
+
+    #[derive(Serialize)]
+    struct User {
+        id: u32,
+        name: String,
+        asked_by: Option<String>,
+        verbose: bool
+    }
+
+    #[derive(Deserialize)]
+    struct NewUser {
+        name: String
+    }
+
+    #[derive(Deserialize)]
+    struct UserQuery {
+        #[serde(default)]
+        verbose: bool
+    }
+
+    async fn get_user(call: HttpCall) -> Result<HttpResponse, Error> {
+        let id: u32 = call.param("id")?;
+        let query: UserQuery = call.query()?;
+        http::json(200, &User { id, name: format!("user-{}", id), asked_by: call.header("x-user")?, verbose: query.verbose })
+    }
+
+    async fn create_user(call: HttpCall) -> Result<HttpResponse, Error> {
+        let user: NewUser = call.json()?;
+        http::json(201, &User { id: 1, name: user.name, asked_by: call.header("x-user")?, verbose: false })
+    }
+
+    async fn get_file(call: HttpCall) -> Result<HttpResponse, Error> {
+        let path: String = call.param("path")?;
+        http::json(200, &path)
+    }
+
+    async fn stall(_call: HttpCall) -> Result<HttpResponse, Error> {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        Ok(HttpResponse::new(200))
+    }
+
+    async fn hops(_call: HttpCall) -> Result<HttpResponse, Error> {
+        let mut response = http::json(200, &"hops".to_string())?;
+        response.headers.insert("Content-Length".to_string(), "999".to_string());
+        response.headers.insert("Transfer-Encoding".to_string(), "chunked".to_string());
+        response.headers.insert("Connection".to_string(), "keep-alive, x-hop".to_string());
+        response.headers.insert("X-Hop".to_string(), "portal".to_string());
+        Ok(response)
+    }
+
+    async fn forwarded(call: HttpCall) -> Result<HttpResponse, Error> {
+        http::json(200, &(call.header::<String>("x-forwarded-for")?, call.header::<String>("cookie")?))
+    }
+
+    fn routed_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
+        let router = HttpRouter::new()
+            .with_route("GET", "/users/:id", get_user)
+            .with_route("POST", "/users", create_user)
+            .with_route("GET", "/files/*path", get_file)
+            .with_route("GET", "/stall", stall)
+            .with_route("GET", "/hops", hops)
+            .with_route("GET", "/forwarded", forwarded);
+        Box::new(RoutedPortalCtrl { skel, router })
+    }
+
+    // answers http through an HttpRouter
+    pub struct RoutedPortalCtrl {
+        #[allow(dead_code)]
+        pub skel: PortalSkel,
+        pub router: HttpRouter
+    }
+
+    #[async_trait]
+    impl PortalCtrl for RoutedPortalCtrl {
+        async fn http_request(&self, request: client::Request<HttpRequest>) -> Result<HttpResponse, Error> {
+            self.router.route(request).await
+        }
+    }
+
+    static HANDLED: AtomicU32 = AtomicU32::new(0);
+
+    fn counting_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
+        Box::new(CountingPortalCtrl { skel })
+    }
+
+    // counts every request its port handles in HANDLED, taking its time when asked to be slow
+    pub struct CountingPortalCtrl {
+        #[allow(dead_code)]
+        pub skel: PortalSkel
+    }
+
+    #[async_trait]
+    impl PortalCtrl for CountingPortalCtrl {
+        fn ports(&self) -> HashMap<String,Box<dyn PortCtrl>> {
+            struct OncePort {}
+
+            #[async_trait]
+            impl PortCtrl for OncePort {
+                async fn request( &self, request: client::Request<PortOperation> ) -> Result<Option<ResponseEntity>,Error>{
+                    let handled = HANDLED.fetch_add(1, Ordering::SeqCst) + 1;
+                    if let Entity::Payload(Payload::Text(text)) = &request.entity {
+                        if text == "slow" {
+                            tokio::time::sleep(Duration::from_millis(1500)).await;
+                        }
+                    }
+                    Ok(Option::Some(ResponseEntity::Ok(Entity::Payload(Payload::Text(format!("handled {}", handled))))))
+                }
+            }
+
+            let mut ports = HashMap::new();
+            let port : Box<dyn PortCtrl> = Box::new(OncePort {});
+            ports.insert( "once".to_string(), port );
+            ports
+        }
+    }
+
+    fn draining_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
+        Box::new(DrainingPortalCtrl { skel })
+    }
+
+    pub struct DrainingPortalCtrl {
+        pub skel: PortalSkel
+    }
+
+    #[async_trait]
+    impl PortalCtrl for DrainingPortalCtrl {
+        fn ports(&self) -> HashMap<String,Box<dyn PortCtrl>> {
+            struct SlowPort {}
+
+            #[async_trait]
+            impl PortCtrl for SlowPort {
+                async fn request( &self, _request: client::Request<PortOperation> ) -> Result<Option<ResponseEntity>,Error>{
+                    tokio::time::sleep(Duration::from_millis(250)).await;
+                    Ok(Option::Some(ResponseEntity::Ok(Entity::Payload(Payload::Text("done".to_string())))))
+                }
+            }
+
+            let mut ports = HashMap::new();
+            let port : Box<dyn PortCtrl> = Box::new(SlowPort {});
+            ports.insert( "slow".to_string(), port );
+            ports
+        }
+
+        async fn on_close(&self, _reason: CloseReason) -> Result<(), Error> {
+            SHUTDOWN_TX.send(self.skel.info.address.clone()).unwrap_or_default();
+            Ok(())
+        }
+    }
+
+    fn lifecycle_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
+        Box::new(LifecyclePortalCtrl { skel })
+    }
+
+    // reports every hook as (address, hook) on LIFECYCLE_TX
+    pub struct LifecyclePortalCtrl {
+        pub skel: PortalSkel
+    }
+
+    impl LifecyclePortalCtrl {
+        fn report(&self, hook: String) -> Result<(), Error> {
+            LIFECYCLE_TX.send((self.skel.info.address.clone(), hook)).unwrap_or_default();
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl PortalCtrl for LifecyclePortalCtrl {
+        async fn on_ready(&self) -> Result<(), Error> {
+            self.report("ready".to_string())
+        }
+
+        async fn on_status_change(&self, status: Status) -> Result<(), Error> {
+            self.report(format!("status {}", status))
+        }
+
+        async fn on_close(&self, reason: CloseReason) -> Result<(), Error> {
+            self.report(format!("close {}", reason))
+        }
+
+        async fn on_shutdown(&self, deadline: std::time::Instant) -> Result<(), Error> {
+            // the ctrl's own shutdown_timeout, not the portal's response_timeout
+            assert!(deadline > std::time::Instant::now() + Duration::from_secs(20));
+            self.report("shutdown".to_string())
+        }
+
+        fn shutdown_timeout(&self) -> Duration {
+            Duration::from_secs(30)
+        }
+
+        async fn on_disconnect(&self) -> Result<(), Error> {
+            self.report("disconnect".to_string())
+        }
+
+        async fn on_reconnect(&self) -> Result<(), Error> {
+            self.report("reconnect".to_string())
+        }
+    }
+
+    // a transport that goes nowhere
+    struct NullInlet {}
+
+    impl Inlet for NullInlet {
+        fn send_frame(&self, _frame: inlet::Frame) {}
+    }
+
+    fn stateful_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
+        Box::new(StatefulPortalCtrl { skel })
+    }
+
+    // counts its own starts in its state
+    pub struct StatefulPortalCtrl {
+        pub skel: PortalSkel
+    }
+
+    #[async_trait]
+    impl PortalCtrl for StatefulPortalCtrl {
+        fn persists_state(&self) -> bool {
+            true
+        }
+
+        async fn init(&mut self) -> Result<(), Error> {
+            let starts: u32 = self.skel.state.get("starts")?.unwrap_or_default();
+            self.skel.state.set("starts", &(starts + 1))
+        }
+    }
+
     fn friendly_portal_ctrl_factory(skel: PortalSkel) -> Box<dyn PortalCtrl> {
         Box::new(FriendlyPortalCtrl { skel })
     }
@@ -345,7 +2221,7 @@ lazy_static! {
         async fn init(&mut self) -> Result<(), Error> {
             println!("FriendlyPortalCtrl.init()");
             // wait just a bit to make sure everyone got chance to be in the muxer
-            tokio::time::sleep(Duration::from_millis(50));
+            tokio::time::sleep(Duration::from_millis(50)).await;
 
             let mut request = inlet::Request::new(Operation::Resource(
                 ResourceOperation::Select(Selector::new()),
@@ -353,49 +2229,46 @@ lazy_static! {
             request.to.push(self.skel.info.parent.clone());
 
 println!("FriendlyPortalCtrl::exchange...");
-            match self.skel.api().exchange(request).await {
-                Ok(response) => match response.signal {
-                    ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(resources))) => {
-println!("FriendlyPortalCtrl::Ok");
-                        for resource in resources {
-                            if resource.key != self.skel.info.key {
-                                (self.skel.logger)(format!(
-                                    "INFO: found resource: {}",
-                                    resource.address
-                                ).as_str());
-                                let mut request = inlet::Request::new(Operation::Ext(
-                                    ExtOperation::Port(PortOperation {
-                                        port: "greet".to_string(),
-                                        entity: Entity::Payload(Payload::Text(format!(
-                                            "Hello, my name is '{}' and I live at '{}'",
-                                            self.skel.info.owner, self.skel.info.address
-                                        ))),
-                                    }),
-                                ));
-                                let result = self.skel.api().exchange(request).await;
-                                match result {
-                                    Ok(response) => {
-                                        match &response.signal {
-                                            ResponseEntity::Ok(Entity::Payload(Payload::Text(response))) => {
-                                                println!("got response: {}", response );
-                                                GLOBAL_TX.send(GlobalEvent::Finished(self.skel.info.owner.clone()));
-                                            }
-                                            _ => {
-                                                GLOBAL_TX.send(GlobalEvent::Fail(self.skel.info.owner.clone()));
+            if let Ok(response) = self.skel.api().exchange(request).await { match response.signal {
+                                ResponseEntity::Ok(Entity::Resource(ResourceEntity::Stubs(resources))) => {
+            println!("FriendlyPortalCtrl::Ok");
+                                    for resource in resources {
+                                        if resource.key != self.skel.info.key {
+                                            (self.skel.logger)(format!(
+                                                "INFO: found resource: {}",
+                                                resource.address
+                                            ).as_str());
+                                            let request = inlet::Request::new(Operation::Ext(
+                                                ExtOperation::Port(PortOperation {
+                                                    port: "greet".to_string(),
+                                                    entity: Entity::Payload(Payload::Text(format!(
+                                                        "Hello, my name is '{}' and I live at '{}'",
+                                                        self.skel.info.owner, self.skel.info.address
+                                                    ))),
+                                                }),
+                                            ));
+                                            let result = self.skel.api().exchange(request).await;
+                                            match result {
+                                                Ok(response) => {
+                                                    match &response.signal {
+                                                        ResponseEntity::Ok(Entity::Payload(Payload::Text(response))) => {
+                                                            println!("got response: {}", response );
+                                                            GLOBAL_TX.send(GlobalEvent::Finished(self.skel.info.owner.clone())).unwrap_or_default();
+                                                        }
+                                                        _ => {
+                                                            GLOBAL_TX.send(GlobalEvent::Fail(self.skel.info.owner.clone())).unwrap_or_default();
+                                                        }
+                                                    }
+                                                }
+                                                Err(_) => {
+                                                    GLOBAL_TX.send(GlobalEvent::Fail(self.skel.info.owner.clone())).unwrap_or_default();
+                                                }
                                             }
                                         }
                                     }
-                                    Err(_) => {
-                                        GLOBAL_TX.send(GlobalEvent::Fail(self.skel.info.owner.clone()));
-                                    }
                                 }
-                            }
-                        }
-                    }
-                    _ => (self.skel.logger)("ERROR: unexpected response!"),
-                },
-                Err(_) => {}
-            }
+                                _ => (self.skel.logger)("ERROR: unexpected response!"),
+                            } }
 
             Ok(())
         }
@@ -403,6 +2276,7 @@ println!("FriendlyPortalCtrl::Ok");
         fn ports(&self) -> HashMap<String,Box<dyn PortCtrl>> {
 
             struct GreetPort {
+                #[allow(dead_code)]
                 skel: PortalSkel
             }
 
@@ -410,7 +2284,7 @@ println!("FriendlyPortalCtrl::Ok");
             impl PortCtrl for GreetPort {
                 async fn request( &self, request: client::Request<PortOperation> ) -> Result<Option<ResponseEntity>,Error>{
                     match &request.entity {
-                        Entity::Payload(Payload::Text(text)) => Ok(Option::Some(ResponseEntity::Ok(
+                        Entity::Payload(Payload::Text(_text)) => Ok(Option::Some(ResponseEntity::Ok(
                             Entity::Payload(Payload::Text("Hello, <username>".to_string())),
                         ))),
                         _ => Err(anyhow!("unexpected request entity")),
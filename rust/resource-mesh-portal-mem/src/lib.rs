@@ -0,0 +1,127 @@
+#[macro_use]
+extern crate anyhow;
+
+use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
+
+use anyhow::Error;
+use tokio::sync::mpsc;
+
+use resource_mesh_portal_api_client::{Inlet, Portal, PortalCtrl, PortalSkel};
+use resource_mesh_portal_api_server::MuxerHandle;
+use resource_mesh_portal_api_server::policy::Policy;
+use resource_mesh_portal_serde::version::latest::config::Info;
+use resource_mesh_portal_serde::version::latest::frame::PrimitiveFrame;
+use resource_mesh_portal_serde::version::latest::portal::{inlet, outlet};
+
+// links an api-client Portal directly to an api-server Portal through channels.
+// no sockets are involved, yet every frame travels exactly as it would over the wire
+// and when codec is enabled each frame is serialized & deserialized along the way
+pub struct PortalMemClient {
+    pub info: Info,
+    pub portal: Arc<Portal>
+}
+
+impl PortalMemClient {
+
+    pub async fn new( info: Info, muxer: MuxerHandle, ctrl_factory: fn(skel: PortalSkel) -> Box<dyn PortalCtrl>, policy: Arc<Policy>, logger: fn(message: &str) ) -> Result<Self,Error> {
+        Self::connect(info, muxer, ctrl_factory, policy, logger, false ).await
+    }
+
+    pub async fn new_with_codec( info: Info, muxer: MuxerHandle, ctrl_factory: fn(skel: PortalSkel) -> Box<dyn PortalCtrl>, policy: Arc<Policy>, logger: fn(message: &str) ) -> Result<Self,Error> {
+        Self::connect(info, muxer, ctrl_factory, policy, logger, true ).await
+    }
+
+    async fn connect( info: Info, muxer: MuxerHandle, ctrl_factory: fn(skel: PortalSkel) -> Box<dyn PortalCtrl>, policy: Arc<Policy>, logger: fn(message: &str), codec: bool ) -> Result<Self,Error> {
+        let (outlet_tx, mut outlet_rx) = mpsc::channel(1024);
+        let (inlet_tx, inlet_rx) = mpsc::channel(1024);
+
+        let portal = resource_mesh_portal_api_server::Portal::new(info.clone(), outlet_tx, inlet_rx, policy, resource_mesh_portal_api_server::log );
+        muxer.add(portal).await?;
+
+        let info = match outlet_rx.recv().await {
+            Some(frame) => {
+                match round_trip(frame, codec)? {
+                    outlet::Frame::Init(info) => info,
+                    _ => {
+                        let message = "expected portal info.".to_string();
+                        (logger)(message.as_str());
+                        return Err(anyhow!(message));
+                    }
+                }
+            }
+            None => {
+                return Err(anyhow!("server portal closed before sending portal info."));
+            }
+        };
+
+        let (client_outlet_tx, client_outlet_rx) = mpsc::channel(1024);
+        tokio::spawn(async move {
+            while let Option::Some(frame) = outlet_rx.recv().await {
+                let close = matches!(frame, outlet::Frame::Close(_));
+                match round_trip(frame, codec) {
+                    Ok(frame) => {
+                        if client_outlet_tx.send(frame).await.is_err() {
+                            (logger)("FATAL: client portal outlet disconnected");
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        (logger)(format!("ERROR: outlet frame codec error: {}", err).as_str());
+                    }
+                }
+                // like any transport nothing follows a Close frame
+                if close {
+                    break;
+                }
+            }
+        });
+
+        let inlet = Box::new(MemInlet {
+            sender: inlet_tx,
+            codec,
+            logger
+        });
+
+        let portal = Portal::new(info.clone(), inlet, client_outlet_rx, ctrl_factory, logger).await?;
+
+        Ok(Self {
+            info,
+            portal
+        })
+    }
+}
+
+struct MemInlet {
+    pub sender: mpsc::Sender<inlet::Frame>,
+    pub codec: bool,
+    pub logger: fn( message: &str )
+}
+
+impl Inlet for MemInlet {
+    fn send_frame(&self, frame: inlet::Frame) {
+        let frame = match round_trip(frame, self.codec) {
+            Ok(frame) => frame,
+            Err(err) => {
+                (self.logger)(format!("ERROR: inlet frame codec error: {}", err).as_str());
+                return;
+            }
+        };
+        match self.sender.try_send(frame)
+        {
+            Ok(_) => {}
+            Err(_) => {
+                (self.logger)("ERROR: frame failed to send to server portal inlet")
+            }
+        }
+    }
+}
+
+fn round_trip<FRAME>( frame: FRAME, codec: bool ) -> Result<FRAME,Error> where FRAME: TryInto<PrimitiveFrame,Error=Error>+TryFrom<PrimitiveFrame,Error=Error> {
+    if codec {
+        let frame: PrimitiveFrame = frame.try_into()?;
+        FRAME::try_from(frame)
+    } else {
+        Ok(frame)
+    }
+}
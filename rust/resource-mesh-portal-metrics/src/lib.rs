@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// seconds, roughly what a request/response exchange is expected to take
+pub const LATENCY_BUCKETS: [f64; 12] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64
+}
+
+impl Counter {
+    pub fn inc( &self ) {
+        self.add(1);
+    }
+
+    pub fn add( &self, amount: u64 ) {
+        self.value.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn get( &self ) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gauge {
+    value: AtomicI64
+}
+
+impl Gauge {
+    pub fn inc( &self ) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec( &self ) {
+        self.value.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn set( &self, value: i64 ) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get( &self ) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    // f64 bits, updated with compare & swap
+    sum: AtomicU64
+}
+
+impl Histogram {
+    pub fn new( bounds: &[f64] ) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            buckets: bounds.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0f64.to_bits())
+        }
+    }
+
+    pub fn observe( &self, value: f64 ) {
+        for (bound, bucket) in self.bounds.iter().zip(self.buckets.iter()) {
+            if value <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let mut current = self.sum.load(Ordering::Relaxed);
+        loop {
+            let next = (f64::from_bits(current) + value).to_bits();
+            match self.sum.compare_exchange_weak(current, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(actual) => current = actual
+            }
+        }
+    }
+
+    pub fn observe_duration( &self, duration: Duration ) {
+        self.observe(duration.as_secs_f64());
+    }
+
+    pub fn count( &self ) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn sum( &self ) -> f64 {
+        f64::from_bits(self.sum.load(Ordering::Relaxed))
+    }
+}
+
+#[derive(Clone)]
+enum Metric {
+    Counter(Arc<Counter>),
+    Gauge(Arc<Gauge>),
+    Histogram(Arc<Histogram>)
+}
+
+impl Metric {
+    fn kind( &self ) -> &'static str {
+        match self {
+            Metric::Counter(_) => "counter",
+            Metric::Gauge(_) => "gauge",
+            Metric::Histogram(_) => "histogram"
+        }
+    }
+}
+
+struct Family {
+    help: String,
+    series: BTreeMap<Vec<(String,String)>,Metric>
+}
+
+// every metric is identified by its name and its labels, asking for the same pair
+// twice hands back the same metric so callers never need to hold on to the Registry
+pub struct Registry {
+    families: Mutex<BTreeMap<String,Family>>
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self {
+            families: Mutex::new(BTreeMap::new())
+        }
+    }
+
+    pub fn counter( &self, name: &str, help: &str, labels: &[(&str,&str)] ) -> Arc<Counter> {
+        match self.metric(name, help, labels, || Metric::Counter(Arc::new(Counter::default()))) {
+            Metric::Counter(counter) => counter,
+            _ => panic!("metric '{}' is not a counter", name)
+        }
+    }
+
+    pub fn gauge( &self, name: &str, help: &str, labels: &[(&str,&str)] ) -> Arc<Gauge> {
+        match self.metric(name, help, labels, || Metric::Gauge(Arc::new(Gauge::default()))) {
+            Metric::Gauge(gauge) => gauge,
+            _ => panic!("metric '{}' is not a gauge", name)
+        }
+    }
+
+    pub fn histogram( &self, name: &str, help: &str, labels: &[(&str,&str)], bounds: &[f64] ) -> Arc<Histogram> {
+        match self.metric(name, help, labels, || Metric::Histogram(Arc::new(Histogram::new(bounds)))) {
+            Metric::Histogram(histogram) => histogram,
+            _ => panic!("metric '{}' is not a histogram", name)
+        }
+    }
+
+    fn metric( &self, name: &str, help: &str, labels: &[(&str,&str)], create: impl FnOnce() -> Metric ) -> Metric {
+        let labels: Vec<(String,String)> = labels.iter().map(|(k,v)| (k.to_string(), v.to_string())).collect();
+        let mut families = self.families.lock().expect("expected metrics lock");
+        let family = families.entry(name.to_string()).or_insert_with(|| Family {
+            help: help.to_string(),
+            series: BTreeMap::new()
+        });
+        family.series.entry(labels).or_insert_with(create).clone()
+    }
+
+    // drops every series carrying label=value, for instance everything about a portal that has left
+    pub fn forget( &self, label: &str, value: &str ) {
+        let mut families = self.families.lock().expect("expected metrics lock");
+        for family in families.values_mut() {
+            family.series.retain(|labels,_| !labels.iter().any(|(k,v)| k == label && v == value));
+        }
+        families.retain(|_,family| !family.series.is_empty());
+    }
+
+    // Prometheus text exposition format 0.0.4
+    pub fn render( &self ) -> String {
+        let families = self.families.lock().expect("expected metrics lock");
+        let mut out = String::new();
+        for (name, family) in families.iter() {
+            let kind = match family.series.values().next() {
+                Some(metric) => metric.kind(),
+                None => continue
+            };
+            writeln!(out, "# HELP {} {}", name, escape_help(family.help.as_str())).unwrap_or_default();
+            writeln!(out, "# TYPE {} {}", name, kind).unwrap_or_default();
+            for (labels, metric) in family.series.iter() {
+                match metric {
+                    Metric::Counter(counter) => {
+                        writeln!(out, "{}{} {}", name, render_labels(labels, Option::None), counter.get()).unwrap_or_default();
+                    }
+                    Metric::Gauge(gauge) => {
+                        writeln!(out, "{}{} {}", name, render_labels(labels, Option::None), gauge.get()).unwrap_or_default();
+                    }
+                    Metric::Histogram(histogram) => {
+                        for (bound, bucket) in histogram.bounds.iter().zip(histogram.buckets.iter()) {
+                            let le = ("le".to_string(), bound.to_string());
+                            writeln!(out, "{}_bucket{} {}", name, render_labels(labels, Option::Some(&le)), bucket.load(Ordering::Relaxed)).unwrap_or_default();
+                        }
+                        let le = ("le".to_string(), "+Inf".to_string());
+                        writeln!(out, "{}_bucket{} {}", name, render_labels(labels, Option::Some(&le)), histogram.count()).unwrap_or_default();
+                        writeln!(out, "{}_sum{} {}", name, render_labels(labels, Option::None), histogram.sum()).unwrap_or_default();
+                        writeln!(out, "{}_count{} {}", name, render_labels(labels, Option::None), histogram.count()).unwrap_or_default();
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn render_labels( labels: &[(String,String)], extra: Option<&(String,String)> ) -> String {
+    let labels: Vec<String> = labels.iter().chain(extra)
+        .map(|(k,v)| format!("{}=\"{}\"", k, escape_label(v.as_str())))
+        .collect();
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+fn escape_label( value: &str ) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn escape_help( value: &str ) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+static GLOBAL: OnceLock<Registry> = OnceLock::new();
+
+// the registry every portal crate reports to
+pub fn global() -> &'static Registry {
+    GLOBAL.get_or_init(Registry::new)
+}
+
+// the same set of metrics is kept for every portal on both the server & the client side.  the
+// key tells apart portals that reuse an address, so forgetting a key forgets just the one portal
+#[derive(Clone)]
+pub struct PortalMetrics {
+    pub frames_in: Arc<Counter>,
+    pub frames_out: Arc<Counter>,
+    pub bytes_in: Arc<Counter>,
+    pub bytes_out: Arc<Counter>,
+    pub exchanges_started: Arc<Counter>,
+    pub exchanges_completed: Arc<Counter>,
+    pub exchanges_timed_out: Arc<Counter>,
+    pub exchange_seconds: Arc<Histogram>
+}
+
+impl PortalMetrics {
+    pub fn new( registry: &Registry, side: &str, key: &str, portal: &str ) -> Self {
+        let labels = [("side", side), ("key", key), ("portal", portal)];
+        Self {
+            frames_in: registry.counter("portal_frames_in_total", "Frames received by a portal.", &labels),
+            frames_out: registry.counter("portal_frames_out_total", "Frames sent by a portal.", &labels),
+            bytes_in: registry.counter("portal_bytes_in_total", "Serialized bytes of the frames received by a portal.", &labels),
+            bytes_out: registry.counter("portal_bytes_out_total", "Serialized bytes of the frames sent by a portal.", &labels),
+            exchanges_started: registry.counter("portal_exchanges_started_total", "Request/response exchanges started by a portal.", &labels),
+            exchanges_completed: registry.counter("portal_exchanges_completed_total", "Request/response exchanges that received a response.", &labels),
+            exchanges_timed_out: registry.counter("portal_exchanges_timed_out_total", "Request/response exchanges that gave up waiting for a response.", &labels),
+            exchange_seconds: registry.histogram("portal_exchange_seconds", "Time from request to response of an exchange.", &labels, &LATENCY_BUCKETS)
+        }
+    }
+}
+
+// answers GET /metrics with the global registry.  binding to a loopback address
+// is strongly advised since nothing here authenticates the scraper
+pub async fn serve( addr: &str ) -> Result<SocketAddr,Error> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut request = vec![];
+                let mut buf = [0u8; 1024];
+                // only the request line matters, so stop at the end of the headers
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n])
+                    }
+                }
+                let request = String::from_utf8_lossy(request.as_slice());
+                let response = match request.lines().next() {
+                    Some(line) if line.starts_with("GET /metrics ") => {
+                        let body = global().render();
+                        format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+                    }
+                    _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                };
+                stream.write_all(response.as_bytes()).await.unwrap_or_default();
+                stream.shutdown().await.unwrap_or_default();
+            });
+        }
+    });
+    Ok(local_addr)
+}
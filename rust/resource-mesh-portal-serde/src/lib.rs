@@ -1,13 +1,7 @@
 #[macro_use]
 extern crate anyhow;
 
-#[macro_use]
-extern crate lazy_static;
-
-use std::collections::HashMap;
-use std::sync::Arc;
 
-use serde::{Deserialize, Serialize};
 use crate::version::latest::log::Log;
 
 pub mod version;
@@ -22,7 +22,7 @@ pub mod generic {
     use crate::version::latest::portal::{inlet, outlet};
     use crate::version::latest::delivery::ResponseEntity;
     use crate::version::latest::id::Identifier;
-    use crate::version::latest::messaging::{ExchangeId, ExchangeKind};
+    use crate::version::latest::messaging::{ExchangeId, ExchangeKind, IdempotencyKey};
     use crate::version::latest::operation::{ExtOperation, Operation};
 
     #[derive(Clone)]
@@ -31,6 +31,7 @@ pub mod generic {
         pub from: Identifier,
         pub operation: OPERATION,
         pub kind: ExchangeKind,
+        pub idempotency_key: Option<IdempotencyKey>
     }
 
     impl<OPERATION> Request<OPERATION> {
@@ -39,7 +40,8 @@ pub mod generic {
                 to,
                 from,
                 operation,
-                kind: ExchangeKind::None
+                kind: ExchangeKind::None,
+                idempotency_key: Option::None
             }
         }
     }
@@ -57,7 +59,8 @@ pub mod generic {
                         to: self.to,
                         from: self.from,
                         operation: ext,
-                        kind: self.kind
+                        kind: self.kind,
+                        idempotency_key: self.idempotency_key
                     })
                 }
             }
@@ -70,27 +73,30 @@ pub mod generic {
                 to,
                 from,
                 operation: request.operation,
-                kind: request.kind
+                kind: request.kind,
+                idempotency_key: request.idempotency_key
             }
         }
     }
 
-    impl Into<inlet::Request> for Request<Operation> {
-        fn into(self) -> inlet::Request {
+    impl From<Request<Operation>> for inlet::Request {
+        fn from(val: Request<Operation>) -> Self {
             inlet::Request {
-                to: vec![self.to],
-                operation: self.operation,
-                kind: self.kind
+                to: vec![val.to],
+                operation: val.operation,
+                kind: val.kind,
+                idempotency_key: val.idempotency_key
             }
         }
     }
 
-    impl Into<outlet::Request> for Request<ExtOperation> {
-        fn into(self) -> outlet::Request {
+    impl From<Request<ExtOperation>> for outlet::Request {
+        fn from(val: Request<ExtOperation>) -> Self {
             outlet::Request {
-                from: self.from,
-                operation: self.operation,
-                kind: self.kind
+                from: val.from,
+                operation: val.operation,
+                kind: val.kind,
+                idempotency_key: val.idempotency_key
             }
         }
     }
@@ -114,22 +120,22 @@ pub mod generic {
         }
     }
 
-    impl Into<inlet::Response> for Response {
-        fn into(self) -> inlet::Response {
+    impl From<Response> for inlet::Response {
+        fn from(val: Response) -> Self {
             inlet::Response {
-                to: self.to,
-                exchange_id: self.exchange_id,
-                signal: self.signal
+                to: val.to,
+                exchange_id: val.exchange_id,
+                signal: val.signal
             }
         }
     }
 
-    impl Into<outlet::Response> for Response {
-        fn into(self) -> outlet::Response {
+    impl From<Response> for outlet::Response {
+        fn from(val: Response) -> Self {
             outlet::Response {
-                from: self.from,
-                exchange_id: self.exchange_id,
-                signal: self.signal
+                from: val.from,
+                exchange_id: val.exchange_id,
+                signal: val.signal
             }
         }
     }
@@ -1,5 +1,7 @@
 use crate::version::v0_0_1;
 
+// everything 0.0.2 changed comes from v0_0_2, the rest is still as it was in v0_0_1
+
 pub type State=v0_0_1::State;
 pub type ArtifactRef=v0_0_1::ArtifactRef;
 pub type Artifact=v0_0_1::Artifact;
@@ -20,12 +22,22 @@ pub mod id {
 
     pub type Identifiers = generic::id::Identifiers<Key, Address>;
     pub type Identifier = generic::id::Identifier<Key, Address>;
+
+    // the key or address as it is, for logs & for matching against patterns
+    pub fn identifier_name( identifier: &Identifier ) -> String {
+        match identifier {
+            Identifier::Key(key) => key.clone(),
+            Identifier::Address(address) => address.clone()
+        }
+    }
 }
 
 pub mod messaging {
     use crate::version::v0_0_1::messaging;
+    use crate::version::v0_0_2;
     pub type ExchangeId = messaging::ExchangeId;
     pub type ExchangeKind = messaging::ExchangeKind;
+    pub type IdempotencyKey = v0_0_2::messaging::IdempotencyKey;
 }
 
 
@@ -64,28 +76,30 @@ pub mod command {
 
 pub mod http {
     use crate::version::v0_0_1::http;
+    use crate::version::v0_0_2;
 
-    pub type HttpRequest = http::HttpRequest;
+    pub type HttpRequest = v0_0_2::http::HttpRequest;
     pub type HttpResponse = http::HttpResponse;
 }
 
 pub mod resource {
     use crate::version::latest::id::{Key, Address, Kind};
-    use serde::{Deserialize, Serialize};
+
     use crate::version::v0_0_1::resource;
 
-    use crate::version::v0_0_1::{State, generic};
+    use crate::version::v0_0_1::generic;
+    use crate::version::v0_0_2;
 
     pub type Status = resource::Status;
 
-    pub type Operation=generic::operation::Operation<Key,Address,Kind>;
-    pub type ResourceOperation=generic::operation::ResourceOperation<Key,Address,Kind>;
+    pub type Operation=v0_0_2::generic::operation::Operation<Key,Address,Kind>;
+    pub type ResourceOperation=v0_0_2::generic::operation::ResourceOperation<Key,Address,Kind>;
     pub type Create=generic::resource::Create<Key,Address,Kind>;
 
     pub type StateSrc=generic::resource::StateSrc;
     pub type CreateStrategy=generic::resource::CreateStrategy;
     pub type AddressSrc=generic::resource::AddressSrc;
-    pub type Selector=generic::resource::Selector;
+    pub type Selector=v0_0_2::generic::resource::Selector;
     pub type MetaSelector=generic::resource::MetaSelector;
     pub type ResourceStub = generic::resource::ResourceStub<Key,Address,Kind>;
     pub type Archetype = generic::resource::Archetype<Kind>;
@@ -94,11 +108,12 @@ pub mod resource {
 pub mod config {
     use crate::version::latest::id::{Key, Address, Kind};
     use crate::version::v0_0_1::config;
-    use crate::version::v0_0_1::generic;
+    use crate::version::v0_0_2;
 
-    pub type Info = generic::config::Info<Key,Address,Kind>;
+    pub type Info = v0_0_2::generic::config::Info<Key,Address,Kind>;
     pub type PortalKind = config::PortalKind;
-    pub type Config = config::Config;
+    pub type Config = v0_0_2::config::Config;
+    pub type RateLimit = v0_0_2::config::RateLimit;
     pub type SchemaRef = config::SchemaRef;
     pub type BindConfig = config::BindConfig;
     pub type PortConfig = config::PortConfig;
@@ -111,37 +126,59 @@ pub mod delivery {
     use crate::version::latest::id::{Key, Address, Kind};
     use crate::version::v0_0_1::delivery;
     use crate::version::v0_0_1::generic;
+    use crate::version::v0_0_2;
 
     pub type Payload = delivery::Payload;
     pub type Entity = generic::delivery::Entity<Key,Address,Kind>;
     pub type ResourceEntity = generic::delivery::ResourceEntity<Key,Address,Kind>;
-    pub type ResponseEntity = generic::delivery::ResponseEntity<Key,Address,Kind>;
+    pub type ResponseEntity = v0_0_2::generic::delivery::ResponseEntity<Key,Address,Kind>;
 }
 
 
 
+pub mod fail {
+    use crate::version::v0_0_1::generic::fail;
+    use crate::version::v0_0_2;
+
+    pub type Fail = v0_0_2::generic::fail::Fail;
+    pub type Standard = fail::Standard;
+    pub type NotFound = fail::NotFound;
+    pub type Bad = fail::Bad;
+    pub type Timeout = fail::Timeout;
+
+    pub mod mesh {
+        use crate::version::v0_0_2::generic::fail::mesh;
+
+        pub type Fail = mesh::Fail;
+        pub type Forbidden = mesh::Forbidden;
+        pub type Throttled = mesh::Throttled;
+    }
+
+    pub mod resource {
+        use crate::version::v0_0_1::generic::fail::resource;
+
+        pub type Fail = resource::Fail;
+    }
+}
+
 pub mod operation {
     use crate::version::latest::id::{Key, Address, Kind};
 
-    use crate::version::v0_0_1::generic::resource::{Create, Selector};
     use crate::version::v0_0_1::generic::operation;
+    use crate::version::v0_0_2;
 
-    pub type Operation = operation::Operation<Key,Address,Kind>;
-    pub type ResourceOperation = operation::ResourceOperation<Key,Address,Kind>;
-    pub type ExtOperation = operation::ExtOperation<Key,Address,Kind>;
+    pub type Operation = v0_0_2::generic::operation::Operation<Key,Address,Kind>;
+    pub type ResourceOperation = v0_0_2::generic::operation::ResourceOperation<Key,Address,Kind>;
+    pub type ExtOperation = v0_0_2::generic::operation::ExtOperation<Key,Address,Kind>;
     pub type PortOperation = operation::PortOperation<Key,Address,Kind>;
 }
 
 pub mod portal {
     pub mod inlet {
         use crate::version::latest::id::{Key, Address, Kind};
-        use std::convert::TryFrom;
-        use std::convert::TryInto;
 
-        use anyhow::Error;
-        use serde::{Deserialize, Serialize};
 
-        use crate::version::v0_0_1::generic;
+        use crate::version::v0_0_2::generic;
 
         pub type Request=generic::portal::inlet::Request<Key,Address,Kind>;
         pub type Response=generic::portal::inlet::Response<Key,Address,Kind>;
@@ -151,12 +188,8 @@ pub mod portal {
     pub mod outlet {
         use crate::version::latest::id::{Key, Address, Kind};
 
-        use std::convert::TryFrom;
-        use std::convert::TryInto;
 
-        use anyhow::Error;
-        use serde::{Deserialize, Serialize};
-        use crate::version::v0_0_1::generic;
+        use crate::version::v0_0_2::generic;
 
 
         pub type Request=generic::portal::outlet::Request<Key,Address,Kind>;
@@ -1,10 +1,81 @@
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
 use std::str::FromStr;
-use nom::error::{context, VerboseError};
-use nom::sequence::{tuple, terminated, separated_pair};
-use nom::character::complete::digit1;
-use nom::bytes::complete::tag;
+
 use anyhow::Error;
-use nom::IResult;
+
+use crate::version::latest::frame::PrimitiveFrame;
+use crate::version::latest::portal::{inlet, outlet};
 
 pub mod v0_0_1;
+pub mod v0_0_2;
 pub mod latest;
+
+// the frame layout a transport speaks, which one is agreed on during the handshake.  frames are
+// always handled as latest & converted on their way to & from a peer of an older version
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WireVersion {
+    V0_0_1,
+    V0_0_2
+}
+
+impl WireVersion {
+    pub fn latest() -> Self {
+        WireVersion::V0_0_2
+    }
+
+    pub fn encode_inlet( &self, frame: inlet::Frame ) -> Result<PrimitiveFrame,Error> {
+        match self {
+            WireVersion::V0_0_1 => v0_0_1::generic::portal::inlet::Frame::from(frame).try_into(),
+            WireVersion::V0_0_2 => frame.try_into()
+        }
+    }
+
+    pub fn decode_inlet( &self, frame: PrimitiveFrame ) -> Result<inlet::Frame,Error> {
+        match self {
+            WireVersion::V0_0_1 => Ok(v0_0_1::generic::portal::inlet::Frame::try_from(frame)?.into()),
+            WireVersion::V0_0_2 => inlet::Frame::try_from(frame)
+        }
+    }
+
+    pub fn encode_outlet( &self, frame: outlet::Frame ) -> Result<PrimitiveFrame,Error> {
+        match self {
+            WireVersion::V0_0_1 => v0_0_1::generic::portal::outlet::Frame::try_from(frame)?.try_into(),
+            WireVersion::V0_0_2 => frame.try_into()
+        }
+    }
+
+    pub fn decode_outlet( &self, frame: PrimitiveFrame ) -> Result<outlet::Frame,Error> {
+        match self {
+            WireVersion::V0_0_1 => Ok(v0_0_1::generic::portal::outlet::Frame::try_from(frame)?.into()),
+            WireVersion::V0_0_2 => outlet::Frame::try_from(frame)
+        }
+    }
+}
+
+impl Default for WireVersion {
+    fn default() -> Self {
+        Self::latest()
+    }
+}
+
+impl fmt::Display for WireVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireVersion::V0_0_1 => f.write_str("0.0.1"),
+            WireVersion::V0_0_2 => f.write_str("0.0.2")
+        }
+    }
+}
+
+impl FromStr for WireVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0.0.1" => Ok(WireVersion::V0_0_1),
+            "0.0.2" => Ok(WireVersion::V0_0_2),
+            _ => Err(anyhow!("unknown wire version '{}'", s))
+        }
+    }
+}
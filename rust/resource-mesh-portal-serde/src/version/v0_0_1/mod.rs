@@ -1,14 +1,8 @@
 mod parse;
 
 use std::collections::HashMap;
-use std::convert::From;
-use std::convert::TryInto;
 use std::sync::Arc;
 
-use anyhow::Error;
-use serde::{Deserialize, Serialize};
-use std::hash::Hash;
-use std::fmt::Debug;
 use crate::version::v0_0_1::bin::Bin;
 
 
@@ -61,8 +55,8 @@ pub mod id {
         type Err = Error;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let (leftover,segments) = parse_address(s)?;
-            if leftover.len() != 0 {
+            let (leftover,segments) = parse_address(s).map_err(|e| anyhow!(e.to_string()))?;
+            if !leftover.is_empty() {
                 Err(anyhow!(format!("could not parse entire address: '{}' leftover '{}'", s, leftover )))
             } else {
                 Ok(Self{
@@ -72,16 +66,15 @@ pub mod id {
         }
     }
 
-    impl ToString for Address {
-        fn to_string(&self) -> String {
-            let mut rtn = String::new();
+    impl std::fmt::Display for Address {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             for (i, segment) in self.segments.iter().enumerate() {
-                rtn.push_str( segment.as_str() );
+                f.write_str( segment.as_str() )?;
                 if i != self.segments.len() {
-                    rtn.push_str(":");
+                    f.write_str(":")?;
                 }
             }
-            rtn.to_string()
+            Ok(())
         }
     }
 
@@ -94,14 +87,15 @@ pub mod id {
         pub version: Version,
     }
 
-    impl ToString for Specific {
-        fn to_string(&self) -> String {
-            format!(
+    impl std::fmt::Display for Specific {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
                 "{}:{}:{}:{}",
-                self.vendor.to_string(),
+                self.vendor,
                 self.product,
                 self.variant,
-                self.version.to_string()
+                self.version
             )
         }
     }
@@ -110,8 +104,8 @@ pub mod id {
         type Err = Error;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let (leftover, specific) = parse_specific(s)?;
-            if leftover.len() != 0 {
+            let (leftover, specific) = parse_specific(s).map_err(|e| anyhow!(e.to_string()))?;
+            if !leftover.is_empty() {
                 let message = format!(
                     "could not process '{}' portion of Specific '{}'",
                     leftover, s
@@ -143,19 +137,20 @@ pub mod id {
     }
 
 
-    impl ToString for Version {
-        fn to_string(&self) -> String {
+    impl std::fmt::Display for Version {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             match &self.release {
                 None => {
-                    format!("{}.{}.{}", self.major, self.minor, self.patch)
+                    write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
                 }
                 Some(release) => {
-                    format!(
+                    write!(
+                        f,
                         "{}.{}.{}-{}",
                         self.major,
                         self.minor,
                         self.patch,
-                        release.to_string()
+                        release
                     )
                 }
             }
@@ -167,9 +162,9 @@ pub mod id {
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
             let string = s.to_string();
-            let s: &'static str = string.as_str();
-            let (remaining, version) = parse_version(s)?;
-            if remaining.len() > 0 {
+            let s: &str = string.as_str();
+            let (remaining, version) = parse_version(s).map_err(|e| anyhow!(e.to_string()))?;
+            if !remaining.is_empty() {
                 Err(anyhow!(format!(
                     "could not parse '{}' portion for version string '{}",
                     remaining, s
@@ -217,13 +212,13 @@ pub mod log {
         Fatal(String)
     }
 
-    impl ToString for Log {
-        fn to_string(&self) -> String {
+    impl std::fmt::Display for Log {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             match self {
-                Log::Warn(message) => { format!("WARN: {}", message) }
-                Log::Info(message) => { format!("INFO: {}", message) }
-                Log::Error(message) => { format!("ERROR: {}", message) }
-                Log::Fatal(message) => { format!("FATAL: {}", message) }
+                Log::Warn(message) => { write!(f, "WARN: {}", message) }
+                Log::Info(message) => { write!(f, "INFO: {}", message) }
+                Log::Error(message) => { write!(f, "ERROR: {}", message) }
+                Log::Fatal(message) => { write!(f, "FATAL: {}", message) }
             }
         }
     }
@@ -356,13 +351,21 @@ pub mod http {
     }
 
     impl HttpResponse {
-        pub fn server_side_error() -> Self {
+        pub fn new( code: usize ) -> Self {
             Self {
                 headers: Default::default(),
-                code: 500,
+                code,
                 body: None
             }
         }
+
+        pub fn server_side_error() -> Self {
+            Self::new(500)
+        }
+
+        pub fn not_found() -> Self {
+            Self::new(404)
+        }
     }
 }
 
@@ -374,7 +377,7 @@ pub mod config {
 
     use crate::version::v0_0_1::generic;
     use std::collections::HashMap;
-    use crate::version::latest::ArtifactRef;
+    use crate::version::v0_0_1::ArtifactRef;
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub enum PortalKind {
@@ -382,11 +385,11 @@ pub mod config {
         Portal
     }
 
-    impl ToString for PortalKind {
-        fn to_string(&self) -> String {
+    impl std::fmt::Display for PortalKind {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             match self {
-                PortalKind::Mechtron => "Mechtron".to_string(),
-                PortalKind::Portal => "Portal".to_string()
+                PortalKind::Mechtron => f.write_str("Mechtron"),
+                PortalKind::Portal => f.write_str("Portal")
             }
         }
     }
@@ -437,17 +440,11 @@ pub mod config {
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[derive(Default)]
     pub struct BindConfig {
         pub ports: HashMap<String, PortConfig>,
     }
 
-    impl Default for BindConfig {
-        fn default() -> Self {
-            Self {
-                ports: Default::default()
-            }
-        }
-    }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct PortConfig {
@@ -482,8 +479,6 @@ pub mod operation {
 
     use crate::version::v0_0_1::id::{Key, Address, Kind};
 
-    use crate::version::v0_0_1::{State, http};
-    use crate::version::v0_0_1::generic;
     use crate::version::v0_0_1::generic::operation;
 
     pub type Operation = operation::Operation<Key,Address,Kind>;
@@ -520,11 +515,7 @@ pub mod resource {
 pub mod portal {
     pub mod inlet {
         use crate::version::v0_0_1::id::{Key, Address, Kind};
-        use std::convert::TryFrom;
-        use std::convert::TryInto;
 
-        use anyhow::Error;
-        use serde::{Deserialize, Serialize};
         use crate::version::v0_0_1::generic;
 
 
@@ -535,11 +526,7 @@ pub mod portal {
 
     pub mod outlet {
         use crate::version::v0_0_1::id::{Key, Address, Kind};
-        use std::convert::TryFrom;
-        use std::convert::TryInto;
 
-        use anyhow::Error;
-        use serde::{Deserialize, Serialize};
         use crate::version::v0_0_1::generic;
 
         pub type Request=generic::portal::outlet::Request<Key,Address,Kind>;
@@ -550,22 +537,13 @@ pub mod portal {
 }
 
 pub mod generic {
-    use std::collections::HashMap;
-    use std::convert::From;
-    use std::convert::TryInto;
-    use std::sync::Arc;
 
-    use anyhow::Error;
-    use serde::{Deserialize, Serialize};
-    use std::hash::Hash;
-    use std::fmt::Debug;
-    use std::str::FromStr;
 
     //pub trait Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync {}
 
     pub mod id {
         use serde::{Serialize,Deserialize};
-        use crate::version::v0_0_1::generic;
+
         use std::fmt::Debug;
         use std::hash::Hash;
         use std::str::FromStr;
@@ -621,9 +599,9 @@ pub mod generic {
 
     pub mod operation {
         use serde::{Serialize,Deserialize};
-        use crate::version::v0_0_1::generic;
-        use crate::version::latest::{State, http};
-        use serde::__private::fmt::Debug;
+
+        use crate::version::v0_0_1::{State, http};
+        use std::fmt::Debug;
         use std::hash::Hash;
         use std::str::FromStr;
         use crate::version::v0_0_1::generic::resource::{Create, Selector};
@@ -661,7 +639,7 @@ pub mod generic {
     pub mod resource {
         use serde::{Deserialize, Serialize};
         use crate::version::v0_0_1::State;
-        use crate::version::v0_0_1::generic;
+
         use std::fmt::Debug;
         use std::hash::Hash;
         use std::str::FromStr;
@@ -717,7 +695,13 @@ pub mod generic {
 
         #[derive(Debug, Clone, Serialize, Deserialize)]
         pub struct Selector {
-            meta: MetaSelector
+            pub(crate) meta: MetaSelector
+        }
+
+        impl Default for Selector {
+            fn default() -> Self {
+                Self::new()
+            }
         }
 
         impl Selector {
@@ -749,7 +733,7 @@ pub mod generic {
             use crate::version::v0_0_1::resource::Status;
             use crate::version::v0_0_1::bin::BinParcel;
             use crate::version::v0_0_1::frame::{CloseReason, PrimitiveFrame};
-            use crate::version::v0_0_1::id::{Address, Kind, Key};
+
             use std::fmt::Debug;
             use crate::version::v0_0_1::generic::id::Identifier;
             use crate::version::v0_0_1::generic::operation::Operation;
@@ -804,7 +788,7 @@ pub mod generic {
                 }
             }
 
-            impl TryFrom<PrimitiveFrame> for Frame<Key,Address,Kind>{
+            impl TryFrom<PrimitiveFrame> for Frame<String,String,String>{
                 type Error = Error;
 
                 fn try_from(value: PrimitiveFrame) -> Result<Self, Self::Error> {
@@ -833,7 +817,7 @@ pub mod generic {
             use anyhow::Error;
             use serde::{Deserialize, Serialize};
             use crate::version::v0_0_1::messaging::{ExchangeKind, ExchangeId};
-            use crate::version::v0_0_1::generic;
+
             use crate::version::v0_0_1::command::CommandEvent;
             use crate::version::v0_0_1::bin::BinParcel;
             use crate::version::v0_0_1::frame::{CloseReason, PrimitiveFrame};
@@ -844,7 +828,6 @@ pub mod generic {
             use crate::version::v0_0_1::generic::operation::ExtOperation;
             use crate::version::v0_0_1::generic::delivery::ResponseEntity;
             use crate::version::v0_0_1::generic::config::Info;
-            use crate::version::v0_0_1::id::{Key, Address, Kind};
 
             #[derive(Debug, Clone, Serialize, Deserialize)]
             pub struct Request<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
@@ -882,7 +865,7 @@ pub mod generic {
             }
 
 
-            impl TryFrom<PrimitiveFrame> for Frame<Key,Address,Kind> {
+            impl TryFrom<PrimitiveFrame> for Frame<String,String,String> {
                 type Error = Error;
 
                 fn try_from(value: PrimitiveFrame) -> Result<Self, Self::Error> {
@@ -898,7 +881,7 @@ pub mod generic {
 
         use crate::version::v0_0_1::{State, http};
         use crate::version::v0_0_1::delivery::Payload;
-        use crate::version::v0_0_1::generic;
+
         use std::fmt::Debug;
         use std::hash::Hash;
         use std::str::FromStr;
@@ -928,12 +911,11 @@ pub mod generic {
 
     }
 
-    mod fail {
+    pub mod fail {
         use serde::{Deserialize, Serialize};
-        use crate::version::v0_0_1::id::Specific;
 
 
-        mod mesh {
+        pub mod mesh {
             use serde::{Deserialize, Serialize};
             #[derive(Debug, Clone, Serialize, Deserialize)]
             pub enum Fail{
@@ -942,7 +924,7 @@ pub mod generic {
             }
         }
 
-        mod mechtron {
+        pub mod mechtron {
             use serde::{Deserialize, Serialize};
             use crate::version::v0_0_1::generic::fail::{resource, port, http};
 
@@ -955,10 +937,10 @@ pub mod generic {
             }
         }
 
-        mod resource {
+        pub mod resource {
             use serde::{Deserialize, Serialize};
-            use crate::version::v0_0_1::id::Address;
-            use crate::version::v0_0_1::generic::fail::{NotFound, Bad, Standard};
+
+            use crate::version::v0_0_1::generic::fail::Standard;
 
             #[derive(Debug, Clone, Serialize, Deserialize)]
             pub enum Fail {
@@ -981,7 +963,7 @@ pub mod generic {
 
 
 
-        mod port {
+        pub mod port {
             use serde::{Deserialize, Serialize};
             use crate::version::v0_0_1::generic::fail::Standard;
 
@@ -992,9 +974,8 @@ pub mod generic {
             }
         }
 
-        mod http {
+        pub mod http {
             use serde::{Deserialize, Serialize};
-            use crate::version::v0_0_1::generic::fail::Standard;
 
             #[derive(Debug, Clone, Serialize, Deserialize)]
             pub struct Error{
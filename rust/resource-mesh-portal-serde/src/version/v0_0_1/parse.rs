@@ -1,19 +1,14 @@
-use std::convert::TryInto;
-use std::str::FromStr;
 
 use nom::{AsChar, InputTakeAtPosition, IResult};
-use nom::bytes::complete::{tag, take};
-use nom::character::complete::{alpha0, alpha1, anychar, digit0, digit1, one_of, alphanumeric1, multispace0};
-use nom::combinator::{not, opt, all_consuming};
+use nom::bytes::complete::tag;
+use nom::character::complete::digit1;
+use nom::combinator::{not, opt};
 use nom::error::{context, ErrorKind, VerboseError};
-use nom::multi::{many1, many_m_n, separated_list0, separated_list1};
-use nom::sequence::{delimited, preceded, terminated, tuple};
-use serde::{Deserialize, Serialize};
+use nom::multi::separated_list1;
+use nom::sequence::{preceded, terminated, tuple};
 
-use nom::branch::alt;
 
-use anyhow::Error;
-use crate::version::v0_0_1::id::{Specific, ResourceType,Version};
+use crate::version::v0_0_1::id::{Specific,Version};
 
 pub type Res<T, U> = IResult<T, U, VerboseError<T>>;
 
@@ -25,10 +20,10 @@ fn any_resource_path_segment<T>(i: T) -> Res<T, T>
     i.split_at_position1_complete(
         |item| {
             let char_item = item.as_char();
-            !(char_item == '-')
-            && !(char_item == '.')
-            && !(char_item == '/')
-            && !(char_item == '_')
+            (char_item != '-')
+            && (char_item != '.')
+            && (char_item != '/')
+            && (char_item != '_')
             && !(char_item.is_alpha() || char_item.is_dec_digit())
         },
         ErrorKind::AlphaNumeric,
@@ -43,7 +38,7 @@ fn loweralphanumerichyphen1<T>(i: T) -> Res<T, T>
     i.split_at_position1_complete(
         |item| {
             let char_item = item.as_char();
-            !(char_item == '-')
+            (char_item != '-')
                 && !((char_item.is_alpha() && char_item.is_lowercase()) || char_item.is_dec_digit())
         },
         ErrorKind::AlphaNumeric,
@@ -58,56 +53,14 @@ fn domain<T>(i: T) -> Res<T, T>
     i.split_at_position1_complete(
         |item| {
             let char_item = item.as_char();
-            !(char_item == '-') &&
-            !(char_item == '.') &&
+            (char_item != '-') &&
+            (char_item != '.') &&
             !((char_item.is_alpha() && char_item.is_lowercase()) || char_item.is_dec_digit())
         },
         ErrorKind::AlphaNumeric,
     )
 }
 
-fn not_whitespace<T>(i: T) -> Res<T, T>
-    where
-        T: InputTakeAtPosition,
-        <T as InputTakeAtPosition>::Item: AsChar,
-{
-    i.split_at_position1_complete(
-        |item| {
-            let char_item = item.as_char();
-            (char_item == ' ')
-        },
-        ErrorKind::AlphaNumeric,
-    )
-}
-
-pub fn not_whitespace_or_semi<T>(i: T) -> Res<T, T>
-    where
-        T: InputTakeAtPosition,
-        <T as InputTakeAtPosition>::Item: AsChar,
-{
-    i.split_at_position1_complete(
-        |item| {
-            let char_item = item.as_char();
-            (char_item == ' ' || char_item == ';')
-        },
-        ErrorKind::AlphaNumeric,
-    )
-}
-
-fn anything_but_single_quote<T>(i: T) -> Res<T, T>
-    where
-        T: InputTakeAtPosition,
-        <T as InputTakeAtPosition>::Item: AsChar,
-{
-    i.split_at_position1_complete(
-        |item| {
-            let char_item = item.as_char();
-            char_item == '\''
-        },
-        ErrorKind::AlphaNumeric,
-    )
-}
-
 
 fn parse_version_major_minor_patch(input: &str) -> Res<&str, (usize, usize, usize)> {
     context(
@@ -136,18 +89,13 @@ pub fn parse_version(input: &str) -> Res<&str, Version> {
         tuple((parse_version_major_minor_patch, opt(preceded(tag("-"), parse_skewer)))),
     )(input)
         .map(|(next_input, ((major, minor, patch), release))| {
-            let release = match release
-            {
-                None => {Option::None}
-                Some(s) => {Option::Some(s.to_string())}
-            };
+            let release = release.map(|s| s.to_string());
             (next_input, Version::new(major, minor, patch, release))
         })
 }
 
 fn parse_skewer(input: &str) -> Res<&str, &str> {
     context("skewer-case", loweralphanumerichyphen1)(input)
-        .map(|(input, skewer)| (input, skewer))
 }
 
 pub fn parse_specific(input: &str) -> Res<&str, Specific> {
@@ -167,7 +115,7 @@ pub fn parse_specific(input: &str) -> Res<&str, Specific> {
                     vendor: vendor.to_string(),
                     product: product.to_string(),
                     variant: variant.to_string(),
-                    version: version,
+                    version,
                 },
             )
         })
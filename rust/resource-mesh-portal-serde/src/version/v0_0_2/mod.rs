@@ -0,0 +1,704 @@
+// 0.0.2 shares everything with 0.0.1 except the layouts defined here, which a 0.0.1 peer cannot read:
+// a Config carries a RateLimit, an HttpRequest its method, a Selector filters & pages, a Request may
+// carry an IdempotencyKey and a ResponseEntity may be a Fail.  each converts to & from its 0.0.1
+// counterpart, losing whatever 0.0.1 has no room for
+
+pub mod messaging {
+    // the same for every attempt of one request, so its recipient can tell a retry from a new request
+    pub type IdempotencyKey = String;
+}
+
+pub mod http {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::version::v0_0_1;
+    use crate::version::v0_0_1::bin::Bin;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct HttpRequest {
+        pub method: String,
+        pub headers: HashMap<String, String>,
+        pub path: String,
+        pub body: Option<Bin>
+    }
+
+    // 0.0.1 has no method, a GET is all it could have meant
+    impl From<v0_0_1::http::HttpRequest> for HttpRequest {
+        fn from(request: v0_0_1::http::HttpRequest) -> Self {
+            Self {
+                method: "GET".to_string(),
+                headers: request.headers,
+                path: request.path,
+                body: request.body
+            }
+        }
+    }
+
+    impl From<HttpRequest> for v0_0_1::http::HttpRequest {
+        fn from(request: HttpRequest) -> Self {
+            Self {
+                headers: request.headers,
+                path: request.path,
+                body: request.body
+            }
+        }
+    }
+}
+
+pub mod config {
+    use serde::{Serialize,Deserialize};
+
+    use crate::version::v0_0_1;
+    use crate::version::v0_0_1::config::BindConfig;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Config {
+        pub max_bin_size: u32,
+        pub bin_parcel_size: u32,
+        pub init_timeout: u64,
+        pub frame_timeout: u64,
+        pub response_timeout: u64,
+        pub bind: BindConfig,
+        pub rate_limit: RateLimit
+    }
+
+    impl Config {
+        pub fn with_bind_config(bind: BindConfig) -> Self {
+            Self {
+                bind,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            v0_0_1::config::Config::default().into()
+        }
+    }
+
+    // a 0.0.1 Config leaves the rate unlimited
+    impl From<v0_0_1::config::Config> for Config {
+        fn from(config: v0_0_1::config::Config) -> Self {
+            Self {
+                max_bin_size: config.max_bin_size,
+                bin_parcel_size: config.bin_parcel_size,
+                init_timeout: config.init_timeout,
+                frame_timeout: config.frame_timeout,
+                response_timeout: config.response_timeout,
+                bind: config.bind,
+                rate_limit: Default::default()
+            }
+        }
+    }
+
+    impl From<Config> for v0_0_1::config::Config {
+        fn from(config: Config) -> Self {
+            Self {
+                max_bin_size: config.max_bin_size,
+                bin_parcel_size: config.bin_parcel_size,
+                init_timeout: config.init_timeout,
+                frame_timeout: config.frame_timeout,
+                response_timeout: config.response_timeout,
+                bind: config.bind
+            }
+        }
+    }
+
+    // zero means unlimited
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct RateLimit {
+        pub requests_per_sec: u32,
+        pub bytes_per_sec: u64
+    }
+
+    impl RateLimit {
+        pub fn unlimited() -> Self {
+            Self {
+                requests_per_sec: 0,
+                bytes_per_sec: 0
+            }
+        }
+
+        pub fn is_unlimited(&self) -> bool {
+            self.requests_per_sec == 0 && self.bytes_per_sec == 0
+        }
+    }
+
+    impl Default for RateLimit {
+        fn default() -> Self {
+            Self::unlimited()
+        }
+    }
+}
+
+pub mod generic {
+
+    pub mod config {
+        use serde::{Serialize,Deserialize};
+        use std::fmt::Debug;
+        use std::hash::Hash;
+        use std::str::FromStr;
+        use crate::version::v0_0_1;
+        use crate::version::v0_0_1::ArtifactRef;
+        use crate::version::v0_0_1::config::PortalKind;
+        use crate::version::v0_0_1::generic::id::{Identifier, Identifiers};
+        use crate::version::v0_0_1::generic::resource::Archetype;
+        use crate::version::v0_0_2::config::Config;
+
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub struct Info<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
+            pub key: KEY,
+            pub address: ADDRESS,
+            pub owner: String,
+            pub parent: Identifier<KEY,ADDRESS>,
+            pub archetype: Archetype<KIND>,
+            pub config: Config,
+            pub ext_config: Option<ArtifactRef>,
+            pub kind: PortalKind
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> Info<KEY, ADDRESS, KIND> {
+            pub fn identity(&self) -> Identifiers<KEY,ADDRESS> {
+                Identifiers {
+                    key: self.key.clone(),
+                    address: self.address.clone()
+                }
+            }
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<v0_0_1::generic::config::Info<KEY,ADDRESS,KIND>> for Info<KEY,ADDRESS,KIND> {
+            fn from(info: v0_0_1::generic::config::Info<KEY,ADDRESS,KIND>) -> Self {
+                Self {
+                    key: info.key,
+                    address: info.address,
+                    owner: info.owner,
+                    parent: info.parent,
+                    archetype: info.archetype,
+                    config: info.config.into(),
+                    ext_config: info.ext_config,
+                    kind: info.kind
+                }
+            }
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<Info<KEY,ADDRESS,KIND>> for v0_0_1::generic::config::Info<KEY,ADDRESS,KIND> {
+            fn from(info: Info<KEY,ADDRESS,KIND>) -> Self {
+                Self {
+                    key: info.key,
+                    address: info.address,
+                    owner: info.owner,
+                    parent: info.parent,
+                    archetype: info.archetype,
+                    config: info.config.into(),
+                    ext_config: info.ext_config,
+                    kind: info.kind
+                }
+            }
+        }
+    }
+
+    pub mod resource {
+        use serde::{Deserialize, Serialize};
+        use crate::version::v0_0_1;
+        use crate::version::v0_0_1::config::PortalKind;
+        use crate::version::v0_0_1::generic::resource::MetaSelector;
+
+        // every criteria left as None matches anything.  results are ordered by address so
+        // offset & limit can be used to page through a large selection
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub struct Selector {
+            meta: MetaSelector,
+            pub kind: Option<PortalKind>,
+            pub owner: Option<String>,
+            pub archetype_kind: Option<String>,
+            pub specific: Option<String>,
+            pub address_prefix: Option<String>,
+            pub offset: u64,
+            pub limit: Option<u64>
+        }
+
+        impl Default for Selector {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Selector {
+            pub fn new() -> Self {
+                Self {
+                    meta: MetaSelector::None,
+                    kind: None,
+                    owner: None,
+                    archetype_kind: None,
+                    specific: None,
+                    address_prefix: None,
+                    offset: 0,
+                    limit: None
+                }
+            }
+
+            pub fn with_kind( mut self, kind: PortalKind ) -> Self {
+                self.kind = Option::Some(kind);
+                self
+            }
+
+            pub fn with_owner( mut self, owner: String ) -> Self {
+                self.owner = Option::Some(owner);
+                self
+            }
+
+            pub fn with_archetype_kind( mut self, kind: String ) -> Self {
+                self.archetype_kind = Option::Some(kind);
+                self
+            }
+
+            pub fn with_specific( mut self, specific: String ) -> Self {
+                self.specific = Option::Some(specific);
+                self
+            }
+
+            pub fn with_address_prefix( mut self, prefix: String ) -> Self {
+                self.address_prefix = Option::Some(prefix);
+                self
+            }
+
+            pub fn with_page( mut self, offset: u64, limit: u64 ) -> Self {
+                self.offset = offset;
+                self.limit = Option::Some(limit);
+                self
+            }
+        }
+
+        impl From<v0_0_1::generic::resource::Selector> for Selector {
+            fn from(selector: v0_0_1::generic::resource::Selector) -> Self {
+                Self {
+                    meta: selector.meta,
+                    ..Self::new()
+                }
+            }
+        }
+
+        // 0.0.1 can neither filter nor page, it selects everything
+        impl From<Selector> for v0_0_1::generic::resource::Selector {
+            fn from(selector: Selector) -> Self {
+                Self {
+                    meta: selector.meta
+                }
+            }
+        }
+    }
+
+    pub mod operation {
+        use serde::{Serialize,Deserialize};
+        use std::fmt::Debug;
+        use std::hash::Hash;
+        use std::str::FromStr;
+        use crate::version::v0_0_1;
+        use crate::version::v0_0_1::State;
+        use crate::version::v0_0_1::generic::operation::PortOperation;
+        use crate::version::v0_0_1::generic::resource::Create;
+        use crate::version::v0_0_2::http;
+        use crate::version::v0_0_2::generic::resource::Selector;
+
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub enum Operation<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync>  {
+            Resource(ResourceOperation<KEY,ADDRESS,KIND>),
+            Ext(ExtOperation<KEY,ADDRESS,KIND>)
+        }
+
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub enum ResourceOperation<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync>  {
+            Create(Create<KEY,ADDRESS,KIND>),
+            Select(Selector),
+            Get,
+            Set(State),
+            Delete,
+        }
+
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub enum ExtOperation<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
+            Http(http::HttpRequest),
+            Port(PortOperation<KEY,ADDRESS,KIND>)
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<v0_0_1::generic::operation::Operation<KEY,ADDRESS,KIND>> for Operation<KEY,ADDRESS,KIND> {
+            fn from(operation: v0_0_1::generic::operation::Operation<KEY,ADDRESS,KIND>) -> Self {
+                match operation {
+                    v0_0_1::generic::operation::Operation::Resource(operation) => Operation::Resource(operation.into()),
+                    v0_0_1::generic::operation::Operation::Ext(operation) => Operation::Ext(operation.into())
+                }
+            }
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<Operation<KEY,ADDRESS,KIND>> for v0_0_1::generic::operation::Operation<KEY,ADDRESS,KIND> {
+            fn from(operation: Operation<KEY,ADDRESS,KIND>) -> Self {
+                match operation {
+                    Operation::Resource(operation) => v0_0_1::generic::operation::Operation::Resource(operation.into()),
+                    Operation::Ext(operation) => v0_0_1::generic::operation::Operation::Ext(operation.into())
+                }
+            }
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<v0_0_1::generic::operation::ResourceOperation<KEY,ADDRESS,KIND>> for ResourceOperation<KEY,ADDRESS,KIND> {
+            fn from(operation: v0_0_1::generic::operation::ResourceOperation<KEY,ADDRESS,KIND>) -> Self {
+                match operation {
+                    v0_0_1::generic::operation::ResourceOperation::Create(create) => ResourceOperation::Create(create),
+                    v0_0_1::generic::operation::ResourceOperation::Select(selector) => ResourceOperation::Select(selector.into()),
+                    v0_0_1::generic::operation::ResourceOperation::Get => ResourceOperation::Get,
+                    v0_0_1::generic::operation::ResourceOperation::Set(state) => ResourceOperation::Set(state),
+                    v0_0_1::generic::operation::ResourceOperation::Delete => ResourceOperation::Delete
+                }
+            }
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<ResourceOperation<KEY,ADDRESS,KIND>> for v0_0_1::generic::operation::ResourceOperation<KEY,ADDRESS,KIND> {
+            fn from(operation: ResourceOperation<KEY,ADDRESS,KIND>) -> Self {
+                match operation {
+                    ResourceOperation::Create(create) => v0_0_1::generic::operation::ResourceOperation::Create(create),
+                    ResourceOperation::Select(selector) => v0_0_1::generic::operation::ResourceOperation::Select(selector.into()),
+                    ResourceOperation::Get => v0_0_1::generic::operation::ResourceOperation::Get,
+                    ResourceOperation::Set(state) => v0_0_1::generic::operation::ResourceOperation::Set(state),
+                    ResourceOperation::Delete => v0_0_1::generic::operation::ResourceOperation::Delete
+                }
+            }
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<v0_0_1::generic::operation::ExtOperation<KEY,ADDRESS,KIND>> for ExtOperation<KEY,ADDRESS,KIND> {
+            fn from(operation: v0_0_1::generic::operation::ExtOperation<KEY,ADDRESS,KIND>) -> Self {
+                match operation {
+                    v0_0_1::generic::operation::ExtOperation::Http(request) => ExtOperation::Http(request.into()),
+                    v0_0_1::generic::operation::ExtOperation::Port(port) => ExtOperation::Port(port)
+                }
+            }
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<ExtOperation<KEY,ADDRESS,KIND>> for v0_0_1::generic::operation::ExtOperation<KEY,ADDRESS,KIND> {
+            fn from(operation: ExtOperation<KEY,ADDRESS,KIND>) -> Self {
+                match operation {
+                    ExtOperation::Http(request) => v0_0_1::generic::operation::ExtOperation::Http(request.into()),
+                    ExtOperation::Port(port) => v0_0_1::generic::operation::ExtOperation::Port(port)
+                }
+            }
+        }
+    }
+
+    pub mod delivery {
+        use serde::{Deserialize, Serialize};
+        use std::fmt::Debug;
+        use std::hash::Hash;
+        use std::str::FromStr;
+        use crate::version::v0_0_1;
+        use crate::version::v0_0_1::generic::delivery::Entity;
+        use crate::version::v0_0_2::generic::fail;
+
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub enum ResponseEntity<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
+            Ok(Entity<KEY,ADDRESS,KIND>),
+            Error(String),
+            Fail(fail::Fail)
+        }
+
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<v0_0_1::generic::delivery::ResponseEntity<KEY,ADDRESS,KIND>> for ResponseEntity<KEY,ADDRESS,KIND> {
+            fn from(entity: v0_0_1::generic::delivery::ResponseEntity<KEY,ADDRESS,KIND>) -> Self {
+                match entity {
+                    v0_0_1::generic::delivery::ResponseEntity::Ok(entity) => ResponseEntity::Ok(entity),
+                    v0_0_1::generic::delivery::ResponseEntity::Error(message) => ResponseEntity::Error(message)
+                }
+            }
+        }
+
+        // 0.0.1 has no Fail, it gets to read what the Fail was instead
+        impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<ResponseEntity<KEY,ADDRESS,KIND>> for v0_0_1::generic::delivery::ResponseEntity<KEY,ADDRESS,KIND> {
+            fn from(entity: ResponseEntity<KEY,ADDRESS,KIND>) -> Self {
+                match entity {
+                    ResponseEntity::Ok(entity) => v0_0_1::generic::delivery::ResponseEntity::Ok(entity),
+                    ResponseEntity::Error(message) => v0_0_1::generic::delivery::ResponseEntity::Error(message),
+                    ResponseEntity::Fail(fail) => v0_0_1::generic::delivery::ResponseEntity::Error(format!("{:?}", fail))
+                }
+            }
+        }
+    }
+
+    pub mod fail {
+        use serde::{Deserialize, Serialize};
+        use crate::version::v0_0_1::generic::fail::{mechtron, resource};
+
+        pub mod mesh {
+            use serde::{Deserialize, Serialize};
+
+            #[derive(Debug, Clone, Serialize, Deserialize)]
+            pub enum Fail{
+                Error(String),
+                QueueOverflow,
+                Forbidden(Forbidden),
+                Throttled(Throttled)
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize)]
+            pub struct Throttled {
+                pub scope: String,
+                pub limit: String,
+                pub retry_after_millis: u64
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize)]
+            pub struct Forbidden {
+                pub from: String,
+                pub to: String,
+                pub operation: String,
+                pub rule: Option<String>
+            }
+        }
+
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub enum Fail {
+            Mesh(mesh::Fail),
+            Resource(resource::Fail),
+            Mechtron(mechtron::Fail),
+        }
+    }
+
+    pub mod portal {
+        pub mod inlet {
+            use std::convert::TryFrom;
+            use std::convert::TryInto;
+
+            use anyhow::Error;
+            use serde::{Deserialize, Serialize};
+            use std::fmt::Debug;
+            use std::hash::Hash;
+            use std::str::FromStr;
+            use crate::version::v0_0_1;
+            use crate::version::v0_0_1::messaging::{ExchangeId, ExchangeKind};
+            use crate::version::v0_0_1::log::Log;
+            use crate::version::v0_0_1::command::Command;
+            use crate::version::v0_0_1::resource::Status;
+            use crate::version::v0_0_1::bin::BinParcel;
+            use crate::version::v0_0_1::frame::{CloseReason, PrimitiveFrame};
+            use crate::version::v0_0_1::generic::id::Identifier;
+            use crate::version::v0_0_2::messaging::IdempotencyKey;
+            use crate::version::v0_0_2::generic::operation::Operation;
+            use crate::version::v0_0_2::generic::delivery::ResponseEntity;
+
+            #[derive(Debug, Clone, Serialize, Deserialize)]
+            pub struct Request<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync>  {
+                pub to: Vec<Identifier<KEY,ADDRESS>>,
+                pub operation: Operation<KEY,ADDRESS,KIND>,
+                pub kind: ExchangeKind,
+                pub idempotency_key: Option<IdempotencyKey>
+            }
+
+            impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> Request<KEY,ADDRESS,KIND> {
+                pub fn new(operation: Operation<KEY,ADDRESS,KIND>) -> Self {
+                    Self {
+                        to: vec![],
+                        operation,
+                        kind: ExchangeKind::None,
+                        idempotency_key: Option::None
+                    }
+                }
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize)]
+            pub struct Response<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
+                pub to: Identifier<KEY,ADDRESS>,
+                pub exchange_id: ExchangeId,
+                pub signal: ResponseEntity<KEY,ADDRESS,KIND>,
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize, strum_macros::Display)]
+            pub enum Frame<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
+                Log(Log),
+                Command(Command),
+                Request(Request<KEY,ADDRESS,KIND>),
+                Response(Response<KEY,ADDRESS,KIND>),
+                Status(Status),
+                BinParcel(BinParcel),
+                Close(CloseReason)
+            }
+
+            impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> TryInto<PrimitiveFrame> for Frame<KEY,ADDRESS,KIND> {
+                type Error = Error;
+
+                fn try_into(self) -> Result<PrimitiveFrame, Self::Error> {
+                    let data = bincode::serialize(&self)?;
+                    Ok(PrimitiveFrame {
+                        data
+                    })
+                }
+            }
+
+            impl TryFrom<PrimitiveFrame> for Frame<String,String,String>{
+                type Error = Error;
+
+                fn try_from(value: PrimitiveFrame) -> Result<Self, Self::Error> {
+                    let frame = bincode::deserialize(value.data.as_slice())?;
+                    Ok(frame)
+                }
+            }
+
+            impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<v0_0_1::generic::portal::inlet::Frame<KEY,ADDRESS,KIND>> for Frame<KEY,ADDRESS,KIND> {
+                fn from(frame: v0_0_1::generic::portal::inlet::Frame<KEY,ADDRESS,KIND>) -> Self {
+                    match frame {
+                        v0_0_1::generic::portal::inlet::Frame::Log(log) => Frame::Log(log),
+                        v0_0_1::generic::portal::inlet::Frame::Command(command) => Frame::Command(command),
+                        v0_0_1::generic::portal::inlet::Frame::Request(request) => Frame::Request(Request {
+                            to: request.to,
+                            operation: request.operation.into(),
+                            kind: request.kind,
+                            idempotency_key: Option::None
+                        }),
+                        v0_0_1::generic::portal::inlet::Frame::Response(response) => Frame::Response(Response {
+                            to: response.to,
+                            exchange_id: response.exchange_id,
+                            signal: response.signal.into()
+                        }),
+                        v0_0_1::generic::portal::inlet::Frame::Status(status) => Frame::Status(status),
+                        v0_0_1::generic::portal::inlet::Frame::BinParcel(parcel) => Frame::BinParcel(parcel),
+                        v0_0_1::generic::portal::inlet::Frame::Close(reason) => Frame::Close(reason)
+                    }
+                }
+            }
+
+            impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<Frame<KEY,ADDRESS,KIND>> for v0_0_1::generic::portal::inlet::Frame<KEY,ADDRESS,KIND> {
+                fn from(frame: Frame<KEY,ADDRESS,KIND>) -> Self {
+                    match frame {
+                        Frame::Log(log) => v0_0_1::generic::portal::inlet::Frame::Log(log),
+                        Frame::Command(command) => v0_0_1::generic::portal::inlet::Frame::Command(command),
+                        Frame::Request(request) => v0_0_1::generic::portal::inlet::Frame::Request(v0_0_1::generic::portal::inlet::Request {
+                            to: request.to,
+                            operation: request.operation.into(),
+                            kind: request.kind
+                        }),
+                        Frame::Response(response) => v0_0_1::generic::portal::inlet::Frame::Response(v0_0_1::generic::portal::inlet::Response {
+                            to: response.to,
+                            exchange_id: response.exchange_id,
+                            signal: response.signal.into()
+                        }),
+                        Frame::Status(status) => v0_0_1::generic::portal::inlet::Frame::Status(status),
+                        Frame::BinParcel(parcel) => v0_0_1::generic::portal::inlet::Frame::BinParcel(parcel),
+                        Frame::Close(reason) => v0_0_1::generic::portal::inlet::Frame::Close(reason)
+                    }
+                }
+            }
+        }
+
+        pub mod outlet {
+            use std::convert::TryFrom;
+            use std::convert::TryInto;
+
+            use anyhow::Error;
+            use serde::{Deserialize, Serialize};
+            use std::fmt::Debug;
+            use std::hash::Hash;
+            use std::str::FromStr;
+            use crate::version::v0_0_1;
+            use crate::version::v0_0_1::messaging::{ExchangeId, ExchangeKind};
+            use crate::version::v0_0_1::command::CommandEvent;
+            use crate::version::v0_0_1::bin::BinParcel;
+            use crate::version::v0_0_1::frame::{CloseReason, PrimitiveFrame};
+            use crate::version::v0_0_1::generic::id::Identifier;
+            use crate::version::v0_0_2::messaging::IdempotencyKey;
+            use crate::version::v0_0_2::generic::operation::ExtOperation;
+            use crate::version::v0_0_2::generic::delivery::ResponseEntity;
+            use crate::version::v0_0_2::generic::config::Info;
+
+            #[derive(Debug, Clone, Serialize, Deserialize)]
+            pub struct Request<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
+                pub from: Identifier<KEY,ADDRESS>,
+                pub operation: ExtOperation<KEY,ADDRESS,KIND>,
+                pub kind: ExchangeKind,
+                pub idempotency_key: Option<IdempotencyKey>
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize)]
+            pub struct Response<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
+                pub from: Identifier<KEY,ADDRESS>,
+                pub exchange_id: ExchangeId,
+                pub signal: ResponseEntity<KEY,ADDRESS,KIND>,
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize, strum_macros::Display)]
+            pub enum Frame<KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> {
+                Init(Info<KEY,ADDRESS,KIND>),
+                CommandEvent(CommandEvent),
+                Request(Request<KEY,ADDRESS,KIND>),
+                Response(Response<KEY,ADDRESS,KIND>),
+                BinParcel(BinParcel),
+                Close(CloseReason),
+                // asks the portal to save its State before it is closed, answered by an inlet
+                // Response with the same exchange id.  there is no such frame in 0.0.1
+                Checkpoint(ExchangeId)
+            }
+
+            impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> TryInto<PrimitiveFrame> for Frame<KEY,ADDRESS,KIND> {
+                type Error = Error;
+
+                fn try_into(self) -> Result<PrimitiveFrame, Self::Error> {
+                    let data = bincode::serialize(&self)?;
+                    Ok(PrimitiveFrame {
+                        data
+                    })
+                }
+            }
+
+            impl TryFrom<PrimitiveFrame> for Frame<String,String,String> {
+                type Error = Error;
+
+                fn try_from(value: PrimitiveFrame) -> Result<Self, Self::Error> {
+                    let frame = bincode::deserialize(value.data.as_slice())?;
+                    Ok(frame)
+                }
+            }
+
+            impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> From<v0_0_1::generic::portal::outlet::Frame<KEY,ADDRESS,KIND>> for Frame<KEY,ADDRESS,KIND> {
+                fn from(frame: v0_0_1::generic::portal::outlet::Frame<KEY,ADDRESS,KIND>) -> Self {
+                    match frame {
+                        v0_0_1::generic::portal::outlet::Frame::Init(info) => Frame::Init(info.into()),
+                        v0_0_1::generic::portal::outlet::Frame::CommandEvent(event) => Frame::CommandEvent(event),
+                        v0_0_1::generic::portal::outlet::Frame::Request(request) => Frame::Request(Request {
+                            from: request.from,
+                            operation: request.operation.into(),
+                            kind: request.kind,
+                            idempotency_key: Option::None
+                        }),
+                        v0_0_1::generic::portal::outlet::Frame::Response(response) => Frame::Response(Response {
+                            from: response.from,
+                            exchange_id: response.exchange_id,
+                            signal: response.signal.into()
+                        }),
+                        v0_0_1::generic::portal::outlet::Frame::BinParcel(parcel) => Frame::BinParcel(parcel),
+                        v0_0_1::generic::portal::outlet::Frame::Close(reason) => Frame::Close(reason)
+                    }
+                }
+            }
+
+            impl <KEY: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, ADDRESS: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync, KIND: Debug + Clone + Serialize + Eq + PartialEq + Hash + ToString + FromStr + Send + Sync> TryFrom<Frame<KEY,ADDRESS,KIND>> for v0_0_1::generic::portal::outlet::Frame<KEY,ADDRESS,KIND> {
+                type Error = Error;
+
+                fn try_from(frame: Frame<KEY,ADDRESS,KIND>) -> Result<Self, Self::Error> {
+                    Ok(match frame {
+                        Frame::Init(info) => v0_0_1::generic::portal::outlet::Frame::Init(info.into()),
+                        Frame::CommandEvent(event) => v0_0_1::generic::portal::outlet::Frame::CommandEvent(event),
+                        Frame::Request(request) => v0_0_1::generic::portal::outlet::Frame::Request(v0_0_1::generic::portal::outlet::Request {
+                            from: request.from,
+                            operation: request.operation.into(),
+                            kind: request.kind
+                        }),
+                        Frame::Response(response) => v0_0_1::generic::portal::outlet::Frame::Response(v0_0_1::generic::portal::outlet::Response {
+                            from: response.from,
+                            exchange_id: response.exchange_id,
+                            signal: response.signal.into()
+                        }),
+                        Frame::BinParcel(parcel) => v0_0_1::generic::portal::outlet::Frame::BinParcel(parcel),
+                        Frame::Close(reason) => v0_0_1::generic::portal::outlet::Frame::Close(reason),
+                        Frame::Checkpoint(_) => return Err(anyhow!("a 0.0.1 portal cannot be asked for a checkpoint"))
+                    })
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,2 @@
+// the credentials moved to tcp-common so a gateway can present them too
+pub use resource_mesh_portal_tcp_common::auth::{BearerCredentials, Credentials, HmacCredentials, PasswordCredentials};
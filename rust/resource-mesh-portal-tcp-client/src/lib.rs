@@ -6,20 +6,21 @@ extern crate anyhow;
 
 
 
-use resource_mesh_portal_tcp_common::{PrimitiveFrameReader, PrimitiveFrameWriter, FrameWriter, FrameReader};
+use resource_mesh_portal_tcp_common::{PrimitiveFrameReader, PrimitiveFrameWriter, FrameWriter, FrameReader, FrameTooLarge, versioned_flavor};
+use resource_mesh_portal_tcp_common::tls::{TlsClient, TlsClientConfig};
 use anyhow::Error;
-use resource_mesh_portal_api_client::{Portal, PortalCtrl, PortalSkel, InletApi, Inlet };
+use resource_mesh_portal_api_client::{Portal, PortalCtrl, PortalSkel, Inlet };
 use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
-use resource_mesh_portal_serde::version::latest::portal;
-use resource_mesh_portal_serde::version::latest::log::Log;
-use tokio::sync::mpsc::error::TrySendError;
-use resource_mesh_portal_serde::version;
-use std::thread;
-use tokio::time::Duration;
 use resource_mesh_portal_serde::version::latest::portal::{outlet, inlet};
+use resource_mesh_portal_serde::version::WireVersion;
 
+pub mod auth;
+
+#[cfg(unix)]
+pub mod uds;
 
 pub struct PortalTcpClient {
     pub host: String,
@@ -29,93 +30,154 @@ pub struct PortalTcpClient {
 impl PortalTcpClient {
 
     pub async fn new( host: String, client: Box<dyn PortalClient> ) -> Result<Self,Error> {
+        Self::connect(host, client, Option::None).await
+    }
+
+    pub async fn new_tls( host: String, client: Box<dyn PortalClient>, tls: TlsClientConfig ) -> Result<Self,Error> {
+        let tls = TlsClient::new(&tls)?;
+        Self::connect(host, client, Option::Some(tls)).await
+    }
+
+    async fn connect( host: String, client: Box<dyn PortalClient>, tls: Option<TlsClient> ) -> Result<Self,Error> {
 
         let stream = TcpStream::connect(host.clone()).await?;
 
-        let (reader,writer) = stream.into_split();
-        let mut reader = PrimitiveFrameReader::new(reader);
-        let mut writer = PrimitiveFrameWriter::new(writer);
+        let (reader, writer) = match tls {
+            None => {
+                let (reader,writer) = stream.into_split();
+                (PrimitiveFrameReader::boxed(reader), PrimitiveFrameWriter::boxed(writer))
+            }
+            Some(tls) => {
+                tls.connect(host.as_str(), stream).await?
+            }
+        };
 
-        writer.write_string(client.flavor()).await?;
+        let portal = handshake(reader, writer, client).await?;
 
-        let result = reader.read_string().await?;
+        Ok(Self {
+            host,
+            portal
+        })
+    }
+}
 
+// establishes a portal over any byte stream: tcp, tls, unix sockets, stdio or an in memory duplex
+pub async fn connect<R,W>( reader: R, writer: W, client: Box<dyn PortalClient> ) -> Result<Arc<Portal>,Error> where R: AsyncRead+Send+Unpin+'static, W: AsyncWrite+Send+Unpin+'static {
+    handshake(PrimitiveFrameReader::boxed(reader), PrimitiveFrameWriter::boxed(writer), client).await
+}
 
-        if result != "Ok" {
-            let message = format!("FLAVOR MATCH FAILED: {}",result);
-            (client.logger())(message.as_str());
-            return Err(anyhow!(message));
+pub async fn handshake( mut reader: PrimitiveFrameReader, mut writer: PrimitiveFrameWriter, client: Box<dyn PortalClient> ) -> Result<Arc<Portal>,Error> {
+    if let Err(err) = negotiate(&mut reader, &mut writer, client.as_ref()).await {
+        if let Option::Some(too_large) = err.downcast_ref::<FrameTooLarge>() {
+            (client.logger())(format!("ERROR: {}", too_large).as_str());
+            writer.write_frame(inlet::Frame::Close(too_large.close_reason())).await.unwrap_or_default();
         }
+        return Err(err);
+    }
 
-        client.auth(&mut reader, &mut writer).await?;
-
-        let result = reader.read_string().await?;
+    let mut reader : FrameReader<outlet::Frame> = FrameReader::new_with_version(reader, client.version() );
+    let mut writer : FrameWriter<inlet::Frame>  = FrameWriter::new_with_version(writer, client.version() );
 
-        if result != "Ok" {
-            let message = format!("AUTH FAILED: {}",result);
+    let info = match reader.read( ).await {
+        Ok(outlet::Frame::Init(info)) => info,
+        Ok(_) => {
+            let message = "expected portal info.".to_string();
             (client.logger())(message.as_str());
             return Err(anyhow!(message));
         }
-
-        let mut reader : FrameReader<outlet::Frame> = FrameReader::new(reader );
-        let mut writer : FrameWriter<inlet::Frame>  = FrameWriter::new(writer );
-
-
-        let (inlet_tx, mut inlet_rx) = mpsc::channel(1024 );
-        let (outlet_tx, mut outlet_rx) = mpsc::channel(1024 );
-
-        {
-            let logger = client.logger();
-            tokio::spawn(async move {
-                while let Option::Some(frame) = inlet_rx.recv().await {
-                    match writer.write(frame).await {
-                        Ok(_) => {}
-                        Err(err) => {
-                            (logger)("FATAL: writer disconnected");
-                            break;
-                        }
+        Err(err) => {
+            if let Option::Some(too_large) = err.downcast_ref::<FrameTooLarge>() {
+                (client.logger())(format!("ERROR: {}", too_large).as_str());
+                writer.close(too_large.close_reason()).await;
+            }
+            return Err(err);
+        }
+    };
+
+    reader.set_max_frame_size(info.config.max_bin_size as usize);
+
+    let (inlet_tx, mut inlet_rx) = mpsc::channel(1024 );
+    let (outlet_tx, outlet_rx) = mpsc::channel(1024 );
+
+    {
+        let logger = client.logger();
+        tokio::spawn(async move {
+            while let Option::Some(frame) = inlet_rx.recv().await {
+                let close = matches!(frame, inlet::Frame::Close(_));
+                match writer.write(frame).await {
+                    Ok(_) => {}
+                    Err(_err) => {
+                        (logger)("FATAL: writer disconnected");
+                        break;
                     }
                 }
-            });
-        }
-
-
-        let inlet = Box::new(TcpInlet{
-          sender: inlet_tx,
-           logger: client.logger()
+                if close {
+                    break;
+                }
+            }
         });
+    }
 
-
-        if let outlet::Frame::Init(info) = reader.read( ).await?  {
-            let portal = Portal::new(info, inlet, client.portal_ctrl_factory(), client.logger()).await?;
-
-
-            {
-                let logger = client.logger();
-                tokio::spawn(async move {
-                    while let Result::Ok(frame) = reader.read().await {
-                        match outlet_tx.try_send( frame ) {
+    {
+        let logger = client.logger();
+        let inlet_tx = inlet_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                match reader.read().await {
+                    Ok(frame) => {
+                        match outlet_tx.send( frame ).await {
                             Result::Ok(_) => {}
-                            Result::Err(err) => {
+                            Result::Err(_err) => {
                                 (logger)("FATAL: reader disconnected");
                                 break;
                             }
                         }
                     }
-                });
+                    Err(err) => {
+                        if let Option::Some(too_large) = err.downcast_ref::<FrameTooLarge>() {
+                            (logger)(format!("ERROR: {}", too_large).as_str());
+                            inlet_tx.send(inlet::Frame::Close(too_large.close_reason())).await.unwrap_or_default();
+                        }
+                        break;
+                    }
+                }
             }
+        });
+    }
 
+    let inlet = Box::new(TcpInlet{
+      sender: inlet_tx,
+       logger: client.logger()
+    });
 
-            return Ok(Self {
-                host,
-                portal
-            });
-        } else {
-            let message = "expected portal info.".to_string();
-            (client.logger())(message.as_str());
-            return Err(anyhow!(message));
-        }
+    let portal = Portal::new(info, inlet, outlet_rx, client.portal_ctrl_factory(), client.logger()).await?;
+
+    Ok(portal)
+}
+
+async fn negotiate( reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter, client: &dyn PortalClient ) -> Result<(),Error> {
+    writer.write_string(versioned_flavor(client.flavor().as_str(), client.version())).await?;
+
+    let result = reader.read_string().await?;
+
+
+    if result != "Ok" {
+        let message = format!("FLAVOR MATCH FAILED: {}",result);
+        (client.logger())(message.as_str());
+        return Err(anyhow!(message));
     }
+
+    client.auth(reader, writer).await?;
+
+    let result = reader.read_string().await?;
+
+    if result != "Ok" {
+        let message = format!("AUTH FAILED: {}",result);
+        (client.logger())(message.as_str());
+        return Err(anyhow!(message));
+    }
+
+    Ok(())
 }
 
 #[async_trait]
@@ -124,6 +186,11 @@ pub trait PortalClient: Send+Sync {
     async fn auth( &self, reader: & mut PrimitiveFrameReader, writer: & mut PrimitiveFrameWriter ) -> Result<(),Error>;
     fn portal_ctrl_factory(&self)->fn( skel: PortalSkel) -> Box<dyn PortalCtrl>;
     fn logger(&self) -> fn(message: &str);
+
+    // a server that predates 0.0.2 only understands a client that speaks 0.0.1
+    fn version(&self) -> WireVersion {
+        WireVersion::latest()
+    }
 }
 
 struct TcpInlet {
@@ -136,8 +203,8 @@ impl Inlet for TcpInlet {
         match self.sender.try_send(frame)
         {
             Ok(_) => {}
-            Err(err) => {
-                (self.logger)(format!("ERROR: frame failed to send to client inlet").as_str())
+            Err(_err) => {
+                (self.logger)("ERROR: frame failed to send to client inlet".to_string().as_str())
             }
         }
     }
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Error;
+use tokio::net::UnixStream;
+
+use resource_mesh_portal_api_client::Portal;
+
+use crate::{connect, PortalClient};
+
+pub struct PortalUdsClient {
+    pub path: PathBuf,
+    pub portal: Arc<Portal>
+}
+
+impl PortalUdsClient {
+    pub async fn new( path: PathBuf, client: Box<dyn PortalClient> ) -> Result<Self,Error> {
+        let stream = UnixStream::connect(&path).await?;
+        let (reader, writer) = stream.into_split();
+        let portal = connect(reader, writer, client).await?;
+
+        Ok(Self {
+            path,
+            portal
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Error;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{PrimitiveFrameReader, PrimitiveFrameWriter};
+
+// every built in authenticator begins by naming its mechanism so a server
+// can offer several of them on the same port
+pub const BEARER: &str = "bearer";
+pub const HMAC_SHA256: &str = "hmac-sha256";
+pub const PASSWORD: &str = "password";
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac( secret: &[u8], user: &str, nonce: &str ) -> Result<HmacSha256,Error> {
+    let mut mac = HmacSha256::new_from_slice(secret).map_err(|err| anyhow!("invalid hmac secret: {}", err))?;
+    mac.update(user.as_bytes());
+    mac.update(b":");
+    mac.update(nonce.as_bytes());
+    Ok(mac)
+}
+
+pub fn sign( secret: &[u8], user: &str, nonce: &str ) -> Result<String,Error> {
+    Ok(hex::encode(mac(secret, user, nonce)?.finalize().into_bytes()))
+}
+
+pub fn verify( secret: &[u8], user: &str, nonce: &str, signature: &str ) -> Result<(),Error> {
+    let signature = hex::decode(signature).map_err(|_| anyhow!("malformed hmac signature"))?;
+    mac(secret, user, nonce)?.verify_slice(signature.as_slice()).map_err(|_| anyhow!("hmac signature does not match"))
+}
+
+// the client half of the authenticators offered by resource_mesh_portal_tcp_server::auth.
+// a PortalClient simply forwards its auth() call to whichever credentials it holds and a
+// Gateway presents them when it links to another
+#[async_trait]
+pub trait Credentials: Send+Sync {
+    async fn authenticate(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<(),Error>;
+}
+
+pub struct BearerCredentials {
+    pub token: String
+}
+
+impl BearerCredentials {
+    pub fn new( token: String ) -> Self {
+        Self {
+            token
+        }
+    }
+}
+
+#[async_trait]
+impl Credentials for BearerCredentials {
+    async fn authenticate(&self, _reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<(),Error> {
+        writer.write_string(BEARER.to_string()).await?;
+        writer.write_string(self.token.clone()).await?;
+        Ok(())
+    }
+}
+
+pub struct HmacCredentials {
+    pub user: String,
+    secret: Vec<u8>
+}
+
+impl HmacCredentials {
+    pub fn new( user: String, secret: Vec<u8> ) -> Self {
+        Self {
+            user,
+            secret
+        }
+    }
+}
+
+#[async_trait]
+impl Credentials for HmacCredentials {
+    async fn authenticate(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<(),Error> {
+        writer.write_string(HMAC_SHA256.to_string()).await?;
+        writer.write_string(self.user.clone()).await?;
+        let nonce = reader.read_string().await?;
+        let signature = sign(self.secret.as_slice(), self.user.as_str(), nonce.as_str())?;
+        writer.write_string(signature).await?;
+        Ok(())
+    }
+}
+
+pub struct PasswordCredentials {
+    pub user: String,
+    password: String
+}
+
+impl PasswordCredentials {
+    pub fn new( user: String, password: String ) -> Self {
+        Self {
+            user,
+            password
+        }
+    }
+}
+
+#[async_trait]
+impl Credentials for PasswordCredentials {
+    async fn authenticate(&self, _reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<(),Error> {
+        writer.write_string(PASSWORD.to_string()).await?;
+        writer.write_string(self.user.clone()).await?;
+        writer.write_string(self.password.clone()).await?;
+        Ok(())
+    }
+}
@@ -1,17 +1,27 @@
+#[macro_use]
+extern crate async_trait;
+
 #[macro_use]
 extern crate anyhow;
 
 
 use std::convert::{TryFrom, TryInto};
+use std::str::FromStr;
 
 use anyhow::Error;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, AsyncWrite};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, AsyncWrite, AsyncRead};
 
-use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use std::fmt;
 use std::marker::PhantomData;
-use std::time::Duration;
+use std::sync::Arc;
+use resource_mesh_portal_metrics::Counter;
+use resource_mesh_portal_serde::version::latest::config::Config;
 use resource_mesh_portal_serde::version::latest::frame::{PrimitiveFrame, CloseReason};
 use resource_mesh_portal_serde::version::latest::portal::{outlet, inlet};
+use resource_mesh_portal_serde::version::WireVersion;
+
+pub mod auth;
+pub mod tls;
 
 #[cfg(test)]
 mod tests {
@@ -22,24 +32,74 @@ mod tests {
     }
 }
 
-pub struct FrameWriter<FRAME> where FRAME: TryInto<PrimitiveFrame> {
-    stream: PrimitiveFrameWriter,
+#[derive(Debug, Clone)]
+pub struct FrameTooLarge {
+    pub size: usize,
+    pub max: usize
+}
+
+impl fmt::Display for FrameTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame of {} bytes exceeds the maximum frame size of {} bytes", self.size, self.max)
+    }
+}
+
+impl std::error::Error for FrameTooLarge {}
+
+impl FrameTooLarge {
+    pub fn close_reason(&self) -> CloseReason {
+        CloseReason::Error(self.to_string())
+    }
+}
+
+pub fn default_max_frame_size() -> usize {
+    Config::default().max_bin_size as usize
+}
+
+// a client names the wire version it speaks after its flavor as in 'flavor@0.0.2'.  a flavor
+// without one comes from a client that predates versions and so speaks 0.0.1
+pub fn versioned_flavor( flavor: &str, version: WireVersion ) -> String {
+    match version {
+        WireVersion::V0_0_1 => flavor.to_string(),
+        version => format!("{}@{}", flavor, version)
+    }
+}
+
+pub fn split_flavor( flavor: &str ) -> Result<(String,WireVersion),Error> {
+    match flavor.rsplit_once('@') {
+        Some((flavor, version)) => Ok((flavor.to_string(), WireVersion::from_str(version)?)),
+        None => Ok((flavor.to_string(), WireVersion::V0_0_1))
+    }
+}
+
+pub type DynRead = Box<dyn AsyncRead+Send+Unpin>;
+pub type DynWrite = Box<dyn AsyncWrite+Send+Unpin>;
+
+pub struct FrameWriter<FRAME,W=DynWrite> where FRAME: TryInto<PrimitiveFrame>, W: AsyncWrite+Unpin {
+    stream: PrimitiveFrameWriter<W>,
+    version: WireVersion,
     phantom: PhantomData<FRAME>
 }
 
-impl <FRAME> FrameWriter<FRAME> where FRAME: TryInto<PrimitiveFrame>  {
-    pub fn new(stream: PrimitiveFrameWriter) -> Self {
+impl <FRAME,W> FrameWriter<FRAME,W> where FRAME: TryInto<PrimitiveFrame>, W: AsyncWrite+Unpin  {
+    pub fn new(stream: PrimitiveFrameWriter<W>) -> Self {
+        Self::new_with_version(stream, WireVersion::latest())
+    }
+
+    // frames are written in the layout of version, whatever the peer agreed to speak
+    pub fn new_with_version(stream: PrimitiveFrameWriter<W>, version: WireVersion) -> Self {
         Self {
             stream,
+            version,
             phantom: PhantomData
         }
     }
 }
 
-impl FrameWriter<outlet::Frame>  {
+impl <W> FrameWriter<outlet::Frame,W> where W: AsyncWrite+Unpin {
 
     pub async fn write( &mut self, frame: outlet::Frame ) -> Result<(),Error> {
-        let frame = frame.try_into()?;
+        let frame = self.version.encode_outlet(frame)?;
         self.stream.write(frame).await
     }
 
@@ -49,10 +109,10 @@ impl FrameWriter<outlet::Frame>  {
 
 }
 
-impl FrameWriter<inlet::Frame> {
+impl <W> FrameWriter<inlet::Frame,W> where W: AsyncWrite+Unpin {
 
     pub async fn write( &mut self, frame: inlet::Frame ) -> Result<(),Error> {
-        let frame = frame.try_into()?;
+        let frame = self.version.encode_inlet(frame)?;
         self.stream.write(frame).await
     }
 
@@ -62,52 +122,97 @@ impl FrameWriter<inlet::Frame> {
 }
 
 
-pub struct FrameReader<FRAME> where FRAME: TryFrom<PrimitiveFrame> {
-    stream: PrimitiveFrameReader,
+pub struct FrameReader<FRAME,R=DynRead> where FRAME: TryFrom<PrimitiveFrame>, R: AsyncRead+Unpin {
+    stream: PrimitiveFrameReader<R>,
+    version: WireVersion,
     phantom: PhantomData<FRAME>
 }
 
-impl <FRAME> FrameReader<FRAME>  where FRAME: TryFrom<PrimitiveFrame> {
-    pub fn new(stream: PrimitiveFrameReader) -> Self {
+impl <FRAME,R> FrameReader<FRAME,R>  where FRAME: TryFrom<PrimitiveFrame>, R: AsyncRead+Unpin {
+    pub fn new(stream: PrimitiveFrameReader<R>) -> Self {
+        Self::new_with_version(stream, WireVersion::latest())
+    }
+
+    pub fn new_with_version(stream: PrimitiveFrameReader<R>, version: WireVersion) -> Self {
         Self {
             stream,
+            version,
             phantom: PhantomData
         }
     }
+
+    pub fn set_max_frame_size( &mut self, max_frame_size: usize ) {
+        self.stream.set_max_frame_size(max_frame_size);
+    }
 }
 
-impl FrameReader<outlet::Frame> {
+impl <R> FrameReader<outlet::Frame,R> where R: AsyncRead+Unpin {
     pub async fn read( &mut self ) -> Result<outlet::Frame,Error> {
         let frame = self.stream.read().await?;
-        Ok(outlet::Frame::try_from(frame)?)
+        self.version.decode_outlet(frame)
     }
 }
 
-impl FrameReader<inlet::Frame> {
+impl <R> FrameReader<inlet::Frame,R> where R: AsyncRead+Unpin {
     pub async fn read( &mut self ) -> Result<inlet::Frame,Error> {
         let frame = self.stream.read().await?;
-        Ok(inlet::Frame::try_from(frame)?)
+        self.version.decode_inlet(frame)
     }
 }
 
-pub struct PrimitiveFrameReader {
-    read: OwnedReadHalf
+fn bytes_read() -> Arc<Counter> {
+    resource_mesh_portal_metrics::global().counter("portal_bytes_read_total", "Bytes read from portal streams, length prefixes included.", &[])
+}
+
+fn bytes_written() -> Arc<Counter> {
+    resource_mesh_portal_metrics::global().counter("portal_bytes_written_total", "Bytes written to portal streams, length prefixes included.", &[])
+}
+
+pub struct PrimitiveFrameReader<R=DynRead> where R: AsyncRead+Unpin {
+    read: R,
+    max_frame_size: usize,
+    bytes_read: Arc<Counter>
 }
 
 impl PrimitiveFrameReader {
+    pub fn boxed<R>(read: R) -> Self where R: AsyncRead+Send+Unpin+'static {
+        Self::new(Box::new(read))
+    }
+}
 
-    pub fn new(read: OwnedReadHalf ) -> Self {
+impl <R> PrimitiveFrameReader<R> where R: AsyncRead+Unpin {
+
+    pub fn new(read: R ) -> Self {
         Self {
-           read
+           read,
+           max_frame_size: default_max_frame_size(),
+           bytes_read: bytes_read()
         }
     }
 
+    pub fn set_max_frame_size( &mut self, max_frame_size: usize ) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
     pub async fn read(&mut self) -> Result<PrimitiveFrame,Error> {
         let size = self.read.read_u32().await? as usize;
 
-        let mut vec= vec![0 as u8; size];
+        // never trust the length prefix enough to allocate it blindly
+        if size > self.max_frame_size {
+            return Err(FrameTooLarge {
+                size,
+                max: self.max_frame_size
+            }.into());
+        }
+
+        let mut vec= vec![0_u8; size];
         let buf = vec.as_mut_slice();
         self.read.read_exact(buf).await?;
+        self.bytes_read.add(4 + size as u64);
         Result::Ok(PrimitiveFrame {
             data: vec
         })
@@ -115,21 +220,29 @@ impl PrimitiveFrameReader {
 
     pub async fn read_string(&mut self) -> Result<String,Error> {
         let frame = self.read().await?;
-        Ok(frame.try_into()?)
+        frame.try_into()
     }
 
 
 }
 
-pub struct PrimitiveFrameWriter {
-    write: OwnedWriteHalf,
+pub struct PrimitiveFrameWriter<W=DynWrite> where W: AsyncWrite+Unpin {
+    write: W,
+    bytes_written: Arc<Counter>
 }
 
 impl PrimitiveFrameWriter {
+    pub fn boxed<W>(write: W) -> Self where W: AsyncWrite+Send+Unpin+'static {
+        Self::new(Box::new(write))
+    }
+}
 
-    pub fn new(write: OwnedWriteHalf) -> Self {
+impl <W> PrimitiveFrameWriter<W> where W: AsyncWrite+Unpin {
+
+    pub fn new(write: W) -> Self {
         Self {
             write,
+            bytes_written: bytes_written()
         }
     }
 
@@ -137,13 +250,18 @@ impl PrimitiveFrameWriter {
     pub async fn write( &mut self, frame: PrimitiveFrame ) -> Result<(),Error> {
         self.write.write_u32(frame.size() ).await?;
         self.write.write_all(frame.data.as_slice() ).await?;
+        self.bytes_written.add(4 + frame.data.len() as u64);
         Ok(())
     }
 
 
+    pub async fn write_frame<FRAME>( &mut self, frame: FRAME ) -> Result<(),Error> where FRAME: TryInto<PrimitiveFrame,Error=Error> {
+        let frame = frame.try_into()?;
+        self.write(frame).await
+    }
+
     pub async fn write_string(&mut self, string: String) -> Result<(),Error> {
         let frame = PrimitiveFrame::from(string);
         self.write(frame).await
     }
 }
-
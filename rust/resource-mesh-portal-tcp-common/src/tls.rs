@@ -0,0 +1,197 @@
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Error;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName};
+use tokio_rustls::rustls::server::{AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use crate::{PrimitiveFrameReader, PrimitiveFrameWriter};
+
+#[derive(Debug, Clone)]
+pub enum ClientAuth {
+    None,
+    Optional(PathBuf),
+    Required(PathBuf)
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsServerConfig {
+    pub cert_chain: PathBuf,
+    pub private_key: PathBuf,
+    pub client_auth: ClientAuth
+}
+
+impl TlsServerConfig {
+    pub fn new( cert_chain: PathBuf, private_key: PathBuf ) -> Self {
+        Self {
+            cert_chain,
+            private_key,
+            client_auth: ClientAuth::None
+        }
+    }
+
+    pub fn with_client_auth( mut self, client_auth: ClientAuth ) -> Self {
+        self.client_auth = client_auth;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsClientConfig {
+    pub ca: PathBuf,
+    pub server_name: Option<String>,
+    pub client_cert: Option<(PathBuf,PathBuf)>
+}
+
+impl TlsClientConfig {
+    pub fn new( ca: PathBuf ) -> Self {
+        Self {
+            ca,
+            server_name: None,
+            client_cert: None
+        }
+    }
+
+    pub fn with_server_name( mut self, server_name: String ) -> Self {
+        self.server_name = Option::Some(server_name);
+        self
+    }
+
+    pub fn with_client_cert( mut self, cert_chain: PathBuf, private_key: PathBuf ) -> Self {
+        self.client_cert = Option::Some((cert_chain,private_key));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PeerIdentity {
+    pub common_name: Option<String>,
+    pub certificate: Vec<u8>
+}
+
+impl PeerIdentity {
+    pub fn from_der( certificate: Vec<u8> ) -> Result<Self,Error> {
+        let common_name = {
+            let (_, cert) = x509_parser::parse_x509_certificate(certificate.as_slice()).map_err(|err| anyhow!("could not parse peer certificate: {}", err))?;
+            let common_name = cert.subject().iter_common_name().next().and_then(|cn| cn.as_str().ok()).map(|cn| cn.to_string());
+            common_name
+        };
+        Ok(Self {
+            common_name,
+            certificate
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct TlsServer {
+    acceptor: TlsAcceptor
+}
+
+impl TlsServer {
+    pub fn new( config: &TlsServerConfig ) -> Result<Self,Error> {
+        let certs = load_certs(&config.cert_chain)?;
+        let key = load_private_key(&config.private_key)?;
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let server_config = match &config.client_auth {
+            ClientAuth::None => {
+                builder.with_no_client_auth().with_single_cert(certs, key)?
+            }
+            ClientAuth::Optional(ca) => {
+                let roots = load_root_store(ca)?;
+                builder.with_client_cert_verifier(AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed()).with_single_cert(certs, key)?
+            }
+            ClientAuth::Required(ca) => {
+                let roots = load_root_store(ca)?;
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed()).with_single_cert(certs, key)?
+            }
+        };
+
+        Ok(Self {
+            acceptor: TlsAcceptor::from(Arc::new(server_config))
+        })
+    }
+
+    pub async fn accept( &self, stream: TcpStream ) -> Result<(PrimitiveFrameReader, PrimitiveFrameWriter, Option<PeerIdentity>),Error> {
+        let stream = self.acceptor.accept(stream).await?;
+        let peer = match stream.get_ref().1.peer_certificates() {
+            Some(certs) if !certs.is_empty() => {
+                Option::Some(PeerIdentity::from_der(certs[0].0.clone())?)
+            }
+            _ => Option::None
+        };
+        let (reader, writer) = tokio::io::split(stream);
+        Ok((PrimitiveFrameReader::boxed(reader), PrimitiveFrameWriter::boxed(writer), peer))
+    }
+}
+
+pub struct TlsClient {
+    connector: TlsConnector,
+    server_name: Option<String>
+}
+
+impl TlsClient {
+    pub fn new( config: &TlsClientConfig ) -> Result<Self,Error> {
+        let roots = load_root_store(&config.ca)?;
+        let builder = ClientConfig::builder().with_safe_defaults().with_root_certificates(roots);
+        let client_config = match &config.client_cert {
+            None => builder.with_no_client_auth(),
+            Some((cert_chain,private_key)) => {
+                let certs = load_certs(cert_chain)?;
+                let key = load_private_key(private_key)?;
+                builder.with_client_auth_cert(certs, key)?
+            }
+        };
+
+        Ok(Self {
+            connector: TlsConnector::from(Arc::new(client_config)),
+            server_name: config.server_name.clone()
+        })
+    }
+
+    pub async fn connect( &self, host: &str, stream: TcpStream ) -> Result<(PrimitiveFrameReader, PrimitiveFrameWriter),Error> {
+        let server_name = match &self.server_name {
+            Some(server_name) => server_name.clone(),
+            None => host.split(':').next().unwrap_or(host).to_string()
+        };
+        let server_name = ServerName::try_from(server_name.as_str()).map_err(|err| anyhow!("invalid tls server name '{}': {}", server_name, err))?;
+        let stream = self.connector.connect(server_name, stream).await?;
+        let (reader, writer) = tokio::io::split(stream);
+        Ok((PrimitiveFrameReader::boxed(reader), PrimitiveFrameWriter::boxed(writer)))
+    }
+}
+
+pub fn load_certs( path: &PathBuf ) -> Result<Vec<Certificate>,Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let certs: Vec<Certificate> = rustls_pemfile::certs(&mut reader)?.into_iter().map(Certificate).collect();
+    if certs.is_empty() {
+        return Err(anyhow!("no certificates found in '{}'", path.display()));
+    }
+    Ok(certs)
+}
+
+pub fn load_private_key( path: &PathBuf ) -> Result<PrivateKey,Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    for item in rustls_pemfile::read_all(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) | rustls_pemfile::Item::ECKey(key) => {
+                return Ok(PrivateKey(key));
+            }
+            _ => {}
+        }
+    }
+    Err(anyhow!("no private key found in '{}'", path.display()))
+}
+
+fn load_root_store( path: &PathBuf ) -> Result<RootCertStore,Error> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(&cert)?;
+    }
+    Ok(roots)
+}
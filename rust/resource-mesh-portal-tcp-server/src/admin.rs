@@ -0,0 +1,200 @@
+use std::convert::{TryFrom, TryInto};
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+
+use resource_mesh_portal_api_server::admin::{PortalSnapshot, RoutingState};
+use resource_mesh_portal_serde::version::latest::frame::{CloseReason, PrimitiveFrame};
+use resource_mesh_portal_serde::version::latest::id::Identifier;
+use resource_mesh_portal_tcp_common::{PrimitiveFrameReader, PrimitiveFrameWriter};
+
+use crate::Call;
+use crate::uds::UdsServerConfig;
+
+// the admin socket speaks the same Call api as the server's call_tx, one request
+// answered by one response, as many as the admin cares to send over a connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AdminRequest {
+    Portals,
+    Portal(Identifier),
+    Close{ id: Identifier, reason: CloseReason },
+    Routing
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+pub enum AdminResponse {
+    Portals(Vec<PortalSnapshot>),
+    Portal(Option<PortalSnapshot>),
+    Closed(bool),
+    Routing(RoutingState),
+    Fail(String)
+}
+
+impl TryInto<PrimitiveFrame> for AdminRequest {
+    type Error = Error;
+
+    fn try_into(self) -> Result<PrimitiveFrame, Self::Error> {
+        Ok(PrimitiveFrame {
+            data: bincode::serialize(&self)?
+        })
+    }
+}
+
+impl TryFrom<PrimitiveFrame> for AdminRequest {
+    type Error = Error;
+
+    fn try_from(value: PrimitiveFrame) -> Result<Self, Self::Error> {
+        Ok(bincode::deserialize(value.data.as_slice())?)
+    }
+}
+
+impl TryInto<PrimitiveFrame> for AdminResponse {
+    type Error = Error;
+
+    fn try_into(self) -> Result<PrimitiveFrame, Self::Error> {
+        Ok(PrimitiveFrame {
+            data: bincode::serialize(&self)?
+        })
+    }
+}
+
+impl TryFrom<PrimitiveFrame> for AdminResponse {
+    type Error = Error;
+
+    fn try_from(value: PrimitiveFrame) -> Result<Self, Self::Error> {
+        Ok(bincode::deserialize(value.data.as_slice())?)
+    }
+}
+
+// the admin can close any portal, so it is only offered on a unix socket where the socket's
+// mode & owner and the config's allowed uids & gids decide who gets in.  the socket is
+// removed once the portal server has shutdown
+pub async fn serve( config: UdsServerConfig, server: mpsc::Sender<Call> ) -> Result<PathBuf,Error> {
+    if config.path.exists() {
+        std::fs::remove_file(&config.path)?;
+    }
+    let listener = UnixListener::bind(&config.path)?;
+    config.secure()?;
+    let path = config.path.clone();
+    tokio::spawn(async move {
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => break
+                },
+                _ = server.closed() => break
+            };
+            if config.authorize(&stream).is_err() {
+                continue;
+            }
+            let server = server.clone();
+            tokio::spawn(async move {
+                let (reader, writer) = stream.into_split();
+                let mut reader = PrimitiveFrameReader::boxed(reader);
+                let mut writer = PrimitiveFrameWriter::boxed(writer);
+                while let Ok(request) = reader.read().await.and_then(AdminRequest::try_from) {
+                    let response = match handle(&server, request).await {
+                        Ok(response) => response,
+                        Err(err) => AdminResponse::Fail(err.to_string())
+                    };
+                    if writer.write_frame(response).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(listener);
+        std::fs::remove_file(&config.path).unwrap_or_default();
+    });
+    Ok(path)
+}
+
+async fn handle( server: &mpsc::Sender<Call>, request: AdminRequest ) -> Result<AdminResponse,Error> {
+    match request {
+        AdminRequest::Portals => {
+            let (tx,rx) = oneshot::channel();
+            call(server, Call::Portals(tx)).await?;
+            Ok(AdminResponse::Portals(rx.await?))
+        }
+        AdminRequest::Portal(id) => {
+            let (tx,rx) = oneshot::channel();
+            call(server, Call::Portal{ id, tx }).await?;
+            Ok(AdminResponse::Portal(rx.await?))
+        }
+        AdminRequest::Close{ id, reason } => {
+            let (tx,rx) = oneshot::channel();
+            call(server, Call::ClosePortal{ id, reason, tx }).await?;
+            Ok(AdminResponse::Closed(rx.await?))
+        }
+        AdminRequest::Routing => {
+            let (tx,rx) = oneshot::channel();
+            call(server, Call::Routing(tx)).await?;
+            Ok(AdminResponse::Routing(rx.await?))
+        }
+    }
+}
+
+async fn call( server: &mpsc::Sender<Call>, call: Call ) -> Result<(),Error> {
+    server.send(call).await.map_err(|_| anyhow!("portal server has shutdown"))
+}
+
+pub struct AdminClient {
+    reader: PrimitiveFrameReader,
+    writer: PrimitiveFrameWriter
+}
+
+impl AdminClient {
+    pub async fn connect( path: &Path ) -> Result<Self,Error> {
+        let stream = UnixStream::connect(path).await?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self {
+            reader: PrimitiveFrameReader::boxed(reader),
+            writer: PrimitiveFrameWriter::boxed(writer)
+        })
+    }
+
+    pub async fn portals( &mut self ) -> Result<Vec<PortalSnapshot>,Error> {
+        match self.request(AdminRequest::Portals).await? {
+            AdminResponse::Portals(portals) => Ok(portals),
+            response => Err(unexpected(response))
+        }
+    }
+
+    pub async fn portal( &mut self, id: Identifier ) -> Result<Option<PortalSnapshot>,Error> {
+        match self.request(AdminRequest::Portal(id)).await? {
+            AdminResponse::Portal(portal) => Ok(portal),
+            response => Err(unexpected(response))
+        }
+    }
+
+    pub async fn close( &mut self, id: Identifier, reason: CloseReason ) -> Result<bool,Error> {
+        match self.request(AdminRequest::Close{ id, reason }).await? {
+            AdminResponse::Closed(closed) => Ok(closed),
+            response => Err(unexpected(response))
+        }
+    }
+
+    pub async fn routing( &mut self ) -> Result<RoutingState,Error> {
+        match self.request(AdminRequest::Routing).await? {
+            AdminResponse::Routing(routing) => Ok(routing),
+            response => Err(unexpected(response))
+        }
+    }
+
+    async fn request( &mut self, request: AdminRequest ) -> Result<AdminResponse,Error> {
+        self.writer.write_frame(request).await?;
+        AdminResponse::try_from(self.reader.read().await?)
+    }
+}
+
+fn unexpected( response: AdminResponse ) -> Error {
+    match response {
+        AdminResponse::Fail(message) => anyhow!(message),
+        response => anyhow!("unexpected admin response: {:?}", response)
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Error;
+use rand::RngCore;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use resource_mesh_portal_tcp_common::{auth, PrimitiveFrameReader, PrimitiveFrameWriter};
+
+#[async_trait]
+pub trait Authenticator: Send+Sync {
+    fn mechanism(&self) -> String;
+    async fn authenticate(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<String,Error>;
+}
+
+// offers any number of mechanisms on the same server.  a PortalServer simply
+// forwards its auth() call here and gets back the authenticated user
+pub struct Authenticators {
+    authenticators: HashMap<String,Box<dyn Authenticator>>
+}
+
+impl Default for Authenticators {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Authenticators {
+    pub fn new() -> Self {
+        Self {
+            authenticators: HashMap::new()
+        }
+    }
+
+    pub fn with( mut self, authenticator: Box<dyn Authenticator> ) -> Self {
+        self.authenticators.insert(authenticator.mechanism(), authenticator);
+        self
+    }
+
+    pub async fn auth(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<String,Error> {
+        let mechanism = reader.read_string().await?;
+        match self.authenticators.get(&mechanism) {
+            Some(authenticator) => authenticator.authenticate(reader, writer).await,
+            None => Err(anyhow!("unsupported authentication mechanism '{}'", mechanism))
+        }
+    }
+}
+
+pub struct BearerAuthenticator {
+    tokens: Vec<(String,String)>
+}
+
+impl Default for BearerAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BearerAuthenticator {
+    pub fn new() -> Self {
+        Self {
+            tokens: vec![]
+        }
+    }
+
+    pub fn with_token( mut self, token: String, user: String ) -> Self {
+        self.tokens.push((token,user));
+        self
+    }
+}
+
+#[async_trait]
+impl Authenticator for BearerAuthenticator {
+    fn mechanism(&self) -> String {
+        auth::BEARER.to_string()
+    }
+
+    async fn authenticate(&self, reader: &mut PrimitiveFrameReader, _writer: &mut PrimitiveFrameWriter) -> Result<String,Error> {
+        let token = reader.read_string().await?;
+        // compare against every token so the time taken does not reveal which one came close
+        let mut user = Option::None;
+        for (candidate, candidate_user) in &self.tokens {
+            if bool::from(candidate.as_bytes().ct_eq(token.as_bytes())) {
+                user = Option::Some(candidate_user.clone());
+            }
+        }
+        user.ok_or(anyhow!("invalid bearer token"))
+    }
+}
+
+// the server issues a fresh random nonce for every handshake and the client proves
+// it holds the shared secret by returning HMAC-SHA256(secret, user:nonce).
+// the secret never crosses the wire and a captured signature is useless for any later handshake
+pub struct HmacAuthenticator {
+    secrets: HashMap<String,Vec<u8>>,
+    // what an unknown user's signature is checked against, so it costs the same as a known one
+    dummy: Vec<u8>
+}
+
+impl Default for HmacAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HmacAuthenticator {
+    pub fn new() -> Self {
+        let mut dummy = vec![0u8; 32];
+        rand::thread_rng().fill_bytes(dummy.as_mut_slice());
+        Self {
+            secrets: HashMap::new(),
+            dummy
+        }
+    }
+
+    pub fn with_secret( mut self, user: String, secret: Vec<u8> ) -> Self {
+        self.secrets.insert(user, secret);
+        self
+    }
+}
+
+#[async_trait]
+impl Authenticator for HmacAuthenticator {
+    fn mechanism(&self) -> String {
+        auth::HMAC_SHA256.to_string()
+    }
+
+    async fn authenticate(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<String,Error> {
+        let user = reader.read_string().await?;
+
+        let mut nonce = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let nonce = hex::encode(nonce);
+        writer.write_string(nonce.clone()).await?;
+
+        let signature = reader.read_string().await?;
+
+        // unknown users still get a challenge and a check so they cannot be told apart from a bad signature
+        match self.secrets.get(&user) {
+            Some(secret) => {
+                auth::verify(secret.as_slice(), user.as_str(), nonce.as_str(), signature.as_str())?;
+                Ok(user)
+            }
+            None => {
+                auth::verify(self.dummy.as_slice(), user.as_str(), nonce.as_str(), signature.as_str()).ok();
+                Err(anyhow!("hmac signature does not match"))
+            }
+        }
+    }
+}
+
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+#[derive(Clone)]
+struct HashedSecret {
+    iterations: u32,
+    salt: Vec<u8>,
+    hash: Vec<u8>
+}
+
+impl HashedSecret {
+    fn new( secret: &str ) -> Self {
+        Self::new_with_iterations(secret, PBKDF2_ITERATIONS)
+    }
+
+    fn new_with_iterations( secret: &str, iterations: u32 ) -> Self {
+        let mut salt = vec![0u8; 16];
+        rand::thread_rng().fill_bytes(salt.as_mut_slice());
+        let hash = Self::derive(secret, salt.as_slice(), iterations);
+        Self {
+            iterations,
+            salt,
+            hash
+        }
+    }
+
+    fn derive( secret: &str, salt: &[u8], iterations: u32 ) -> Vec<u8> {
+        let mut hash = vec![0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(secret.as_bytes(), salt, iterations, hash.as_mut_slice());
+        hash
+    }
+
+    fn verify( &self, secret: &str ) -> bool {
+        let hash = Self::derive(secret, self.salt.as_slice(), self.iterations);
+        bool::from(hash.as_slice().ct_eq(self.hash.as_slice()))
+    }
+}
+
+// credentials are kept one per line as 'user:iterations:salt:hash' where salt and hash
+// are hex encoded and hash is PBKDF2-HMAC-SHA256 of the secret.  lines starting with '#' are ignored.
+// secrets are sent as is during the handshake, so only offer this mechanism over tls or a unix socket
+pub struct CredentialStore {
+    pub path: PathBuf,
+    credentials: HashMap<String,HashedSecret>,
+    // an unknown user is verified against this so it takes as long as a known one
+    dummy: HashedSecret
+}
+
+impl CredentialStore {
+    pub fn load( path: PathBuf ) -> Result<Self,Error> {
+        let content = std::fs::read_to_string(&path)?;
+        let mut credentials = HashMap::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parts: Vec<&str> = line.split(':').collect();
+            if parts.len() != 4 {
+                return Err(anyhow!("malformed credential on line {} of '{}'", index+1, path.display()));
+            }
+            let secret = HashedSecret {
+                iterations: parts[1].parse()?,
+                salt: hex::decode(parts[2])?,
+                hash: hex::decode(parts[3])?
+            };
+            credentials.insert(parts[0].to_string(), secret);
+        }
+        let iterations = credentials.values().map(|secret| secret.iterations).max().unwrap_or(PBKDF2_ITERATIONS);
+        let mut dummy = vec![0u8; 32];
+        rand::thread_rng().fill_bytes(dummy.as_mut_slice());
+        let dummy = HashedSecret::new_with_iterations(hex::encode(dummy).as_str(), iterations);
+        Ok(Self {
+            path,
+            credentials,
+            dummy
+        })
+    }
+
+    // produces a line suitable for appending to a credential file
+    pub fn entry( user: &str, secret: &str ) -> String {
+        let hashed = HashedSecret::new(secret);
+        format!("{}:{}:{}:{}", user, hashed.iterations, hex::encode(hashed.salt), hex::encode(hashed.hash))
+    }
+
+    pub fn verify( &self, user: &str, secret: &str ) -> bool {
+        match self.credentials.get(user) {
+            Some(hashed) => hashed.verify(secret),
+            None => {
+                std::hint::black_box(self.dummy.verify(secret));
+                false
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Authenticator for CredentialStore {
+    fn mechanism(&self) -> String {
+        auth::PASSWORD.to_string()
+    }
+
+    async fn authenticate(&self, reader: &mut PrimitiveFrameReader, _writer: &mut PrimitiveFrameWriter) -> Result<String,Error> {
+        let user = reader.read_string().await?;
+        let secret = reader.read_string().await?;
+        // deriving the hash takes tens of milliseconds, so it runs on the blocking pool
+        // instead of stalling a runtime worker.  unknown users pay the same against the dummy
+        let (known, hashed) = match self.credentials.get(&user) {
+            Some(hashed) => (true, hashed.clone()),
+            None => (false, self.dummy.clone())
+        };
+        let verified = tokio::task::spawn_blocking(move || hashed.verify(secret.as_str())).await?;
+        if known && verified {
+            Ok(user)
+        } else {
+            Err(anyhow!("invalid user or password"))
+        }
+    }
+}
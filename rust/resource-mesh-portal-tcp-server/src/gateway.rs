@@ -0,0 +1,550 @@
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Semaphore};
+
+use resource_mesh_portal_api_server::{message, Message, MuxerHandle, Request, Response, Router};
+use resource_mesh_portal_api_server::admin::RouteEntry;
+use resource_mesh_portal_api_server::policy::Policy;
+use resource_mesh_portal_api_server::router::not_found;
+use resource_mesh_portal_api_server::throttle::Buckets;
+use resource_mesh_portal_serde::version::latest::config::{Config, RateLimit};
+use resource_mesh_portal_serde::version::latest::delivery::ResponseEntity;
+use resource_mesh_portal_serde::version::latest::fail::{mesh, Fail};
+use resource_mesh_portal_serde::version::latest::frame::PrimitiveFrame;
+use resource_mesh_portal_serde::version::latest::id::Identifier;
+use resource_mesh_portal_serde::version::latest::messaging::{ExchangeId, ExchangeKind, IdempotencyKey};
+use resource_mesh_portal_serde::version::latest::operation::{ExtOperation, Operation};
+use resource_mesh_portal_tcp_common::{PrimitiveFrameReader, PrimitiveFrameWriter};
+use resource_mesh_portal_tcp_common::auth::Credentials;
+
+use crate::auth::Authenticators;
+
+// the frames two gateways exchange over a link.  the gateway that connects opens with Hello
+// carrying its name and then authenticates as that name, the one that listens answers with its
+// own Hello or with Close when it refuses the link.  after that requests & responses flow both
+// ways with their ExchangeId untouched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LinkFrame {
+    Hello(String),
+    Request(LinkRequest),
+    Response(LinkResponse),
+    Close
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkRequest {
+    pub to: Identifier,
+    pub from: Identifier,
+    pub operation: ExtOperation,
+    pub kind: ExchangeKind,
+    pub idempotency_key: Option<IdempotencyKey>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkResponse {
+    pub to: Identifier,
+    pub from: Identifier,
+    pub exchange_id: ExchangeId,
+    pub signal: ResponseEntity
+}
+
+impl TryInto<PrimitiveFrame> for LinkFrame {
+    type Error = Error;
+
+    fn try_into(self) -> Result<PrimitiveFrame, Self::Error> {
+        Ok(PrimitiveFrame {
+            data: bincode::serialize(&self)?
+        })
+    }
+}
+
+impl TryFrom<PrimitiveFrame> for LinkFrame {
+    type Error = Error;
+
+    fn try_from(value: PrimitiveFrame) -> Result<Self, Self::Error> {
+        Ok(bincode::deserialize(value.data.as_slice())?)
+    }
+}
+
+struct GatewayState {
+    muxer: Option<MuxerHandle>,
+    links: HashMap<String,mpsc::Sender<LinkFrame>>,
+    routes: Vec<(String,String)>,
+    // (link, requester, exchange) of every request sent over a link that is owed a response, until its deadline
+    sent: HashMap<(String,Identifier,ExchangeId),Instant>
+}
+
+// links the muxers of two or more servers.  every route maps an address prefix to the
+// name of a linked gateway, the longest matching prefix wins and anything that
+// matches no route is left to the local router.  portals are addressed across
+// links by Address only, keys mean nothing outside of the server that issued them.
+// requests arriving over a link are throttled & checked against the policy as if
+// the linked gateway's name were the owner of the portal that sent them, and must come
+// from an address that is routed back over that same link.  a response arriving over a
+// link is only delivered if it answers a request that was sent over it
+#[derive(Clone)]
+pub struct Gateway {
+    pub name: String,
+    state: Arc<RwLock<GatewayState>>,
+    authenticators: Arc<Authenticators>,
+    credentials: Option<Arc<dyn Credentials>>,
+    policy: Arc<Policy>,
+    handshake_timeout: Duration,
+    max_pending_handshakes: usize,
+    logger: fn(message: &str)
+}
+
+impl Gateway {
+    pub fn new( name: String, logger: fn(message: &str) ) -> Self {
+        Self {
+            name,
+            state: Arc::new(RwLock::new(GatewayState {
+                muxer: Option::None,
+                links: HashMap::new(),
+                routes: vec![],
+                sent: HashMap::new()
+            })),
+            authenticators: Arc::new(Authenticators::new()),
+            credentials: Option::None,
+            policy: Arc::new(Policy::allow_all()),
+            handshake_timeout: Duration::from_secs(30),
+            max_pending_handshakes: 64,
+            logger
+        }
+    }
+
+    // how gateways that connect to this one must authenticate, a gateway without any refuses every link
+    pub fn with_authenticators( mut self, authenticators: Authenticators ) -> Self {
+        self.authenticators = Arc::new(authenticators);
+        self
+    }
+
+    // what this gateway authenticates with when it connects, the user must be the name of this gateway
+    pub fn with_credentials( mut self, credentials: Box<dyn Credentials> ) -> Self {
+        self.credentials = Option::Some(Arc::from(credentials));
+        self
+    }
+
+    pub fn with_policy( mut self, policy: Arc<Policy> ) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // Hello & auth from a gateway that connects to listen() must be done within this
+    pub fn with_handshake_timeout( mut self, timeout: Duration ) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    // connections to listen() beyond this many still in their handshake are refused
+    pub fn with_max_pending_handshakes( mut self, max: usize ) -> Self {
+        self.max_pending_handshakes = max;
+        self
+    }
+
+    pub fn with_route( self, prefix: &str, link: &str ) -> Self {
+        self.add_route(prefix, link);
+        self
+    }
+
+    pub fn add_route( &self, prefix: &str, link: &str ) {
+        let mut state = self.state.write().expect("expected gateway state lock");
+        state.routes.retain(|(existing,_)| existing != prefix);
+        state.routes.push((prefix.to_string(), link.to_string()));
+    }
+
+    // meant to be called from PortalServer::router_factory, messages for remote
+    // portals go over a link and everything else is handed to local
+    pub fn router( &self, muxer: MuxerHandle, local: Box<dyn Router> ) -> Box<dyn Router> {
+        self.state.write().expect("expected gateway state lock").muxer = Option::Some(muxer.clone());
+
+        let (tx, mut rx) = mpsc::channel(1024);
+        let gateway = self.clone();
+        tokio::spawn(async move {
+            while let Option::Some((link, message)) = rx.recv().await {
+                gateway.forward(&muxer, link, message).await;
+            }
+        });
+
+        Box::new(GatewayRouter {
+            gateway: self.clone(),
+            local,
+            tx
+        })
+    }
+
+    pub fn links( &self ) -> Vec<String> {
+        self.state.read().expect("expected gateway state lock").links.keys().cloned().collect()
+    }
+
+    pub fn routes( &self ) -> Vec<RouteEntry> {
+        let state = self.state.read().expect("expected gateway state lock");
+        state.routes.iter().map(|(prefix,link)| RouteEntry {
+            prefix: prefix.clone(),
+            target: link.clone(),
+            linked: state.links.contains_key(link)
+        }).collect()
+    }
+
+    pub async fn listen( &self, addr: &str ) -> Result<SocketAddr,Error> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let gateway = self.clone();
+        // one permit per handshake in progress
+        let pending = Arc::new(Semaphore::new(self.max_pending_handshakes));
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let permit = match pending.clone().try_acquire_owned() {
+                    Ok(permit) => permit,
+                    Err(_) => {
+                        (gateway.logger)("ERROR: too many pending gateway link handshakes, connection refused");
+                        continue;
+                    }
+                };
+                let gateway = gateway.clone();
+                tokio::spawn(async move {
+                    let (reader, writer) = stream.into_split();
+                    let result = tokio::time::timeout(gateway.handshake_timeout, gateway.accept(reader, writer)).await;
+                    drop(permit);
+                    match result {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(err)) => (gateway.logger)(format!("ERROR: gateway link handshake failed: {}", err).as_str()),
+                        Err(_) => (gateway.logger)(format!("ERROR: gateway link handshake did not complete within {} seconds", gateway.handshake_timeout.as_secs_f64()).as_str())
+                    }
+                });
+            }
+        });
+        Ok(local_addr)
+    }
+
+    // returns the name of the gateway on the other side
+    pub async fn connect( &self, addr: &str ) -> Result<String,Error> {
+        let stream = TcpStream::connect(addr).await?;
+        let (reader, writer) = stream.into_split();
+        self.attach(reader, writer).await
+    }
+
+    // the connecting side of a link, returns the name of the gateway on the other side
+    pub async fn attach<R,W>( &self, reader: R, writer: W ) -> Result<String,Error> where R: AsyncRead+Send+Unpin+'static, W: AsyncWrite+Send+Unpin+'static {
+        let mut reader = PrimitiveFrameReader::boxed(reader);
+        let mut writer = PrimitiveFrameWriter::boxed(writer);
+
+        let credentials = self.credentials.clone().ok_or(anyhow!("gateway '{}' has no credentials to link with", self.name))?;
+        writer.write_frame(LinkFrame::Hello(self.name.clone())).await?;
+        credentials.authenticate(&mut reader, &mut writer).await?;
+        let remote = match LinkFrame::try_from(reader.read().await?)? {
+            LinkFrame::Hello(name) => name,
+            LinkFrame::Close => return Err(anyhow!("linked gateway refused the link")),
+            _ => return Err(anyhow!("expected Hello from linked gateway"))
+        };
+
+        let (link_tx, link_rx) = mpsc::channel(1024);
+        self.register(remote.as_str(), &link_tx)?;
+        self.run(remote.clone(), reader, writer, link_tx, link_rx);
+        Ok(remote)
+    }
+
+    // the listening side of a link, returns the name the other side authenticated as
+    pub async fn accept<R,W>( &self, reader: R, writer: W ) -> Result<String,Error> where R: AsyncRead+Send+Unpin+'static, W: AsyncWrite+Send+Unpin+'static {
+        let mut reader = PrimitiveFrameReader::boxed(reader);
+        let mut writer = PrimitiveFrameWriter::boxed(writer);
+
+        let remote = match LinkFrame::try_from(reader.read().await?)? {
+            LinkFrame::Hello(name) => name,
+            _ => return Err(anyhow!("expected Hello from linking gateway"))
+        };
+        let (link_tx, link_rx) = mpsc::channel(1024);
+        let registered = match self.authenticators.auth(&mut reader, &mut writer).await {
+            Ok(user) if user == remote => self.register(remote.as_str(), &link_tx),
+            Ok(user) => Err(anyhow!("gateway authenticated as '{}' but said Hello as '{}'", user, remote)),
+            Err(err) => Err(anyhow!("gateway '{}' failed to authenticate: {}", remote, err))
+        };
+        if let Err(err) = registered {
+            writer.write_frame(LinkFrame::Close).await.unwrap_or_default();
+            return Err(err);
+        }
+        writer.write_frame(LinkFrame::Hello(self.name.clone())).await?;
+
+        self.run(remote.clone(), reader, writer, link_tx, link_rx);
+        Ok(remote)
+    }
+
+    // a live link is never replaced, the one already there must go first
+    fn register( &self, remote: &str, link_tx: &mpsc::Sender<LinkFrame> ) -> Result<(),Error> {
+        let mut state = self.state.write().expect("expected gateway state lock");
+        if state.links.get(remote).is_some_and(|tx| !tx.is_closed()) {
+            return Err(anyhow!("gateway '{}' is already linked to '{}'", self.name, remote));
+        }
+        state.links.insert(remote.to_string(), link_tx.clone());
+        (self.logger)(format!("INFO: gateway '{}' linked to '{}'", self.name, remote).as_str());
+        Ok(())
+    }
+
+    fn run( &self, remote: String, mut reader: PrimitiveFrameReader, mut writer: PrimitiveFrameWriter, link_tx: mpsc::Sender<LinkFrame>, mut link_rx: mpsc::Receiver<LinkFrame> ) {
+
+        {
+            let logger = self.logger;
+            tokio::spawn(async move {
+                while let Option::Some(frame) = link_rx.recv().await {
+                    let close = matches!(frame, LinkFrame::Close);
+                    if let Err(err) = writer.write_frame(frame).await {
+                        (logger)(format!("ERROR: gateway link write failed: {}", err).as_str());
+                        break;
+                    }
+                    if close {
+                        break;
+                    }
+                }
+            });
+        }
+
+        {
+            let gateway = self.clone();
+            let remote = remote.clone();
+            tokio::spawn(async move {
+                let mut buckets = gateway.policy.throttle.portal_buckets(&RateLimit::unlimited());
+                loop {
+                    let frame = match reader.read().await.and_then(LinkFrame::try_from) {
+                        Ok(frame) => frame,
+                        Err(_) => break
+                    };
+                    match frame {
+                        LinkFrame::Request(request) => gateway.deliver_request(request, remote.as_str(), &mut buckets, &link_tx).await,
+                        LinkFrame::Response(response) => gateway.deliver_response(response, remote.as_str()).await,
+                        LinkFrame::Hello(_) => {}
+                        LinkFrame::Close => break
+                    }
+                }
+                let mut state = gateway.state.write().expect("expected gateway state lock");
+                if state.links.get(&remote).is_some_and(|tx| tx.same_channel(&link_tx)) {
+                    state.links.remove(&remote);
+                    state.sent.retain(|(link,_,_),_| *link != remote);
+                }
+                (gateway.logger)(format!("INFO: gateway '{}' unlinked from '{}'", gateway.name, remote).as_str());
+            });
+        }
+    }
+
+    pub fn close( &self, link: &str ) {
+        if let Option::Some(tx) = self.state.write().expect("expected gateway state lock").links.remove(link) {
+            tx.try_send(LinkFrame::Close).unwrap_or_default();
+        }
+    }
+
+    fn route( &self, to: &Identifier ) -> Option<String> {
+        let address = match to {
+            Identifier::Address(address) => address,
+            Identifier::Key(_) => return Option::None
+        };
+        let state = self.state.read().expect("expected gateway state lock");
+        state.routes.iter()
+            .filter(|(prefix,_)| address.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix,_)| prefix.len())
+            .map(|(_,link)| link.clone())
+    }
+
+    fn link( &self, name: &str ) -> Option<mpsc::Sender<LinkFrame>> {
+        self.state.read().expect("expected gateway state lock").links.get(name).cloned()
+    }
+
+    fn muxer( &self ) -> Option<MuxerHandle> {
+        self.state.read().expect("expected gateway state lock").muxer.clone()
+    }
+
+    // the remote side can only answer an Address, so a local key is swapped for its address on the way out
+    async fn forward( &self, muxer: &MuxerHandle, link: String, message: message::inlet::Message ) {
+        let from = match &message {
+            Message::Request(request) => request.from.clone(),
+            Message::Response(response) => response.from.clone()
+        };
+        let info = muxer.lookup(from.clone()).await.ok().flatten();
+        let from = match from {
+            Identifier::Key(_) => match &info {
+                Option::Some(info) => Identifier::Address(info.address.clone()),
+                None => return
+            },
+            address => address
+        };
+
+        let frame = match message {
+            Message::Request(request) => {
+                let operation = match request.operation {
+                    Operation::Ext(operation) => operation,
+                    Operation::Resource(_) => {
+                        (self.logger)("WARN: resource operations cannot cross a gateway link");
+                        return;
+                    }
+                };
+                LinkFrame::Request(LinkRequest {
+                    to: request.to,
+                    from,
+                    operation,
+                    kind: request.kind,
+                    idempotency_key: request.idempotency_key
+                })
+            }
+            Message::Response(response) => LinkFrame::Response(LinkResponse {
+                to: response.to,
+                from,
+                exchange_id: response.exchange_id,
+                signal: response.signal
+            })
+        };
+
+        match self.link(link.as_str()) {
+            Some(tx) => {
+                if let LinkFrame::Request(LinkRequest{ from, kind: ExchangeKind::RequestResponse(exchange_id), .. }) = &frame {
+                    // the response is only let back in until the requester gives up on it
+                    let timeout = info.map(|info| info.config.response_timeout).unwrap_or_else(|| Config::default().response_timeout);
+                    let mut state = self.state.write().expect("expected gateway state lock");
+                    let now = Instant::now();
+                    state.sent.retain(|_,deadline| *deadline > now);
+                    state.sent.insert((link.clone(), from.clone(), exchange_id.clone()), now + Duration::from_secs(timeout));
+                }
+                if tx.send(frame).await.is_err() {
+                    (self.logger)(format!("ERROR: gateway link '{}' is closed", link).as_str());
+                }
+            }
+            None => {
+                (self.logger)(format!("WARN: no gateway link '{}', message dropped", link).as_str());
+                if let LinkFrame::Request(request) = frame {
+                    if let ExchangeKind::RequestResponse(exchange_id) = request.kind {
+                        let response = Response {
+                            to: request.from,
+                            from: request.to.clone(),
+                            exchange_id,
+                            signal: not_found(&request.to)
+                        };
+                        muxer.send(Message::Response(response)).await.unwrap_or_default();
+                    }
+                }
+            }
+        }
+    }
+
+    async fn deliver_request( &self, request: LinkRequest, link: &str, buckets: &mut Buckets, link_tx: &mpsc::Sender<LinkFrame> ) {
+        let muxer = match self.muxer() {
+            Some(muxer) => muxer,
+            None => return
+        };
+        let bytes = bincode::serialized_size(&request).unwrap_or_default();
+        if let Err(throttled) = self.policy.throttle.admit(buckets, link, bytes) {
+            Self::refuse(&request, link_tx, ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Throttled(throttled)))).await;
+            return;
+        }
+        // a key would let the other side reach portals by a name that was never routed to it
+        if let Identifier::Key(_) = &request.to {
+            (self.logger)(format!("WARN: request over link '{}' to a key refused", link).as_str());
+            Self::refuse(&request, link_tx, ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Error("only addresses can be reached over a gateway link".to_string())))).await;
+            return;
+        }
+        // the response goes to from, which must lead back over this link
+        let from = match &request.from {
+            Identifier::Address(address) if self.route(&request.from).as_deref() == Option::Some(link) => address.clone(),
+            Identifier::Address(address) => {
+                (self.logger)(format!("WARN: request over link '{}' from '{}' refused, it is not routed to that link", link, address).as_str());
+                Self::refuse(&request, link_tx, ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Error(format!("requests over link '{}' must come from an address routed to it", link))))).await;
+                return;
+            }
+            Identifier::Key(_) => {
+                (self.logger)(format!("WARN: request over link '{}' from a key refused", link).as_str());
+                Self::refuse(&request, link_tx, ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Error(format!("requests over link '{}' must come from an address routed to it", link))))).await;
+                return;
+            }
+        };
+        match muxer.lookup(request.to.clone()).await {
+            Ok(Option::Some(info)) => {
+                let operation = Operation::Ext(request.operation.clone());
+                if let Err(forbidden) = self.policy.evaluate_link(link, &from, &request.to, Option::Some(&info.address), &operation) {
+                    (self.logger)(format!("WARN: {} from '{}' over link '{}' to '{}' forbidden by policy", forbidden.operation, forbidden.from, link, forbidden.to).as_str());
+                    Self::refuse(&request, link_tx, ResponseEntity::Fail(Fail::Mesh(mesh::Fail::Forbidden(forbidden)))).await;
+                    return;
+                }
+                let request = Request {
+                    to: request.to,
+                    from: request.from,
+                    operation: request.operation,
+                    kind: request.kind,
+                    idempotency_key: request.idempotency_key
+                };
+                muxer.send(Message::Request(request)).await.unwrap_or_default();
+            }
+            Ok(Option::None) => {
+                Self::refuse(&request, link_tx, not_found(&request.to)).await;
+            }
+            Err(_) => {}
+        }
+    }
+
+    // answers a request over the link it came from without it ever reaching a portal
+    async fn refuse( request: &LinkRequest, link_tx: &mpsc::Sender<LinkFrame>, signal: ResponseEntity ) {
+        if let ExchangeKind::RequestResponse(exchange_id) = &request.kind {
+            let response = LinkResponse {
+                to: request.from.clone(),
+                from: request.to.clone(),
+                exchange_id: exchange_id.clone(),
+                signal
+            };
+            link_tx.send(LinkFrame::Response(response)).await.unwrap_or_default();
+        }
+    }
+
+    async fn deliver_response( &self, response: LinkResponse, link: &str ) {
+        let requested = {
+            let mut state = self.state.write().expect("expected gateway state lock");
+            let now = Instant::now();
+            state.sent.retain(|_,deadline| *deadline > now);
+            state.sent.remove(&(link.to_string(), response.to.clone(), response.exchange_id.clone())).is_some()
+        };
+        if !requested {
+            (self.logger)(format!("WARN: dropped response for exchange '{}' over link '{}', no such request is waiting for it", response.exchange_id, link).as_str());
+            return;
+        }
+        if let Option::Some(muxer) = self.muxer() {
+            let response = Response {
+                to: response.to,
+                from: response.from,
+                exchange_id: response.exchange_id,
+                signal: response.signal
+            };
+            muxer.send(Message::Response(response)).await.unwrap_or_default();
+        }
+    }
+}
+
+pub struct GatewayRouter {
+    gateway: Gateway,
+    local: Box<dyn Router>,
+    tx: mpsc::Sender<(String,message::inlet::Message)>
+}
+
+impl Router for GatewayRouter {
+    fn route( &self, message: message::inlet::Message ) {
+        match self.gateway.route(&message.to()) {
+            Some(link) => {
+                if self.tx.try_send((link, message)).is_err() {
+                    (self.gateway.logger)("ERROR: gateway queue is full, message dropped");
+                }
+            }
+            None => self.local.route(message)
+        }
+    }
+
+    fn logger( &self, message: &str ) {
+        (self.gateway.logger)(message)
+    }
+
+    fn routes( &self ) -> Vec<RouteEntry> {
+        let mut routes = self.gateway.routes();
+        routes.append(&mut self.local.routes());
+        routes
+    }
+}
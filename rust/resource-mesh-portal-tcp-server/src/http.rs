@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Error;
+use hyper::{Body, Server, StatusCode};
+use hyper::body::HttpBody;
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use resource_mesh_portal_api_server::{host, MuxerHandle, Request, Response};
+use resource_mesh_portal_serde::version::latest::bin::Bin;
+use resource_mesh_portal_serde::version::latest::delivery::{Entity, ResponseEntity};
+use resource_mesh_portal_serde::version::latest::fail::{resource, Fail, Standard};
+use resource_mesh_portal_serde::version::latest::http::{HttpRequest, HttpResponse};
+use resource_mesh_portal_serde::version::latest::id::{Address, Identifier};
+use resource_mesh_portal_serde::version::latest::operation::{ExtOperation, Operation};
+use resource_mesh_portal_tcp_common::default_max_frame_size;
+
+use crate::{Call, PortalServerHandle};
+
+#[derive(Clone)]
+enum Exchanger {
+    Muxer(MuxerHandle),
+    Server(mpsc::Sender<Call>)
+}
+
+impl Exchanger {
+    async fn exchange( &self, request: Request<Operation> ) -> Result<Response,Error> {
+        match self {
+            Exchanger::Muxer(muxer) => muxer.exchange(request).await?.await,
+            Exchanger::Server(server) => {
+                let (tx,rx) = oneshot::channel();
+                server.send(Call::Exchange{ request, tx }).await.map_err(|_| anyhow!("portal server has shutdown"))?;
+                rx.await.map_err(|_| anyhow!("host exchange abandoned by the portal server"))
+            }
+        }
+    }
+}
+
+// serves http on behalf of portals.  a request is taken to the portal its Host header is
+// mapped to or else to the portal of the longest path prefix it matches, which is stripped
+// from the path the portal sees.  the portal's HttpResponse is what the client gets, unless
+// it answers with anything else or fails (502) or takes longer than timeout or its response_timeout (504).  a request
+// body larger than max_body is refused (413) before it reaches any portal
+#[derive(Clone)]
+pub struct HttpGateway {
+    exchanger: Exchanger,
+    hosts: HashMap<String,Address>,
+    prefixes: Vec<(String,Address)>,
+    timeout: Duration,
+    max_body: usize,
+    logger: fn(message: &str)
+}
+
+// the gateway as it is served, it stops on shutdown()
+pub struct HttpGatewayHandle {
+    addr: SocketAddr,
+    shutdown_tx: oneshot::Sender<()>,
+    join: JoinHandle<()>
+}
+
+impl HttpGatewayHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // no more connections are taken, resolves once the requests in flight are answered
+    pub async fn shutdown(self) -> Result<(),Error> {
+        self.shutdown_tx.send(()).unwrap_or_default();
+        Ok(self.join.await?)
+    }
+}
+
+impl HttpGateway {
+    pub fn new( muxer: MuxerHandle, logger: fn(message: &str) ) -> Self {
+        Self::new_with_exchanger(Exchanger::Muxer(muxer), logger)
+    }
+
+    // exchanges go through the server's Call api rather than a MuxerHandle
+    pub fn new_with_server( server: &PortalServerHandle, logger: fn(message: &str) ) -> Self {
+        Self::new_with_exchanger(Exchanger::Server(server.call_tx()), logger)
+    }
+
+    fn new_with_exchanger( exchanger: Exchanger, logger: fn(message: &str) ) -> Self {
+        Self {
+            exchanger,
+            hosts: HashMap::new(),
+            prefixes: vec![],
+            timeout: Duration::from_secs(30),
+            // no larger than the bin a portal takes by default
+            max_body: default_max_frame_size(),
+            logger
+        }
+    }
+
+    // host is matched without its port and regardless of case
+    pub fn with_host( mut self, host: &str, address: &str ) -> Self {
+        self.hosts.insert(host.to_lowercase(), address.to_string());
+        self
+    }
+
+    // prefix matches whole path segments only, '/api' takes '/api/users' but not '/apis'
+    pub fn with_prefix( mut self, prefix: &str, address: &str ) -> Self {
+        let prefix = format!("/{}", prefix.trim_matches('/'));
+        self.prefixes.retain(|(existing,_)| *existing != prefix);
+        self.prefixes.push((prefix, address.to_string()));
+        self
+    }
+
+    pub fn with_timeout( mut self, timeout: Duration ) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_body( mut self, max_body: usize ) -> Self {
+        self.max_body = max_body;
+        self
+    }
+
+    pub async fn serve( self, addr: &str ) -> Result<HttpGatewayHandle,Error> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let gateway = Arc::new(self);
+        let logger = gateway.logger;
+        let service = make_service_fn(move |stream: &AddrStream| {
+            let gateway = gateway.clone();
+            let remote = stream.remote_addr();
+            async move {
+                Ok::<_,Infallible>(service_fn(move |request| {
+                    let gateway = gateway.clone();
+                    async move {
+                        Ok::<_,Infallible>(gateway.handle(request, Option::Some(remote)).await)
+                    }
+                }))
+            }
+        });
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)?.serve(service).with_graceful_shutdown(async move {
+            shutdown_rx.await.unwrap_or_default();
+        });
+        let join = tokio::spawn(async move {
+            if let Err(err) = server.await {
+                (logger)(format!("ERROR: http gateway: {}", err).as_str());
+            }
+        });
+        Ok(HttpGatewayHandle {
+            addr: local_addr,
+            shutdown_tx,
+            join
+        })
+    }
+
+    pub async fn handle( &self, request: hyper::Request<Body>, remote: Option<SocketAddr> ) -> hyper::Response<Body> {
+        let (address, path) = match self.resolve(&request) {
+            Some(resolved) => resolved,
+            None => return status(StatusCode::NOT_FOUND)
+        };
+
+        let (parts, body) = request.into_parts();
+        let length = parts.headers.get(hyper::header::CONTENT_LENGTH).and_then(|length| length.to_str().ok()?.parse::<usize>().ok());
+        if length.unwrap_or_default() > self.max_body {
+            return status(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        let body = match read_body(body, self.max_body).await {
+            Ok(Some(body)) => body,
+            Ok(None) => return status(StatusCode::PAYLOAD_TOO_LARGE),
+            Err(err) => {
+                (self.logger)(format!("ERROR: http gateway could not read request body: {}", err).as_str());
+                return status(StatusCode::BAD_REQUEST);
+            }
+        };
+
+        // repeated headers are folded into one, cookies are the exception that fold with "; "
+        let mut headers = HashMap::new();
+        for (name, value) in parts.headers.iter() {
+            if let Ok(value) = value.to_str() {
+                let separator = if *name == hyper::header::COOKIE { "; " } else { ", " };
+                headers.entry(name.as_str().to_string()).and_modify(|existing: &mut String| {
+                    existing.push_str(separator);
+                    existing.push_str(value);
+                }).or_insert(value.to_string());
+            }
+        }
+        // proxies in front of the gateway are kept in the chain, the peer goes last
+        if let Some(remote) = remote {
+            headers.entry("x-forwarded-for".to_string()).and_modify(|existing: &mut String| {
+                existing.push_str(", ");
+                existing.push_str(remote.ip().to_string().as_str());
+            }).or_insert(remote.ip().to_string());
+        }
+
+        let http = HttpRequest {
+            method: parts.method.as_str().to_string(),
+            headers,
+            path,
+            body: if body.is_empty() { Option::None } else { Option::Some(Bin::Raw(Arc::new(body))) }
+        };
+        let request = Request::new(Identifier::Address(address.clone()), host(), Operation::Ext(ExtOperation::Http(http)));
+
+        match tokio::time::timeout(self.timeout, self.exchanger.exchange(request)).await {
+            Ok(Ok(response)) => match response.signal {
+                ResponseEntity::Ok(Entity::HttpResponse(response)) => match convert(response) {
+                    Ok(response) => response,
+                    Err(err) => {
+                        (self.logger)(format!("ERROR: http gateway got an unusable response from {}: {}", address, err).as_str());
+                        status(StatusCode::BAD_GATEWAY)
+                    }
+                },
+                ResponseEntity::Ok(_) => {
+                    (self.logger)(format!("ERROR: http gateway expected an HttpResponse from {}", address).as_str());
+                    status(StatusCode::BAD_GATEWAY)
+                }
+                ResponseEntity::Error(message) => {
+                    (self.logger)(format!("ERROR: http gateway request to {} failed: {}", address, message).as_str());
+                    status(StatusCode::BAD_GATEWAY)
+                }
+                ResponseEntity::Fail(Fail::Resource(resource::Fail::Standard(Standard::Timeout(timeout)))) => {
+                    (self.logger)(format!("ERROR: http gateway request to {} timed out: {}", address, timeout.message).as_str());
+                    status(StatusCode::GATEWAY_TIMEOUT)
+                }
+                ResponseEntity::Fail(fail) => {
+                    (self.logger)(format!("ERROR: http gateway request to {} failed: {:?}", address, fail).as_str());
+                    status(StatusCode::BAD_GATEWAY)
+                }
+            },
+            Ok(Err(err)) => {
+                (self.logger)(format!("ERROR: http gateway request to {} failed: {}", address, err).as_str());
+                status(StatusCode::BAD_GATEWAY)
+            }
+            Err(_) => status(StatusCode::GATEWAY_TIMEOUT)
+        }
+    }
+
+    // the portal address & the path it is to see, query included
+    fn resolve( &self, request: &hyper::Request<Body> ) -> Option<(Address,String)> {
+        let path_and_query = request.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/").to_string();
+
+        let host = request.headers().get(hyper::header::HOST).and_then(|host| host.to_str().ok())
+            .or(request.uri().host());
+        if let Some(host) = host {
+            let host = match host.rsplit_once(':') {
+                Some((name,port)) if !port.ends_with(']') => name,
+                _ => host
+            }.to_lowercase();
+            if let Some(address) = self.hosts.get(&host) {
+                return Option::Some((address.clone(), path_and_query));
+            }
+        }
+
+        let path = request.uri().path();
+        let (prefix, address) = self.prefixes.iter()
+            .filter(|(prefix,_)| prefix == "/" || path == prefix || path.starts_with(format!("{}/", prefix).as_str()))
+            .max_by_key(|(prefix,_)| prefix.len())?;
+        let rest = if prefix == "/" { path_and_query.as_str() } else { &path_and_query[prefix.len()..] };
+        let rest = if rest.starts_with('/') { rest.to_string() } else { format!("/{}", rest) };
+        Option::Some((address.clone(), rest))
+    }
+}
+
+fn status( code: StatusCode ) -> hyper::Response<Body> {
+    let mut response = hyper::Response::new(Body::empty());
+    *response.status_mut() = code;
+    response
+}
+
+// at most max bytes of body, None if there is more than that
+async fn read_body( mut body: Body, max: usize ) -> Result<Option<Vec<u8>>,hyper::Error> {
+    let mut read = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if read.len() + chunk.len() > max {
+            return Ok(Option::None);
+        }
+        read.extend_from_slice(&chunk);
+    }
+    Ok(Option::Some(read))
+}
+
+// headers that describe the connection to the portal rather than the response, the
+// connection to the http client has its own & hyper sets them from the body it serves
+const HOP_BY_HOP: [&str; 9] = ["connection", "keep-alive", "proxy-authenticate", "proxy-authorization", "te", "trailer", "transfer-encoding", "upgrade", "content-length"];
+
+fn convert( response: HttpResponse ) -> Result<hyper::Response<Body>,Error> {
+    let body = match response.body {
+        Some(Bin::Raw(raw)) => Body::from(raw.to_vec()),
+        Some(Bin::Src(_)) => return Err(anyhow!("a bin src body cannot be served")),
+        None => Body::empty()
+    };
+    let mut converted = hyper::Response::new(body);
+    *converted.status_mut() = StatusCode::from_u16(u16::try_from(response.code)?)?;
+    // so are the headers the Connection header names
+    let hops: Vec<String> = response.headers.iter()
+        .filter(|(name,_)| name.eq_ignore_ascii_case("connection"))
+        .flat_map(|(_,value)| value.split(',').map(|name| name.trim().to_lowercase()).collect::<Vec<String>>())
+        .collect();
+    for (name, value) in response.headers {
+        let lower = name.to_lowercase();
+        if HOP_BY_HOP.contains(&lower.as_str()) || hops.contains(&lower) {
+            continue;
+        }
+        converted.headers_mut().insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value.as_str())?);
+    }
+    Ok(converted)
+}
@@ -4,39 +4,55 @@ extern crate async_trait;
 #[macro_use]
 extern crate anyhow;
 
-#[macro_use]
-extern crate strum_macros;
-
 
-use std::convert::{TryFrom, TryInto};
-use std::sync::Arc;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use anyhow::Error;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
-use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
-use tokio::sync::{mpsc, oneshot, broadcast, Mutex};
-use tokio::sync::mpsc::error::SendTimeoutError;
-
-use resource_mesh_portal_api_server::{Message, MuxCall, Portal, PortalMuxer, Router};
-use resource_mesh_portal_tcp_common::{FrameReader, FrameWriter, PrimitiveFrameReader, PrimitiveFrameWriter};
-use resource_mesh_portal_serde::version::v0_0_1::config::Info;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot, broadcast, Notify, Semaphore};
+use tokio::task::JoinHandle;
+
+use resource_mesh_portal_api_server::{Message, MuxerHandle, Portal, PortalMuxer, Request, Response, Router};
+use resource_mesh_portal_api_server::admin::{PortalSnapshot, RoutingState};
+use resource_mesh_portal_api_server::event::PortalEvent;
+use resource_mesh_portal_api_server::policy::Policy;
+use resource_mesh_portal_api_server::state::{MemStateStore, StateStore};
+use resource_mesh_portal_metrics as metrics;
+use resource_mesh_portal_tcp_common::{split_flavor, FrameReader, FrameTooLarge, FrameWriter, PrimitiveFrameReader, PrimitiveFrameWriter};
+use resource_mesh_portal_tcp_common::tls::{PeerIdentity, TlsServer, TlsServerConfig};
+use resource_mesh_portal_serde::version::latest::config::Info;
 use tokio::runtime::Runtime;
 use std::thread;
 use resource_mesh_portal_serde::version::latest::frame::CloseReason;
+use resource_mesh_portal_serde::version::latest::id::Identifier;
 use resource_mesh_portal_serde::version::latest::resource::Status;
 use resource_mesh_portal_serde::version::latest::operation::Operation;
 use resource_mesh_portal_serde::version::latest::portal::{inlet, outlet};
 use resource_mesh_portal_serde::version::latest::log::Log;
 
+pub mod admin;
+pub mod auth;
+pub mod gateway;
+pub mod http;
+
+#[cfg(unix)]
+pub mod uds;
+
 #[derive(Clone,strum_macros::Display)]
+#[allow(clippy::large_enum_variant)]
 pub enum Event {
     Status(Status),
     ClientConnected,
     FlavorNegotiation(EventResult<String>),
     Authorization(EventResult<String>),
     Info(EventResult<Info>),
+    HandshakeFailed(String),
+    // everything the muxer reports about its portals
+    Portal(PortalEvent),
     Shutdown,
 }
 
@@ -48,130 +64,438 @@ pub enum EventResult<E>{
 
 pub enum Call {
     ListenEvents(oneshot::Sender<broadcast::Receiver<Event>>),
+    // the Status last broadcast, so a listener that subscribed late knows whether it missed Ready
+    Status(oneshot::Sender<Status>),
+    // messages from the host itself, requests are stamped from resource_mesh_portal_api_server::host()
     InjectMessage(Message<Operation>),
+    // a RequestResponse exchange from the host, tx receives the response
+    Exchange{ request: Request<Operation>, tx: oneshot::Sender<Response> },
+    Portals(oneshot::Sender<Vec<PortalSnapshot>>),
+    Portal{ id: Identifier, tx: oneshot::Sender<Option<PortalSnapshot>> },
+    ClosePortal{ id: Identifier, reason: CloseReason, tx: oneshot::Sender<bool> },
+    Routing(oneshot::Sender<RoutingState>),
     Shutdown
 }
 
-struct Alive {
-    pub alive: bool
-}
-
-impl Alive {
-    pub fn new() -> Self {
-        Self {
-            alive: true
-        }
-    }
+#[derive(Clone)]
+pub(crate) enum Endpoint {
+    Tcp(String),
+    #[cfg(unix)]
+    Uds(uds::UdsServerConfig)
 }
 
 pub struct PortalTcpServer {
-    port: usize,
+    endpoint: Endpoint,
     server: Arc<dyn PortalServer>,
     broadcaster_tx: broadcast::Sender<Event>,
+    // never read, it keeps call_rx open for as long as the server runs
+    #[allow(dead_code)]
     call_tx: mpsc::Sender<Call>,
-    mux_tx: mpsc::Sender<MuxCall>,
-    alive: Arc<Mutex<Alive>>
+    muxer: MuxerHandle,
+    status: Arc<RwLock<Status>>,
+    // notified once by Call::Shutdown, the accept loop stops listening when it is
+    stop: Arc<Notify>,
+    tls: Option<TlsServer>,
+    acceptor: PortalAcceptor
 }
 
 impl PortalTcpServer {
 
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(port: usize, server: Box<dyn PortalServer>) -> mpsc::Sender<Call> {
+        Self::create(Endpoint::Tcp(format!("localhost:{}", port)), server, Option::None)
+    }
+
+    pub fn new_tls(port: usize, server: Box<dyn PortalServer>, tls: TlsServerConfig) -> Result<mpsc::Sender<Call>,Error> {
+        let tls = TlsServer::new(&tls)?;
+        Ok(Self::create(Endpoint::Tcp(format!("localhost:{}", port)), server, Option::Some(tls)))
+    }
+
+    // unlike new() the server built here runs in the caller's runtime
+    pub fn builder(server: Box<dyn PortalServer>) -> PortalTcpServerBuilder {
+        PortalTcpServerBuilder::new(server)
+    }
+
+    pub(crate) fn create(endpoint: Endpoint, server: Box<dyn PortalServer>, tls: Option<TlsServer>) -> mpsc::Sender<Call> {
+        let (server, call_tx, call_rx) = Self::assemble(endpoint, server, tls);
+
+        thread::spawn( || {
+            let rt = Runtime::new().unwrap();
+            rt.block_on(server.run(call_rx, Option::None));
+        });
+
+        call_tx
+    }
+
+    fn assemble(endpoint: Endpoint, server: Box<dyn PortalServer>, tls: Option<TlsServer>) -> (Self, mpsc::Sender<Call>, mpsc::Receiver<Call>) {
         let server:Arc<dyn PortalServer> = server.into();
         let (broadcaster_tx,_) = broadcast::channel(32);
-        let (call_tx,mut call_rx) = mpsc::channel(1024 );
+        let (call_tx,call_rx) = mpsc::channel(1024 );
 
-        let (mux_tx, mux_rx) = mpsc::channel(1024 );
-        let router = server.router_factory(mux_tx.clone());
+        let router_server = server.clone();
+        let muxer = PortalMuxer::new_with_state(move |muxer| router_server.router_factory(muxer), server.state_store() );
 
-        PortalMuxer::new(mux_tx.clone(),mux_rx,router);
+        let acceptor = PortalAcceptor::new(server.clone(), muxer.clone(), broadcaster_tx.clone() );
 
         let server = Self {
-            port,
+            endpoint,
             server,
             broadcaster_tx,
             call_tx: call_tx.clone(),
-            mux_tx: mux_tx.clone(),
-            alive: Arc::new(Mutex::new(Alive::new()))
+            muxer,
+            status: Arc::new(RwLock::new(Status::Unknown)),
+            stop: Arc::new(Notify::new()),
+            tls,
+            acceptor
         };
 
+        (server, call_tx, call_rx)
+    }
 
-        thread::spawn( || {
-            let rt = Runtime::new().unwrap();
-            rt.block_on(async {
-
-                server.broadcaster_tx.send( Event::Status(Status::Initializing) ).unwrap_or_default();
-                {
-                    let port = server.port.clone();
-                    let broadcaster_tx = server.broadcaster_tx.clone();
-                    let alive = server.alive.clone();
-                    tokio::spawn(async move {
-                        tokio::time::sleep(Duration::from_secs(0)).await;
-                        while let Option::Some(call) = call_rx.recv().await {
-                            match call {
-                                Call::InjectMessage(_) => {}
-                                Call::ListenEvents(tx) => {
-                                    tx.send( broadcaster_tx.subscribe() );
-                                },
-                                Call::Shutdown  => {
-                                    broadcaster_tx.send(Event::Shutdown).unwrap_or_default();
-                                    alive.lock().await.alive = false;
-                                    match std::net::TcpStream::connect(format!("localhost:{}", port)) {
-                                        Ok(_) => {}
-                                        Err(_) => {}
+    // a listener that is already bound is used as is, otherwise the endpoint is bound here
+    async fn run(self, call_rx: mpsc::Receiver<Call>, listener: Option<TcpListener>) {
+        let mut call_rx = call_rx;
+        self.report(Status::Initializing);
+        {
+            let broadcaster_tx = self.broadcaster_tx.clone();
+            let mut events = self.muxer.events();
+            tokio::spawn(async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            broadcaster_tx.send( Event::Portal(event) ).unwrap_or_default();
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break
+                    }
+                }
+            });
+        }
+        {
+            let broadcaster_tx = self.broadcaster_tx.clone();
+            let stop = self.stop.clone();
+            let muxer = self.muxer.clone();
+            let status = self.status.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(0)).await;
+                while let Option::Some(call) = call_rx.recv().await {
+                    match call {
+                        Call::InjectMessage(Message::Request(request)) => {
+                            muxer.inject(request).await.unwrap_or_default();
+                        }
+                        Call::InjectMessage(message) => {
+                            muxer.route(message).await.unwrap_or_default();
+                        }
+                        Call::Exchange{ request, mut tx } => {
+                            if let Ok(response) = muxer.exchange(request).await {
+                                // the response may be a while, other calls need not wait for it.
+                                // the muxer answers with a Timeout once the response_timeout passes and
+                                // a caller that gives up first lets the muxer forget the exchange
+                                tokio::spawn(async move {
+                                    tokio::select! {
+                                        response = response => {
+                                            if let Ok(response) = response {
+                                                tx.send(response).unwrap_or_default();
+                                            }
+                                        }
+                                        _ = tx.closed() => {}
                                     }
-                                    return;
-                                }
+                                });
                             }
                         }
-                    });
+                        Call::ListenEvents(tx) => {
+                            tx.send( broadcaster_tx.subscribe() ).ok();
+                        },
+                        Call::Status(tx) => {
+                            tx.send( status.read().expect("expected status read lock").clone() ).ok();
+                        }
+                        Call::Portals(tx) => {
+                            if let Ok(portals) = muxer.portals().await {
+                                tx.send(portals).unwrap_or_default();
+                            }
+                        }
+                        Call::Portal{ id, tx } => {
+                            if let Ok(portal) = muxer.portal(id).await {
+                                tx.send(portal).unwrap_or_default();
+                            }
+                        }
+                        Call::ClosePortal{ id, reason, tx } => {
+                            if let Ok(closed) = muxer.close(id, reason).await {
+                                tx.send(closed).unwrap_or_default();
+                            }
+                        }
+                        Call::Routing(tx) => {
+                            if let Ok(routing) = muxer.routing().await {
+                                tx.send(routing).unwrap_or_default();
+                            }
+                        }
+                        Call::Shutdown  => {
+                            // the listener stops and start() takes care of draining the portals
+                            broadcaster_tx.send(Event::Shutdown).unwrap_or_default();
+                            stop.notify_one();
+                            return;
+                        }
+                    }
                 }
-
-                server.start().await;
             });
-        });
+        }
 
-        call_tx
+        match listener {
+            Some(listener) => self.serve_tcp(listener).await,
+            None => self.start().await
+        }
     }
 
-
     async fn start(self) {
-            let addr = format!("localhost:{}", self.port);
-            match std::net::TcpListener::bind(addr.clone()) {
-                Ok(std_listener) => {
-                    tokio::time::sleep(Duration::from_secs(0)).await;
-                    let listener = TcpListener::from_std(std_listener).unwrap();
-                    self.broadcaster_tx.send( Event::Status(Status::Ready) ).unwrap_or_default();
-                    tokio::time::sleep(Duration::from_secs(0)).await;
-                    while let Ok((stream, _)) = listener.accept().await {
-                        {
-                            if !self.alive.lock().await.alive.clone() {
-                                (self.server.logger())("server reached final shutdown");
-                                break;
-                            }
+        match self.endpoint.clone() {
+            Endpoint::Tcp(addr) => self.start_tcp(addr).await,
+            #[cfg(unix)]
+            Endpoint::Uds(config) => self.start_uds(config).await
+        }
+    }
+
+    async fn start_tcp(self, addr: String) {
+        match TcpListener::bind(addr.clone()).await {
+            Ok(listener) => {
+                self.serve_tcp(listener).await;
+            }
+            Err(error) => {
+                let message = format!("FATAL: could not setup TcpListener {}", error);
+                (self.server.logger())(message.as_str());
+                self.report(Status::Panic(message));
+            }
+        }
+    }
+
+    async fn serve_tcp(self, listener: TcpListener) {
+        self.report(Status::Ready);
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => break
+                },
+                _ = self.stop.notified() => {
+                    (self.server.logger())("server reached final shutdown");
+                    break;
+                }
+            };
+            self.broadcaster_tx.send( Event::ClientConnected ).unwrap_or_default();
+
+            // every handshake runs on its own so a stalled client holds up nobody else
+            let acceptor = self.acceptor.clone();
+            let tls = self.tls.clone();
+            tokio::spawn(async move {
+                acceptor.handshake_with(async move {
+                    match tls {
+                        None => {
+                            let (reader, writer) = stream.into_split();
+                            Ok((PrimitiveFrameReader::boxed(reader), PrimitiveFrameWriter::boxed(writer), Option::None))
                         }
-                        self.broadcaster_tx.send( Event::ClientConnected ).unwrap_or_default();
-                        (&self).handle(stream).await;
+                        Some(tls) => tls.accept(stream).await.map_err(|err| anyhow!("ERROR: tls handshake failed: {}", err))
                     }
-                    self.broadcaster_tx.send( Event::Status(Status::Done) ).unwrap_or_default();
-                }
-                Err(error) => {
-                    let message = format!("FATAL: could not setup TcpListener {}", error);
-                    (self.server.logger())(message.as_str());
-                    self.broadcaster_tx.send( Event::Status(Status::Panic(message)) ).unwrap_or_default();
-                }
-            }
+                }).await.unwrap_or_default();
+            });
+        }
+        drop(listener);
+        self.drain().await;
+    }
+
+    // the listener is already gone, in flight exchanges get until the drain timeout
+    // to complete and then every portal is closed before the final Status::Done
+    pub(crate) async fn drain(&self) {
+        if let Err(err) = self.muxer.drain(self.server.drain_timeout()).await {
+            (self.server.logger())(format!("ERROR: portal muxer drain: {}", err).as_str());
+        }
+        self.report(Status::Done);
+    }
+
+    pub(crate) fn report(&self, status: Status) {
+        *self.status.write().expect("expected status write lock") = status.clone();
+        self.broadcaster_tx.send( Event::Status(status) ).unwrap_or_default();
+    }
+}
+
+pub struct PortalTcpServerBuilder {
+    addr: SocketAddr,
+    server: Box<dyn PortalServer>,
+    tls: Option<TlsServerConfig>
+}
+
+impl PortalTcpServerBuilder {
+    pub fn new(server: Box<dyn PortalServer>) -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            server,
+            tls: Option::None
+        }
+    }
 
+    // any address will do including [::] or 0.0.0.0, port 0 lets the os pick a free port
+    pub fn with_addr(mut self, addr: SocketAddr) -> Self {
+        self.addr = addr;
+        self
     }
 
-    async fn handle( &self, stream: TcpStream ) -> Result<(),Error> {
-        let (reader, writer) = stream.into_split();
-        let mut reader = PrimitiveFrameReader::new(reader);
-        let mut writer = PrimitiveFrameWriter::new(writer);
+    pub fn with_tls(mut self, tls: TlsServerConfig) -> Self {
+        self.tls = Option::Some(tls);
+        self
+    }
+
+    // binds right away so the returned handle knows the address and a bind error surfaces here
+    pub async fn start(self) -> Result<PortalServerHandle,Error> {
+        let tls = match &self.tls {
+            Some(tls) => Option::Some(TlsServer::new(tls)?),
+            None => Option::None
+        };
+        let listener = TcpListener::bind(self.addr).await?;
+        let addr = listener.local_addr()?;
+        let (server, call_tx, call_rx) = PortalTcpServer::assemble(Endpoint::Tcp(addr.to_string()), self.server, tls);
+        let join = tokio::spawn(server.run(call_rx, Option::Some(listener)));
+        Ok(PortalServerHandle {
+            addr,
+            call_tx,
+            join
+        })
+    }
+}
 
-        let flavor = reader.read_string().await?;
+pub struct PortalServerHandle {
+    addr: SocketAddr,
+    call_tx: mpsc::Sender<Call>,
+    join: JoinHandle<()>
+}
+
+impl PortalServerHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn call_tx(&self) -> mpsc::Sender<Call> {
+        self.call_tx.clone()
+    }
+
+    pub async fn send(&self, call: Call) -> Result<(),Error> {
+        self.call_tx.send(call).await.map_err(|_| anyhow!("portal server has shutdown"))
+    }
+
+    pub async fn listen_events(&self) -> Result<broadcast::Receiver<Event>,Error> {
+        let (tx,rx) = oneshot::channel();
+        self.send(Call::ListenEvents(tx)).await?;
+        Ok(rx.await?)
+    }
+
+    pub async fn inject(&self, message: Message<Operation>) -> Result<(),Error> {
+        self.send(Call::InjectMessage(message)).await
+    }
+
+    // the returned future resolves to the response, wrap it in a timeout since a portal may never answer
+    pub async fn exchange(&self, request: Request<Operation>) -> Result<impl Future<Output=Result<Response,Error>>,Error> {
+        let (tx,rx) = oneshot::channel();
+        self.send(Call::Exchange{ request, tx }).await?;
+        Ok(async move {
+            rx.await.map_err(|_| anyhow!("host exchange abandoned, the portal server has shutdown"))
+        })
+    }
+
+    pub async fn shutdown(&self) -> Result<(),Error> {
+        self.send(Call::Shutdown).await
+    }
+
+    // resolves after the server has shutdown and drained its portals
+    pub async fn join(self) -> Result<(),Error> {
+        Ok(self.join.await?)
+    }
+}
+
+// performs the flavor, auth & info handshake over any reader/writer pair and
+// hands the resulting portal to the muxer.  PortalTcpServer uses it for tcp & tls,
+// but it works just as well for stdio, unix sockets or an in memory duplex
+#[derive(Clone)]
+pub struct PortalAcceptor {
+    server: Arc<dyn PortalServer>,
+    muxer: MuxerHandle,
+    broadcaster_tx: broadcast::Sender<Event>,
+    // shared by every portal so owner limits are counted across all of them
+    policy: Arc<Policy>,
+    // one permit per handshake in progress
+    pending: Arc<Semaphore>
+}
+
+impl PortalAcceptor {
+    pub fn new( server: Arc<dyn PortalServer>, muxer: MuxerHandle, broadcaster_tx: broadcast::Sender<Event> ) -> Self {
+        let pending = Arc::new(Semaphore::new(server.max_pending_handshakes()));
+        let policy = server.policy();
+        Self {
+            server,
+            muxer,
+            broadcaster_tx,
+            policy,
+            pending
+        }
+    }
 
-        // first verify flavor matches
+    pub async fn accept<R,W>( &self, reader: R, writer: W, peer: Option<PeerIdentity> ) -> Result<(),Error> where R: AsyncRead+Send+Unpin+'static, W: AsyncWrite+Send+Unpin+'static {
+        self.handshake(PrimitiveFrameReader::boxed(reader), PrimitiveFrameWriter::boxed(writer), peer).await
+    }
+
+    pub async fn handshake( &self, reader: PrimitiveFrameReader, writer: PrimitiveFrameWriter, peer: Option<PeerIdentity> ) -> Result<(),Error> {
+        self.handshake_with(async move { Ok((reader, writer, peer)) }).await
+    }
+
+    // connect is whatever must happen to a stream before the handshake proper (a tls handshake
+    // for instance), it counts against the same handshake_timeout and max_pending_handshakes
+    pub async fn handshake_with<F>( &self, connect: F ) -> Result<(),Error> where F: Future<Output=Result<(PrimitiveFrameReader, PrimitiveFrameWriter, Option<PeerIdentity>),Error>> {
+        let _permit = match self.pending.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => return Err(self.failed("ERROR: too many pending handshakes, connection refused".to_string()))
+        };
+
+        let timeout = self.server.handshake_timeout();
+        let handshake = async {
+            let (reader, writer, peer) = match connect.await {
+                Ok(connected) => connected,
+                Err(err) => return Err(self.failed(err.to_string()))
+            };
+            self.negotiate(reader, writer, peer).await
+        };
+        match tokio::time::timeout(timeout, handshake).await {
+            Ok(result) => result,
+            Err(_) => Err(self.failed(format!("ERROR: handshake did not complete within {} seconds", timeout.as_secs_f64())))
+        }
+    }
+
+    fn failed( &self, message: String ) -> Error {
+        handshake_failed();
+        (self.server.logger())(message.as_str());
+        self.broadcaster_tx.send( Event::HandshakeFailed(message.clone()) ).unwrap_or_default();
+        anyhow!(message)
+    }
+
+    async fn negotiate( &self, mut reader: PrimitiveFrameReader, mut writer: PrimitiveFrameWriter, peer: Option<PeerIdentity> ) -> Result<(),Error> {
+        metrics::global().counter("portal_connections_total", "Connections that started a portal handshake.", &[]).inc();
+        let flavor = match reader.read_string().await {
+            Ok(flavor) => flavor,
+            Err(err) => {
+                handshake_failed();
+                self.reject(&mut writer, &err).await;
+                return Err(err);
+            }
+        };
+
+        // first verify flavor matches, whichever wire version the client speaks along with it
+        let (flavor, version) = match split_flavor(flavor.as_str()) {
+            Ok(split) => split,
+            Err(err) => {
+                handshake_failed();
+                let message = format!("ERROR: {}", err);
+                writer.write_string(message.clone() ).await?;
+                self.broadcaster_tx.send( Event::FlavorNegotiation(EventResult::Err(message.clone()))).unwrap_or_default();
+                return Err(anyhow!(message));
+            }
+        };
         if flavor != self.server.flavor() {
+            handshake_failed();
             let message = format!("ERROR: flavor does not match.  expected '{}'", self.server.flavor() );
 
             writer.write_string(message.clone() ).await?;
@@ -187,15 +511,15 @@ impl PortalTcpServer {
         writer.write_string( "Ok".to_string() ).await?;
         tokio::time::sleep(Duration::from_secs(0)).await;
 
-        match self.server.auth(&mut reader, &mut writer).await
+        match self.server.auth(&mut reader, &mut writer, peer).await
         {
             Ok(user) => {
                 self.broadcaster_tx.send( Event::Authorization(EventResult::Ok(user.clone()))).unwrap_or_default();
                 tokio::time::sleep(Duration::from_secs(0)).await;
                 writer.write_string( "Ok".to_string() ).await?;
 
-                let mut reader : FrameReader<inlet::Frame> = FrameReader::new(reader );
-                let mut writer : FrameWriter<outlet::Frame>  = FrameWriter::new(writer );
+                let reader : FrameReader<inlet::Frame> = FrameReader::new_with_version(reader, version );
+                let mut writer : FrameWriter<outlet::Frame>  = FrameWriter::new_with_version(writer, version );
 
                 match self.server.info(user.clone() ).await {
                     Ok(info) => {
@@ -207,20 +531,37 @@ impl PortalTcpServer {
                         let (inlet_tx,inlet_rx) = mpsc::channel(128);
 
                         fn logger( log: Log ) {
-                            println!("{}", log.to_string() );
+                            println!("{}", log );
                         }
 
-                        let portal = Portal::new(info.clone(), outlet_tx, inlet_rx, logger );
+                        let portal = Portal::new(info.clone(), outlet_tx.clone(), inlet_rx, self.policy.clone(), logger ).with_version(version);
 
                         let mut reader = reader;
+                        reader.set_max_frame_size(info.config.max_bin_size as usize);
                         {
                             let logger = self.server.logger();
+                            let reporter = portal.reporter();
                             tokio::spawn(async move {
-                                while let Result::Ok(frame) = reader.read().await {
-                                    let result = inlet_tx.try_send(frame);
-                                    if result.is_err() {
-                                        (logger)("FATAL: cannot send frame to portal inlet_tx");
-                                        return;
+                                loop {
+                                    match reader.read().await {
+                                        Ok(frame) => {
+                                            let result = inlet_tx.try_send(frame);
+                                            if result.is_err() {
+                                                (logger)("FATAL: cannot send frame to portal inlet_tx");
+                                                return;
+                                            }
+                                        }
+                                        Err(err) => {
+                                            if let Option::Some(too_large) = err.downcast_ref::<FrameTooLarge>() {
+                                                (logger)(format!("ERROR: {}", too_large).as_str());
+                                                outlet_tx.send(outlet::Frame::Close(too_large.close_reason())).await.unwrap_or_default();
+                                            }
+                                            // an io error just means the client went away
+                                            if err.downcast_ref::<std::io::Error>().is_none() {
+                                                reporter.frame_error(err.to_string());
+                                            }
+                                            return;
+                                        }
                                     }
                                 }
                             });
@@ -231,34 +572,42 @@ impl PortalTcpServer {
                             let logger = self.server.logger();
                             tokio::spawn(async move {
                                 while let Option::Some(frame) = outlet_rx.recv().await {
+                                    let close = matches!(frame, outlet::Frame::Close(_));
                                     let result = writer.write(frame).await;
                                     if result.is_err() {
                                         (logger)("FATAL: cannot write to frame writer");
                                         return;
                                     }
+                                    if close {
+                                        return;
+                                    }
                                 }
                             });
                         }
 
-                        match self.mux_tx.send_timeout(MuxCall::Add(portal),Duration::from_secs(info.config.frame_timeout.clone()), ).await {
-                            Err(err) => {
-                                let message = err.to_string();
-                                (self.server.logger())(message.as_str());
-                                self.broadcaster_tx.send( Event::Info(EventResult::Err(message.clone()))).unwrap_or_default();
-                            }
-                            _ => {}
+                        if let Err(err) = self.muxer.add(portal).await {
+                            let message = err.to_string();
+                            (self.server.logger())(message.as_str());
+                            self.broadcaster_tx.send( Event::Info(EventResult::Err(message.clone()))).unwrap_or_default();
                         }
                     }
                     Err(err) => {
-                        let message = format!("ERROR: portal creation error: {}", err.to_string());
+                        handshake_failed();
+                        let message = format!("ERROR: portal creation error: {}", err);
                         (self.server.logger())(message.as_str());
                         self.broadcaster_tx.send( Event::Info(EventResult::Err(message.clone()))).unwrap_or_default();
                         writer.close( CloseReason::Error(message) ).await;
                     }
                 }
             }
+            Err(err) if err.downcast_ref::<FrameTooLarge>().is_some() => {
+                handshake_failed();
+                self.reject(&mut writer, &err).await;
+                return Err(err);
+            }
             Err(err) => {
-                let message = format!("ERROR: authorization failed: {}", err.to_string());
+                handshake_failed();
+                let message = format!("ERROR: authorization failed: {}", err);
                 (self.server.logger())(message.as_str());
                 self.broadcaster_tx.send( Event::Authorization(EventResult::Err(message.clone()))).unwrap_or_default();
                 writer.write_string( message ).await?;
@@ -266,6 +615,14 @@ impl PortalTcpServer {
         }
         Ok(())
     }
+
+    // an oversized frame means the stream can no longer be trusted, so the peer is told why and dropped
+    async fn reject( &self, writer: &mut PrimitiveFrameWriter, err: &Error ) {
+        if let Option::Some(too_large) = err.downcast_ref::<FrameTooLarge>() {
+            (self.server.logger())(format!("ERROR: {}", too_large).as_str());
+            writer.write_frame(outlet::Frame::Close(too_large.close_reason())).await.unwrap_or_default();
+        }
+    }
 }
 
 pub struct RouterProxy {
@@ -273,12 +630,39 @@ pub struct RouterProxy {
 }
 
 
+fn handshake_failed() {
+    metrics::global().counter("portal_handshake_failures_total", "Portal handshakes that ended without a portal.", &[]).inc();
+}
+
 #[async_trait]
 pub trait PortalServer: Sync+Send {
     fn flavor(&self) -> String;
-    async fn auth(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter) -> Result<String,Error>;
-    fn router_factory(&self, mux_tx: tokio::sync::mpsc::Sender<MuxCall> ) -> Box<dyn Router>;
+    async fn auth(&self, reader: &mut PrimitiveFrameReader, writer: &mut PrimitiveFrameWriter, peer: Option<PeerIdentity>) -> Result<String,Error>;
+    fn router_factory(&self, muxer: MuxerHandle ) -> Box<dyn Router>;
     fn logger(&self) -> fn(message: &str);
     async fn info(&self, user: String ) -> Result<Info,Error>;
+
+    fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    // flavor, auth & info must all be done within this
+    fn handshake_timeout(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn max_pending_handshakes(&self) -> usize {
+        64
+    }
+
+    // asked for once by the PortalAcceptor, every portal it accepts shares the one returned
+    fn policy(&self) -> Arc<Policy> {
+        Arc::new(Policy::allow_all())
+    }
+
+    // where each portal's own State is kept, a DirStateStore lets it outlive the server
+    fn state_store(&self) -> Arc<dyn StateStore> {
+        Arc::new(MemStateStore::new())
+    }
 }
 
@@ -0,0 +1,132 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use anyhow::Error;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+
+use resource_mesh_portal_serde::version::latest::resource::Status;
+
+use crate::{Call, Endpoint, Event, PortalServer, PortalTcpServer};
+
+#[derive(Debug, Clone)]
+pub struct UdsServerConfig {
+    pub path: PathBuf,
+    pub mode: u32,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+    pub allow_uids: Vec<u32>,
+    pub allow_gids: Vec<u32>
+}
+
+impl UdsServerConfig {
+    pub fn new( path: PathBuf ) -> Self {
+        Self {
+            path,
+            mode: 0o660,
+            owner: None,
+            group: None,
+            allow_uids: vec![],
+            allow_gids: vec![]
+        }
+    }
+
+    pub fn with_mode( mut self, mode: u32 ) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_owner( mut self, owner: Option<u32>, group: Option<u32> ) -> Self {
+        self.owner = owner;
+        self.group = group;
+        self
+    }
+
+    pub fn allow_uid( mut self, uid: u32 ) -> Self {
+        self.allow_uids.push(uid);
+        self
+    }
+
+    pub fn allow_gid( mut self, gid: u32 ) -> Self {
+        self.allow_gids.push(gid);
+        self
+    }
+
+    pub(crate) fn secure(&self) -> Result<(),Error> {
+        std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(self.mode))?;
+        if self.owner.is_some() || self.group.is_some() {
+            std::os::unix::fs::chown(&self.path, self.owner, self.group)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn authorize(&self, stream: &UnixStream) -> Result<(),Error> {
+        if self.allow_uids.is_empty() && self.allow_gids.is_empty() {
+            return Ok(());
+        }
+        let cred = stream.peer_cred()?;
+        if self.allow_uids.contains(&cred.uid()) || self.allow_gids.contains(&cred.gid()) {
+            Ok(())
+        } else {
+            Err(anyhow!("peer uid '{}' gid '{}' is not allowed to connect", cred.uid(), cred.gid()))
+        }
+    }
+}
+
+pub struct PortalUdsServer {}
+
+impl PortalUdsServer {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(config: UdsServerConfig, server: Box<dyn PortalServer>) -> mpsc::Sender<Call> {
+        PortalTcpServer::create(Endpoint::Uds(config), server, Option::None)
+    }
+}
+
+impl PortalTcpServer {
+    pub(crate) async fn start_uds(self, config: UdsServerConfig) {
+        if config.path.exists() {
+            std::fs::remove_file(&config.path).unwrap_or_default();
+        }
+
+        let listener = match UnixListener::bind(&config.path).map_err(|err| anyhow!(err)).and_then(|listener| {
+            config.secure()?;
+            Ok(listener)
+        }) {
+            Ok(listener) => listener,
+            Err(error) => {
+                let message = format!("FATAL: could not setup UnixListener {}", error);
+                (self.server.logger())(message.as_str());
+                self.report(Status::Panic(message));
+                return;
+            }
+        };
+
+        self.report(Status::Ready);
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => break
+                },
+                _ = self.stop.notified() => {
+                    (self.server.logger())("server reached final shutdown");
+                    break;
+                }
+            };
+            self.broadcaster_tx.send( Event::ClientConnected ).unwrap_or_default();
+            if let Err(err) = config.authorize(&stream) {
+                let message = format!("ERROR: unix socket peer rejected: {}", err);
+                (self.server.logger())(message.as_str());
+                continue;
+            }
+            let (reader, writer) = stream.into_split();
+            let acceptor = self.acceptor.clone();
+            tokio::spawn(async move {
+                acceptor.accept(reader, writer, Option::None).await.unwrap_or_default();
+            });
+        }
+        drop(listener);
+        std::fs::remove_file(&config.path).unwrap_or_default();
+        self.drain().await;
+    }
+}